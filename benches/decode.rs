@@ -0,0 +1,41 @@
+//! Decoding a large pipelined response, which is mostly sizes and numbers. Compare the scalar scanning with the
+//! `simd` feature by running `cargo bench --bench decode` and `cargo bench --bench decode --features simd`
+
+use {
+    criterion::{black_box, criterion_group, criterion_main, Criterion},
+    skytable::proto::PipelineDecoder,
+};
+
+const QUERIES: usize = 100;
+const ROWS: usize = 100;
+
+/// `QUERIES` responses of `ROWS` rows with a username, a follower count, a signed balance and a list of tags
+fn pipeline_response() -> Vec<u8> {
+    let mut response = vec![];
+    for q in 0..QUERIES {
+        response.extend(format!("\x13{}\n4\n", ROWS).bytes());
+        for i in 0..ROWS {
+            let username = format!("user-{:08}", q * ROWS + i);
+            response.extend(format!("\x0D{}\n{}", username.len(), username).bytes());
+            response.extend(format!("\x05{}\n", (i as u64) * 1_000_000_007).bytes());
+            response.extend(format!("\x09{}\n", -(i as i64) * 1_000_000_007).bytes());
+            response.extend(b"\x0E2\n\x0D4\nrust\x0D8\nskytable");
+        }
+    }
+    response
+}
+
+fn decode_pipeline(c: &mut Criterion) {
+    let response = pipeline_response();
+    let mut group = c.benchmark_group("decode_pipeline_100x100_rows");
+    group.bench_function("feed", |b| {
+        b.iter(|| {
+            let mut decoder = PipelineDecoder::new(QUERIES);
+            black_box(decoder.feed(&response).unwrap().unwrap())
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, decode_pipeline);
+criterion_main!(benches);
@@ -0,0 +1,68 @@
+//! Building large queries and pipelines, with and without capacity hints. Run with `cargo bench`
+
+use {
+    criterion::{black_box, criterion_group, criterion_main, Criterion},
+    skytable::{query, Pipeline, Query},
+};
+
+const PARAMS: usize = 10_000;
+const QUERY: &str = "insert into myspace.mymodel(?)";
+
+fn keys() -> Vec<String> {
+    (0..PARAMS).map(|i| format!("user-{:08}", i)).collect()
+}
+
+fn build_query(c: &mut Criterion) {
+    let keys = keys();
+    let bytes = keys.iter().map(String::len).sum();
+    let mut group = c.benchmark_group("query_10k_params");
+    group.bench_function("new", |b| {
+        b.iter(|| {
+            let mut q = Query::new(QUERY);
+            for key in &keys {
+                q.push_param(key);
+            }
+            black_box(q)
+        })
+    });
+    group.bench_function("with_capacity", |b| {
+        b.iter(|| {
+            let mut q = Query::with_capacity(QUERY, keys.len(), bytes);
+            for key in &keys {
+                q.push_param(key);
+            }
+            black_box(q)
+        })
+    });
+    group.finish();
+}
+
+fn build_pipeline(c: &mut Criterion) {
+    let queries: Vec<Query> = keys()
+        .iter()
+        .map(|key| query!("select * from myspace.mymodel where username = ?", key))
+        .collect();
+    let bytes = queries
+        .iter()
+        .map(|q| q.query_str().len() + q.params().map(<[u8]>::len).sum::<usize>() + 4)
+        .sum();
+    let mut group = c.benchmark_group("pipeline_10k_queries");
+    group.bench_function("new", |b| {
+        b.iter(|| {
+            let mut p = Pipeline::new();
+            queries.iter().for_each(|q| p.push(q));
+            black_box(p)
+        })
+    });
+    group.bench_function("with_capacity", |b| {
+        b.iter(|| {
+            let mut p = Pipeline::with_capacity(queries.len(), bytes);
+            queries.iter().for_each(|q| p.push(q));
+            black_box(p)
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, build_query, build_pipeline);
+criterion_main!(benches);
@@ -0,0 +1,136 @@
+//! Decoding responses into a new `Response` and into a reused `ResponseBuf`, along with the number of allocations per
+//! query. Run with `cargo bench --bench response`
+
+use {
+    criterion::{black_box, criterion_group, criterion_main, Criterion},
+    skytable::{query, response::buf::ResponseBuf, syncio::SyncSocket, Query},
+    std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+        io::{self, Read, Write},
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    },
+};
+
+const ROWS: usize = 100;
+
+/// Counts the allocations made by the benchmarks
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// A stream that answers every query with the same response
+struct Replay {
+    response: Vec<u8>,
+    cursor: usize,
+    nonblocking: Cell<bool>,
+}
+
+impl Read for Replay {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.nonblocking.get() {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        let remaining = &self.response[self.cursor..];
+        let n = remaining.len().min(buf.len());
+        buf[..n].copy_from_slice(&remaining[..n]);
+        self.cursor += n;
+        Ok(n)
+    }
+}
+
+impl Write for Replay {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.cursor = 0;
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SyncSocket for Replay {
+    fn set_read_timeout(&self, _: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+    fn set_write_timeout(&self, _: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.nonblocking.set(nonblocking);
+        Ok(())
+    }
+}
+
+/// `ROWS` rows of a username, a follower count and a binary tag
+fn rows() -> Vec<u8> {
+    let mut response = format!("\x13{}\n3\n", ROWS).into_bytes();
+    for i in 0..ROWS {
+        let username = format!("user-{:08}", i);
+        response.extend(format!("\x0D{}\n{}", username.len(), username).bytes());
+        response.extend(format!("\x05{}\n", i * 1000).bytes());
+        response.extend_from_slice(b"\x0C4\n\xca\xfe\xba\xbe");
+    }
+    response
+}
+
+fn connection() -> skytable::syncio::TcpConnection<Replay> {
+    skytable::syncio::TcpConnection::from_raw_stream(Replay {
+        response: rows(),
+        cursor: 0,
+        nonblocking: Cell::new(false),
+    })
+}
+
+/// The average number of allocations made by `f`
+fn allocations(mut f: impl FnMut()) -> f64 {
+    const RUNS: usize = 1000;
+    // warm up (so that reused buffers have grown)
+    f();
+    let start = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..RUNS {
+        f();
+    }
+    (ALLOCATIONS.load(Ordering::Relaxed) - start) as f64 / RUNS as f64
+}
+
+fn decode_rows(c: &mut Criterion) {
+    let q: Query = query!("select * from myspace.mymodel limit ?", ROWS as u64);
+    let mut con = connection();
+    let mut buf = ResponseBuf::new();
+    println!(
+        "allocations per query: query = {}, query_into = {}",
+        allocations(|| drop(black_box(con.query(&q).unwrap()))),
+        allocations(|| con.query_into(&q, &mut buf).unwrap()),
+    );
+    let mut group = c.benchmark_group("decode_100_rows");
+    group.bench_function("query", |b| b.iter(|| black_box(con.query(&q).unwrap())));
+    group.bench_function("query_into", |b| {
+        b.iter(|| {
+            con.query_into(&q, &mut buf).unwrap();
+            black_box(buf.response());
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, decode_rows);
+criterion_main!(benches);
@@ -28,9 +28,97 @@
 //!
 //! // establish a connection to a specific host `subnetx2_db1` and port `2008`
 //! let mut db = Config::new("subnetx2_db1", 2008, "username", "password").connect().unwrap();
+//!
+//! // establish a connection to an address you already have
+//! let addr: std::net::SocketAddr = "10.0.0.2:2003".parse().unwrap();
+//! let mut db = Config::new_addr(addr, "username", "password").connect().unwrap();
+//! ```
+//!
+//! ## Failover
+//!
+//! Use [`Config::add_fallback`] to add endpoints (such as a warm standby) that are tried in order if the server can't be
+//! reached. The endpoint that last worked is tried first the next time a connection is established (this is shared by
+//! clones of the configuration, so it also applies to pools and to re-established connections):
+//!
+//! ```no_run
+//! use skytable::Config;
+//!
+//! let cfg = Config::new("primary.example.com", 2003, "username", "password")
+//!     .add_fallback("standby.example.com", 2003);
+//! let mut db = cfg.connect().unwrap();
+//! ```
+//!
+//! This is not clustering: queries are only ever sent to the one endpoint that the connection was established to.
+//!
+//! ## Wire dumps
+//!
+//! To track down protocol issues, connections can dump every packet that they write and every buffer that they read as
+//! hex, 16 bytes per line with their offsets. Enable this with [`Config::with_wire_debug`] (or for every connection,
+//! by setting the [`WIRE_DEBUG_ENV`] environment variable to `1`). The dumps are emitted as `TRACE` events with the
+//! `skytable::wire` target if the `tracing` feature is enabled, and are written to the standard error otherwise. Use
+//! [`Config::with_wire_debug_sink`] to write them somewhere else:
+//!
+//! ```no_run
+//! use {skytable::Config, std::fs::File};
+//!
+//! let dump = File::create("skytable-wire.log").unwrap();
+//! let mut db = Config::new_default("username", "password")
+//!     .with_wire_debug_sink(dump)
+//!     .connect()
+//!     .unwrap();
+//! ```
+//!
+//! **Warning**: the dumps contain the queries, their parameters and the responses exactly as they were sent, so they
+//! will contain whatever secrets your data has (they aren't affected by [log redaction](crate::query::LogRedaction)).
+//! Dumps start after the handshake, so the credentials used to connect are never dumped.
+//!
+//! ## TLS
+//!
+//! Use a [`TlsConfig`] to choose which certificate authorities are trusted by TLS connections. Certificates are parsed
+//! when the [`TlsConfig`] is built, so a bad certificate is reported right away (and not when connecting):
+//!
+//! ```no_run
+//! use skytable::config::{CaSource, Config, TlsConfig};
+//!
+//! let pem = std::env::var("SKYTABLE_CA").unwrap();
+//! // only trust our own CA
+//! let tls = TlsConfig::builder().ca(CaSource::Pem(pem.into_bytes())).build().unwrap();
+//! let mut db = Config::new("db.example.com", 2002, "username", "password").connect_tls_with(&tls).unwrap();
 //! ```
 
-use crate::protocol::handshake::ProtocolVersion;
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+use {
+    crate::error::EndpointError,
+    std::sync::atomic::{AtomicUsize, Ordering},
+};
+use {
+    crate::{
+        error::{ClientResult, ConnectionSetupError, DEFAULT_RESPONSE_CAPTURE_LEN},
+        protocol::handshake::ProtocolVersion,
+    },
+    core::fmt,
+    native_tls::{Certificate, TlsConnector},
+    std::{
+        env,
+        io::Write,
+        net::SocketAddr,
+        path::PathBuf,
+        sync::{Arc, Mutex},
+        time::Duration,
+    },
+};
+
+#[cfg(feature = "zeroize")]
+/// Secrets are wiped from memory when they are dropped
+type Secret = zeroize::Zeroizing<Box<str>>;
+#[cfg(not(feature = "zeroize"))]
+type Secret = Box<str>;
+
+/// Returns the username and password to use for a new connection
+type CredentialsProvider = Arc<dyn Fn() -> (String, String) + Send + Sync>;
+
+/// Where wire dumps are written to (see [`Config::with_wire_debug_sink`])
+pub(crate) type WireSink = Arc<Mutex<dyn Write + Send>>;
 
 /// The default host
 ///
@@ -40,17 +128,85 @@ pub const DEFAULT_HOST: &str = "127.0.0.1";
 pub const DEFAULT_TCP_PORT: u16 = 2003;
 /// The default TLS port (skyhash/tls)
 pub const DEFAULT_TLS_PORT: u16 = 2002;
+/// The default delay before trying the next address if the host resolves to multiple addresses
+pub const DEFAULT_CONNECT_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+/// The default prefix of the metric names (see [`Config::with_metrics_prefix`])
+pub const DEFAULT_METRICS_PREFIX: &str = "skytable_client";
+/// Setting this environment variable to `1` enables wire dumps for every connection (see [`Config::with_wire_debug`])
+pub const WIRE_DEBUG_ENV: &str = "SKYTABLE_CLIENT_WIRE_DEBUG";
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Clone)]
 /// Configuration for a Skytable connection
 pub struct Config {
     host: Box<str>,
     port: u16,
     username: Box<str>,
-    password: Box<str>,
+    password: Secret,
+    credentials: Option<CredentialsProvider>,
+    addr: Option<SocketAddr>,
+    connect_attempt_delay: Duration,
+    space: Option<Box<str>>,
+    fallbacks: Vec<(Box<str>, u16)>,
+    /// the endpoint that a connection was last established to (`0` is the primary)
+    #[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+    last_good: Arc<AtomicUsize>,
+    metrics_prefix: Box<str>,
+    wire_debug: bool,
+    wire_sink: Option<WireSink>,
+    response_capture_len: usize,
     pub(crate) protocol: ProtocolVersion,
 }
 
+impl PartialEq for Config {
+    fn eq(&self, other: &Self) -> bool {
+        let same_provider = match (&self.credentials, &other.credentials) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+        let same_sink = match (&self.wire_sink, &other.wire_sink) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+        self.host == other.host
+            && self.port == other.port
+            && self.username == other.username
+            && self.password == other.password
+            && same_provider
+            && self.addr == other.addr
+            && self.connect_attempt_delay == other.connect_attempt_delay
+            && self.space == other.space
+            && self.fallbacks == other.fallbacks
+            && self.metrics_prefix == other.metrics_prefix
+            && self.wire_debug == other.wire_debug
+            && same_sink
+            && self.response_capture_len == other.response_capture_len
+            && self.protocol == other.protocol
+    }
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &self.password)
+            .field("credentials_provider", &self.credentials.is_some())
+            .field("addr", &self.addr)
+            .field("connect_attempt_delay", &self.connect_attempt_delay)
+            .field("space", &self.space)
+            .field("fallbacks", &self.fallbacks)
+            .field("metrics_prefix", &self.metrics_prefix)
+            .field("wire_debug", &self.wire_debug)
+            .field("wire_sink", &self.wire_sink.is_some())
+            .field("response_capture_len", &self.response_capture_len)
+            .field("protocol", &self.protocol)
+            .finish()
+    }
+}
+
 impl Config {
     fn _new(
         host: Box<str>,
@@ -63,7 +219,18 @@ impl Config {
             host,
             port,
             username,
-            password,
+            password: Secret::from(password),
+            credentials: None,
+            addr: None,
+            connect_attempt_delay: DEFAULT_CONNECT_ATTEMPT_DELAY,
+            space: None,
+            fallbacks: Vec::new(),
+            #[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+            last_good: Arc::new(AtomicUsize::new(0)),
+            metrics_prefix: DEFAULT_METRICS_PREFIX.into(),
+            wire_debug: false,
+            wire_sink: None,
+            response_capture_len: DEFAULT_RESPONSE_CAPTURE_LEN,
             protocol,
         }
     }
@@ -83,6 +250,232 @@ impl Config {
             ProtocolVersion::V2_0,
         )
     }
+    /// Create a new [`Config`] that connects to an already resolved address (for example, one obtained from service
+    /// discovery) using the provided username and password.
+    ///
+    /// The address is used as is, so IPv6 addresses with scope IDs are supported. The host is set to the IP address.
+    pub fn new_addr(addr: impl Into<SocketAddr>, username: &str, password: &str) -> Self {
+        let addr = addr.into();
+        let mut cfg = Self::new(&addr.ip().to_string(), addr.port(), username, password);
+        cfg.addr = Some(addr);
+        cfg
+    }
+    /// Set the username and password for this configuration. This is useful along with [`Config::default`]:
+    ///
+    /// ```no_run
+    /// use skytable::Config;
+    ///
+    /// let mut db = Config::default().with_credentials("root", "password").connect().unwrap();
+    /// ```
+    pub fn with_credentials(mut self, username: &str, password: &str) -> Self {
+        self.username = username.into();
+        self.password = Secret::from(Box::<str>::from(password));
+        self
+    }
+    /// Get the username and password from `provider` every time a connection is established (or re-established),
+    /// instead of using the username and password set in this configuration. This is useful when credentials (such
+    /// as tokens) are rotated while connections and pools are still in use.
+    ///
+    /// ```no_run
+    /// use skytable::Config;
+    ///
+    /// fn current_token() -> String {
+    ///     std::fs::read_to_string("/run/secrets/skytable-token").unwrap()
+    /// }
+    ///
+    /// let cfg = Config::default().credentials_provider(|| ("root".to_owned(), current_token()));
+    /// let mut db = cfg.connect().unwrap();
+    /// ```
+    pub fn credentials_provider(
+        mut self,
+        provider: impl Fn() -> (String, String) + Send + Sync + 'static,
+    ) -> Self {
+        self.credentials = Some(Arc::new(provider));
+        self
+    }
+    /// Call `f` with the username and password to use for a new connection
+    #[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+    pub(crate) fn with_current_credentials<T>(&self, f: impl FnOnce(&str, &str) -> T) -> T {
+        match self.credentials {
+            Some(ref provider) => {
+                let (username, password) = provider();
+                let password = Secret::from(password.into_boxed_str());
+                f(&username, &password)
+            }
+            None => f(&self.username, &self.password),
+        }
+    }
+    /// Set how long to wait for a connection attempt before also trying the next address, if the host resolves to
+    /// multiple addresses (for example, on a dual-stack host). Defaults to [`DEFAULT_CONNECT_ATTEMPT_DELAY`].
+    ///
+    /// The attempts are started in the order the addresses were resolved (alternating between IPv6 and IPv4) and the
+    /// first connection that is established is used, so a blackholed route doesn't hold up the connection for the
+    /// entire connect timeout.
+    pub fn with_connect_attempt_delay(mut self, delay: Duration) -> Self {
+        self.connect_attempt_delay = delay;
+        self
+    }
+    /// Returns the delay before trying the next address (see [`Config::with_connect_attempt_delay`])
+    pub fn connect_attempt_delay(&self) -> Duration {
+        self.connect_attempt_delay
+    }
+    /// Switch every connection to `space` as soon as it is established (or re-established), so that queries can use
+    /// model names without the space. Connection pools also use this to make sure that a connection is back in this
+    /// space before it is reused (see [`pool`](crate::pool)).
+    pub fn with_space(mut self, space: &str) -> Self {
+        self.space = Some(space.into());
+        self
+    }
+    /// Returns the space that connections are switched to (see [`Config::with_space`])
+    pub fn space(&self) -> Option<&str> {
+        self.space.as_deref()
+    }
+    /// Set the prefix of the names of the metrics that connections emit (if the `metrics` feature is enabled).
+    /// Defaults to [`DEFAULT_METRICS_PREFIX`], which gives these metrics:
+    ///
+    /// - `skytable_client_queries_total`: a counter of queries, with an `outcome` label that is `ok`, `server_error`
+    ///   (the server responded with an error) or `error` (the query failed, for example with an I/O error)
+    /// - `skytable_client_query_duration_seconds`: a histogram of the time taken by each query (or pipeline)
+    /// - `skytable_client_bytes_read_total` and `skytable_client_bytes_written_total`: counters of the bytes read and
+    ///   written by connections
+    /// - `skytable_client_reconnects_total`: a counter of sync connections that were re-established
+    ///
+    /// The metrics are registered with the recorder that is installed when the connection is established, so install
+    /// the recorder before connecting.
+    pub fn with_metrics_prefix(mut self, prefix: &str) -> Self {
+        self.metrics_prefix = prefix.into();
+        self
+    }
+    /// Returns the prefix of the metric names (see [`Config::with_metrics_prefix`])
+    pub fn metrics_prefix(&self) -> &str {
+        &self.metrics_prefix
+    }
+    /// Dump every packet that connections write and every buffer that they read (see
+    /// [wire dumps](crate::config#wire-dumps)). This is meant for debugging: **the dumps contain all the data that is
+    /// sent and received, including any secrets in it**.
+    pub fn with_wire_debug(mut self, enabled: bool) -> Self {
+        self.wire_debug = enabled;
+        self
+    }
+    /// Write wire dumps to `sink` instead of emitting them as `tracing` events (or writing them to the standard error).
+    /// This also enables wire dumps (see [`Config::with_wire_debug`]), and the sink is shared by every connection that
+    /// is established with this configuration (or its clones).
+    pub fn with_wire_debug_sink(mut self, sink: impl Write + Send + 'static) -> Self {
+        self.wire_debug = true;
+        self.wire_sink = Some(Arc::new(Mutex::new(sink)));
+        self
+    }
+    /// Returns true if connections established with this configuration dump their wire traffic, either because it
+    /// was enabled with [`Config::with_wire_debug`] or because [`WIRE_DEBUG_ENV`] is set to `1`
+    pub fn wire_debug(&self) -> bool {
+        self.wire_debug || env::var_os(WIRE_DEBUG_ENV).is_some_and(|v| v == "1")
+    }
+    /// Set the number of bytes that are kept from the start of a response that couldn't be decoded, and returned along
+    /// with the protocol error (see [`CapturedResponse`](crate::error::CapturedResponse)). Defaults to
+    /// [`DEFAULT_RESPONSE_CAPTURE_LEN`], and `0` disables the capture
+    pub fn with_response_capture_len(mut self, len: usize) -> Self {
+        self.response_capture_len = len;
+        self
+    }
+    /// Returns the number of bytes that are kept from the start of a response that couldn't be decoded (see
+    /// [`Config::with_response_capture_len`])
+    pub fn response_capture_len(&self) -> usize {
+        self.response_capture_len
+    }
+    /// Returns the sink that wire dumps are written to, if one was set
+    #[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+    pub(crate) fn wire_sink(&self) -> Option<&WireSink> {
+        self.wire_sink.as_ref()
+    }
+    /// Add an endpoint to try if the server (and any fallbacks added before this one) can't be reached. Endpoints are
+    /// tried in the order they were added, except that the endpoint that a connection was last established to is
+    /// tried first (see [failover](crate::config#failover)).
+    ///
+    /// If no endpoint can be reached, the error is a [`ConnectionSetupError::AllEndpointsFailed`] with the error for
+    /// every endpoint.
+    pub fn add_fallback(mut self, host: &str, port: u16) -> Self {
+        self.fallbacks.push((host.into(), port));
+        self
+    }
+    /// Returns the fallback endpoints (see [`Config::add_fallback`])
+    pub fn fallbacks(&self) -> impl Iterator<Item = (&str, u16)> {
+        self.fallbacks
+            .iter()
+            .map(|(host, port)| (host.as_ref(), *port))
+    }
+    #[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+    /// Returns the configuration for endpoint `i` (`0` is the primary)
+    fn endpoint(&self, i: usize) -> Config {
+        let mut cfg = self.clone();
+        if i != 0 {
+            let (host, port) = &self.fallbacks[i - 1];
+            cfg.host = host.clone();
+            cfg.port = *port;
+            cfg.addr = None;
+        }
+        cfg
+    }
+    #[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+    /// Returns the endpoints in the order they should be tried: the one that last worked, and then the rest in the
+    /// order they were added
+    pub(crate) fn endpoint_order(&self) -> impl Iterator<Item = usize> {
+        let count = self.fallbacks.len() + 1;
+        let first = match self.last_good.load(Ordering::Relaxed) {
+            i if i < count => i,
+            _ => 0,
+        };
+        core::iter::once(first).chain((0..count).filter(move |&i| i != first))
+    }
+    #[cfg(feature = "sync")]
+    /// Run `connect` for every endpoint until it succeeds
+    pub(crate) fn connect_endpoints<T>(
+        &self,
+        connect: impl Fn(&Config) -> ClientResult<T>,
+    ) -> ClientResult<T> {
+        if self.fallbacks.is_empty() {
+            return connect(self);
+        }
+        let mut errors = Vec::new();
+        for i in self.endpoint_order() {
+            let cfg = self.endpoint(i);
+            match connect(&cfg) {
+                Ok(con) => {
+                    self.last_good.store(i, Ordering::Relaxed);
+                    return Ok(con);
+                }
+                Err(e) => errors.push(EndpointError::new(cfg.host, cfg.port, e)),
+            }
+        }
+        Err(ConnectionSetupError::AllEndpointsFailed(errors).into())
+    }
+    #[cfg(any(feature = "aio", feature = "async-std"))]
+    /// Run `connect` for every endpoint until it succeeds
+    pub(crate) async fn connect_endpoints_async<T, F, Fut>(&self, connect: F) -> ClientResult<T>
+    where
+        F: Fn(Config) -> Fut,
+        Fut: core::future::Future<Output = ClientResult<T>>,
+    {
+        if self.fallbacks.is_empty() {
+            return connect(self.clone()).await;
+        }
+        let mut errors = Vec::new();
+        for i in self.endpoint_order() {
+            let cfg = self.endpoint(i);
+            let endpoint = (cfg.host.clone(), cfg.port);
+            match connect(cfg).await {
+                Ok(con) => {
+                    self.last_good.store(i, Ordering::Relaxed);
+                    return Ok(con);
+                }
+                Err(e) => errors.push(EndpointError::new(endpoint.0, endpoint.1, e)),
+            }
+        }
+        Err(ConnectionSetupError::AllEndpointsFailed(errors).into())
+    }
+    /// Returns the address setting for this configuration, if it was created with [`Config::new_addr`]
+    pub fn addr(&self) -> Option<SocketAddr> {
+        self.addr
+    }
     /// Returns the host setting for this this configuration
     pub fn host(&self) -> &str {
         self.host.as_ref()
@@ -91,12 +484,236 @@ impl Config {
     pub fn port(&self) -> u16 {
         self.port
     }
-    /// Returns the username setting for this this configuration
+    /// Returns the username setting for this this configuration (this is not used if a
+    /// [credentials provider](Config::credentials_provider) is set)
     pub fn username(&self) -> &str {
         self.username.as_ref()
     }
-    /// Returns the password set using this configuration
+    /// Returns the password set using this configuration (this is not used if a
+    /// [credentials provider](Config::credentials_provider) is set)
     pub fn password(&self) -> &str {
-        self.password.as_ref()
+        &self.password
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A source of trusted certificate authorities for TLS connections (see [`TlsConfig`])
+pub enum CaSource {
+    /// A PEM encoded certificate, read from a file
+    File(PathBuf),
+    /// A PEM encoded certificate
+    Pem(Vec<u8>),
+    /// A DER encoded certificate
+    Der(Vec<u8>),
+    /// The certificate authorities trusted by the operating system
+    SystemRoots,
+}
+
+#[derive(Debug, Clone, Default)]
+/// A builder for [`TlsConfig`]
+pub struct TlsConfigBuilder {
+    sources: Vec<CaSource>,
+    accept_invalid_hostnames: bool,
+}
+
+impl TlsConfigBuilder {
+    /// Trust the certificate authorities from `source`. This can be called multiple times to trust multiple sources
+    /// (for example, a pinned CA along with [`CaSource::SystemRoots`])
+    pub fn ca(mut self, source: CaSource) -> Self {
+        self.sources.push(source);
+        self
+    }
+    /// Accept certificates that aren't valid for the host that is connected to (they must still be signed by a trusted
+    /// certificate authority). Defaults to `false`.
+    ///
+    /// **This is dangerous**: anyone with a certificate from a trusted authority can impersonate the server. It is
+    /// only meant for servers with a pinned, self-signed certificate that doesn't name the host.
+    pub fn danger_accept_invalid_hostnames(mut self, accept: bool) -> Self {
+        self.accept_invalid_hostnames = accept;
+        self
+    }
+    /// Parse the certificates and build the [`TlsConfig`]. This fails if a certificate can't be read or parsed
+    pub fn build(self) -> ClientResult<TlsConfig> {
+        let mut builder = TlsConnector::builder();
+        builder
+            .disable_built_in_roots(!self.sources.contains(&CaSource::SystemRoots))
+            .danger_accept_invalid_hostnames(self.accept_invalid_hostnames);
+        for source in self.sources.iter() {
+            let cert = match source {
+                CaSource::File(path) => {
+                    let pem = std::fs::read(path).map_err(|e| {
+                        ConnectionSetupError::Other(format!(
+                            "failed to read certificate from {}: {e}",
+                            path.display()
+                        ))
+                    })?;
+                    Certificate::from_pem(&pem)
+                }
+                CaSource::Pem(pem) => Certificate::from_pem(pem),
+                CaSource::Der(der) => Certificate::from_der(der),
+                CaSource::SystemRoots => continue,
+            }
+            .map_err(|e| {
+                ConnectionSetupError::Other(format!("failed to parse certificate: {e}"))
+            })?;
+            builder.add_root_certificate(cert);
+        }
+        let connector = builder.build().map_err(|e| {
+            ConnectionSetupError::Other(format!("failed to set up TLS connector: {e}"))
+        })?;
+        Ok(TlsConfig {
+            sources: self.sources,
+            accept_invalid_hostnames: self.accept_invalid_hostnames,
+            connector,
+        })
+    }
+}
+
+#[derive(Clone)]
+/// The TLS settings for a connection, built with [`TlsConfig::builder`]
+pub struct TlsConfig {
+    sources: Vec<CaSource>,
+    accept_invalid_hostnames: bool,
+    #[cfg_attr(not(any(feature = "sync", feature = "aio")), allow(dead_code))]
+    connector: TlsConnector,
+}
+
+impl TlsConfig {
+    /// Returns a builder that doesn't trust any certificate authorities yet
+    pub fn builder() -> TlsConfigBuilder {
+        TlsConfigBuilder::default()
+    }
+    /// The settings used for a PEM certificate passed as a string (which is also trusted along with the system roots).
+    /// Like before [`TlsConfig`] was added, the certificate doesn't have to name the host
+    #[cfg_attr(not(any(feature = "sync", feature = "aio")), allow(dead_code))]
+    pub(crate) fn from_pem_str(pem: &str) -> ClientResult<Self> {
+        Self::builder()
+            .ca(CaSource::Pem(pem.as_bytes().to_vec()))
+            .ca(CaSource::SystemRoots)
+            .danger_accept_invalid_hostnames(true)
+            .build()
+    }
+    /// Returns the sources of the trusted certificate authorities
+    pub fn sources(&self) -> &[CaSource] {
+        &self.sources
+    }
+    /// Returns true if certificates that aren't valid for the host are accepted (see
+    /// [`TlsConfigBuilder::danger_accept_invalid_hostnames`])
+    pub fn accepts_invalid_hostnames(&self) -> bool {
+        self.accept_invalid_hostnames
+    }
+    #[cfg_attr(not(any(feature = "sync", feature = "aio")), allow(dead_code))]
+    pub(crate) fn connector(&self) -> &TlsConnector {
+        &self.connector
+    }
+}
+
+impl PartialEq for TlsConfig {
+    fn eq(&self, other: &Self) -> bool {
+        self.sources == other.sources
+            && self.accept_invalid_hostnames == other.accept_invalid_hostnames
+    }
+}
+
+impl fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsConfig")
+            .field("sources", &self.sources)
+            .field("accept_invalid_hostnames", &self.accept_invalid_hostnames)
+            .finish()
+    }
+}
+
+impl Default for Config {
+    /// Returns a [`Config`] for [`DEFAULT_HOST`] and [`DEFAULT_TCP_PORT`] with empty credentials. Use
+    /// [`Config::with_credentials`] to set the username and password.
+    fn default() -> Self {
+        Self::new(DEFAULT_HOST, DEFAULT_TCP_PORT, "", "")
+    }
+}
+
+#[test]
+fn default_config() {
+    let cfg = Config::default();
+    assert_eq!(cfg.host(), DEFAULT_HOST);
+    assert_eq!(cfg.port(), DEFAULT_TCP_PORT);
+    assert_eq!(cfg.username(), "");
+    assert_eq!(cfg.password(), "");
+    assert_eq!(
+        cfg.with_credentials("username", "password"),
+        Config::new_default("username", "password")
+    );
+}
+
+#[test]
+fn new_addr_v6_scope_id() {
+    use std::net::{Ipv6Addr, SocketAddrV6};
+    let addr = SocketAddrV6::new(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1), 2003, 0, 2);
+    let cfg = Config::new_addr(addr, "username", "password");
+    assert_eq!(cfg.addr(), Some(SocketAddr::V6(addr)));
+    assert_eq!(cfg.host(), "fe80::1");
+    assert_eq!(cfg.port(), 2003);
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn password_is_zeroizing() {
+    let cfg = Config::new_default("username", "password");
+    let password: &zeroize::Zeroizing<Box<str>> = &cfg.password;
+    assert_eq!(&***password, "password");
+    let clone = cfg.clone();
+    drop(cfg);
+    assert_eq!(clone.password(), "password");
+}
+
+#[cfg(test)]
+const TEST_CA: &str = "-----BEGIN CERTIFICATE-----
+MIIBjDCCATOgAwIBAgIURz1nse7mHkW7svf2tGhHFWjK2x0wCgYIKoZIzj0EAwIw
+GzEZMBcGA1UEAwwQc2t5dGFibGUtdGVzdC1jYTAgFw0yNjEwMTYxMTA5MzFaGA8y
+MTI2MDkyMjExMDkzMVowGzEZMBcGA1UEAwwQc2t5dGFibGUtdGVzdC1jYTBZMBMG
+ByqGSM49AgEGCCqGSM49AwEHA0IABOC6ixVRPxApJIzMSJ1wkxaxuBkZF6FCDJim
+DQKZrLkPjkADtagWXKQbo7KdOx87Gj224gLxY8LqeRFG26n+wk6jUzBRMB0GA1Ud
+DgQWBBQUXFk38ybEpHs8nmzfm7S979ikSTAfBgNVHSMEGDAWgBQUXFk38ybEpHs8
+nmzfm7S979ikSTAPBgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0cAMEQCIHdM
+j4bjfV19YnSgSeORN93nwaI/t42hgRcfKdkXNjYDAiAG25oUGeqPAoWy6Wqtl5m/
+ST9sEUJP9X8GFAw5b210Ug==
+-----END CERTIFICATE-----
+";
+
+#[test]
+fn tls_config_sources() {
+    let pem = TEST_CA.as_bytes().to_vec();
+    let der = Certificate::from_pem(&pem).unwrap().to_der().unwrap();
+    let path = std::env::temp_dir().join(format!("skytable-test-ca-{}.pem", std::process::id()));
+    std::fs::write(&path, &pem).unwrap();
+    for source in [
+        CaSource::File(path.clone()),
+        CaSource::Pem(pem.clone()),
+        CaSource::Der(der),
+        CaSource::SystemRoots,
+    ] {
+        let tls = TlsConfig::builder().ca(source.clone()).build().unwrap();
+        assert_eq!(tls.sources(), [source]);
     }
+    // a pinned CA along with the system roots
+    TlsConfig::builder()
+        .ca(CaSource::Pem(pem.clone()))
+        .ca(CaSource::SystemRoots)
+        .build()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn tls_config_errors() {
+    use crate::error::Error;
+    let error = |source| match TlsConfig::builder().ca(source).build() {
+        Err(Error::ConnectionSetupErr(ConnectionSetupError::Other(e))) => e,
+        r => panic!("unexpected result: {:?}", r),
+    };
+    assert!(error(CaSource::Pem(b"not a certificate".to_vec()))
+        .starts_with("failed to parse certificate"));
+    assert!(error(CaSource::Der(vec![0x30, 0x03, 0x01])).starts_with("failed to parse certificate"));
+    assert!(error(CaSource::File("/nonexistent/ca.pem".into()))
+        .starts_with("failed to read certificate from /nonexistent/ca.pem"));
 }
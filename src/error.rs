@@ -22,7 +22,11 @@
 //! You might find Skytable's documentation on error codes helpful: [https://docs.skytable.io/protocol/errors](https://docs.skytable.io/protocol/errors)
 //!
 
-use {crate::protocol::ProtocolError, core::fmt};
+use {
+    crate::{protocol::ProtocolError, response::Response},
+    alloc::{borrow::Cow, boxed::Box, string::String, sync::Arc, vec::Vec},
+    core::fmt,
+};
 
 /// A [`Result`] type alias for the client driver
 pub type ClientResult<T> = Result<T, Error>;
@@ -33,26 +37,37 @@ pub type ClientResult<T> = Result<T, Error>;
 /// This is a broad classification for all kinds of possible client driver errors, across I/O, server errors and application level parse errors
 pub enum Error {
     /// An I/O error occurred
+    #[cfg(feature = "std")]
     IoError(std::io::Error),
     /// A bad [`Config`](crate::config::Config) throws this error
     ConnectionSetupErr(ConnectionSetupError),
-    /// When running a query, a protocol error was thrown
-    ProtocolError(ProtocolError),
+    /// When running a query, a protocol error was thrown. If the error is about the content of a response, the start of
+    /// the response is kept too (see [`Config::with_response_capture_len`](crate::Config::with_response_capture_len))
+    ProtocolError(ProtocolError, Option<Box<CapturedResponse>>),
     /// A server error code was received
     ServerError(u16),
     /// An application level parse error
     ParseError(ParseError),
+    /// An empty pipeline can't be executed, since there would be no response to wait for. Nothing is sent to the
+    /// server
+    EmptyPipeline,
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for Error {}
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            #[cfg(feature = "std")]
             Self::IoError(e) => write!(f, "io error: {e}"),
             Self::ConnectionSetupErr(e) => write!(f, "connection setup error: {e}"),
-            Self::ProtocolError(e) => write!(f, "protocol error: {e}"),
+            Self::ProtocolError(e, None) => write!(f, "protocol error: {e}"),
+            Self::ProtocolError(e, Some(response)) => {
+                write!(f, "protocol error: {e} (response: {response})")
+            }
             Self::ServerError(e) => write!(f, "server error: {e}"),
             Self::ParseError(e) => write!(f, "application parse error: {e}"),
+            Self::EmptyPipeline => write!(f, "cannot execute an empty pipeline"),
         }
     }
 }
@@ -61,24 +76,113 @@ impl fmt::Display for Error {
 /// An application level parse error, usually raised by [`FromResponse`](crate::response::FromResponse)
 pub enum ParseError {
     /// The response is non-erroring, but the type is not what was expected
+    #[deprecated(
+        note = "not returned by the client: type mismatches are reported as `ValueMismatch` (for a value) or \
+                `ElementCount` (for a row or list)"
+    )]
     TypeMismatch,
     /// The response is non-erroring, but not of the kind we were looking for (for example, if you try to parse a single value from a Row, it won't work!)
     ResponseMismatch,
     /// Some other parse error occurred
     Other(String),
+    /// A value can't be converted to the requested type (boxed to keep errors small)
+    ValueMismatch(Box<ValueMismatch>),
+    /// The response has a different number of elements than the requested type needs (for example, a row with two
+    /// columns can't be parsed into a tuple of three values). This usually means that the query doesn't match the type
+    ElementCount(Box<ElementCount>),
+}
+
+#[derive(PartialEq, Clone)]
+/// The details of a [`ParseError::ValueMismatch`]. The [`Debug`](fmt::Debug) output respects the
+/// [`LogRedaction`](crate::query::LogRedaction) policy
+pub struct ValueMismatch {
+    /// the type that the value was being converted to
+    pub expected: Cow<'static, str>,
+    /// the name of the [`Value`](crate::response::Value) variant that was received
+    pub received: &'static str,
+    /// the index of the value in the list that contained it, if any
+    pub element: Option<usize>,
+    /// the [`Debug`](fmt::Debug) rendering of the value, truncated to
+    /// [`value_preview_len`](crate::response::value_preview_len) bytes (followed by `...` if it was truncated)
+    pub value: String,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// The details of a [`ParseError::ElementCount`]
+pub struct ElementCount {
+    /// the type that the response was being parsed into
+    pub target: &'static str,
+    /// the number of elements that the type needs
+    pub expected: usize,
+    /// the number of elements that were received
+    pub received: usize,
+}
+
+impl ParseError {
+    /// Record the element that this error occurred in (unless a nested element was already recorded)
+    pub(crate) fn in_element(mut self, index: usize) -> Self {
+        if let Self::ValueMismatch(ref mut e) = self {
+            e.element.get_or_insert(index);
+        }
+        self
+    }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for ParseError {}
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            #[allow(deprecated)]
             Self::TypeMismatch => write!(f, "data type mismatch"),
             Self::ResponseMismatch => write!(f, "response type mismatch"),
             Self::Other(e) => write!(f, "{e}"),
+            Self::ValueMismatch(e) => write!(f, "{e}"),
+            Self::ElementCount(e) => write!(f, "{e}"),
         }
     }
 }
 
+impl fmt::Display for ValueMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected {} but received {}",
+            self.expected, self.received
+        )?;
+        if let Some(element) = self.element {
+            write!(f, " in element {element}")?;
+        }
+        match crate::query::log_redaction() {
+            crate::query::LogRedaction::None => write!(f, ": {}", self.value),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for ValueMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("ValueMismatch");
+        d.field("expected", &self.expected)
+            .field("received", &self.received)
+            .field("element", &self.element);
+        match crate::query::log_redaction() {
+            crate::query::LogRedaction::None => d.field("value", &self.value).finish(),
+            _ => d.finish_non_exhaustive(),
+        }
+    }
+}
+
+impl fmt::Display for ElementCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected {} element(s) for {} but received {}",
+            self.expected, self.target, self.received
+        )
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 /// An error specifically returned during connection setup. This is returned usually when there is a bad configuration
 pub enum ConnectionSetupError {
@@ -88,8 +192,12 @@ pub enum ConnectionSetupError {
     HandshakeError(u8),
     /// The server responded with an invalid handshake
     InvalidServerHandshake,
+    /// None of the endpoints (the server and its [fallbacks](crate::config::Config::add_fallback)) could be connected
+    /// to. Has the error for every endpoint, in the order they were tried
+    AllEndpointsFailed(Vec<EndpointError>),
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for ConnectionSetupError {}
 impl fmt::Display for ConnectionSetupError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -97,10 +205,113 @@ impl fmt::Display for ConnectionSetupError {
             Self::Other(e) => write!(f, "{e}"),
             Self::HandshakeError(e) => write!(f, "handshake error code {e}"),
             Self::InvalidServerHandshake => write!(f, "server sent invalid handshake"),
+            Self::AllEndpointsFailed(errors) => {
+                write!(f, "failed to connect to any endpoint")?;
+                for (i, e) in errors.iter().enumerate() {
+                    write!(f, "{} {e}", if i == 0 { ":" } else { ";" })?;
+                }
+                Ok(())
+            }
         }
     }
 }
 
+#[derive(Debug, Clone)]
+/// The error for one of the endpoints in [`ConnectionSetupError::AllEndpointsFailed`]
+pub struct EndpointError {
+    host: Box<str>,
+    port: u16,
+    cause: Arc<Error>,
+}
+
+impl EndpointError {
+    #[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+    pub(crate) fn new(host: Box<str>, port: u16, cause: Error) -> Self {
+        Self {
+            host,
+            port,
+            cause: Arc::new(cause),
+        }
+    }
+    /// Returns the host of the endpoint
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+    /// Returns the port of the endpoint
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+    /// Returns the error that connecting to the endpoint failed with
+    pub fn cause(&self) -> &Error {
+        &self.cause
+    }
+}
+
+impl PartialEq for EndpointError {
+    fn eq(&self, other: &Self) -> bool {
+        self.host == other.host && self.port == other.port && same_error(&self.cause, &other.cause)
+    }
+}
+
+/// I/O errors can't be compared, so they're equal if they have the same kind and message
+fn same_error(a: &Error, b: &Error) -> bool {
+    match (a, b) {
+        #[cfg(feature = "std")]
+        (Error::IoError(a), Error::IoError(b)) => {
+            a.kind() == b.kind() && a.to_string() == b.to_string()
+        }
+        (Error::ConnectionSetupErr(a), Error::ConnectionSetupErr(b)) => a == b,
+        (Error::ProtocolError(a, x), Error::ProtocolError(b, y)) => a == b && x == y,
+        (Error::ServerError(a), Error::ServerError(b)) => a == b,
+        (Error::ParseError(a), Error::ParseError(b)) => a == b,
+        (Error::EmptyPipeline, Error::EmptyPipeline) => true,
+        _ => false,
+    }
+}
+
+impl fmt::Display for EndpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{} ({})", self.host, self.port, self.cause)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EndpointError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.cause)
+    }
+}
+
+impl fmt::Debug for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidServerResponseForData => write!(f, "InvalidServerResponseForData"),
+            Self::InvalidServerResponseUnknownDataType => {
+                write!(f, "InvalidServerResponseUnknownDataType")
+            }
+            Self::InvalidPacket => write!(f, "InvalidPacket"),
+            Self::UnexpectedEof { received, parsing } => f
+                .debug_struct("UnexpectedEof")
+                .field("received", received)
+                .field("parsing", parsing)
+                .finish(),
+            Self::TrailingData { bytes } => f
+                .debug_struct("TrailingData")
+                .field("bytes", bytes)
+                .finish(),
+            Self::InvalidNumber { element, bytes } => {
+                let mut d = f.debug_struct("InvalidNumber");
+                d.field("element", element);
+                match crate::query::log_redaction() {
+                    crate::query::LogRedaction::None => d.field("bytes", bytes).finish(),
+                    _ => d.finish_non_exhaustive(),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
 impl std::error::Error for ProtocolError {}
 impl fmt::Display for ProtocolError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -110,7 +321,106 @@ impl fmt::Display for ProtocolError {
                 write!(f, "new or unknown data type received from server")
             }
             Self::InvalidPacket => write!(f, "invalid packet received from server"),
+            Self::UnexpectedEof { received, parsing } => write!(
+                f,
+                "connection closed by server while decoding {parsing} (received {received} bytes)"
+            ),
+            Self::TrailingData { bytes } => {
+                write!(f, "received {bytes} unexpected bytes after the response")
+            }
+            Self::InvalidNumber { element, bytes } => {
+                match crate::query::log_redaction() {
+                    crate::query::LogRedaction::None => {
+                        write!(f, "invalid number {:?}", String::from_utf8_lossy(bytes))?
+                    }
+                    _ => write!(f, "invalid number")?,
+                }
+                match element {
+                    Some(element) => write!(f, " in element {element}"),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// The default number of bytes that are kept from the start of a response that couldn't be decoded (see
+/// [`CapturedResponse`])
+pub const DEFAULT_RESPONSE_CAPTURE_LEN: usize = 128;
+
+#[derive(PartialEq, Clone)]
+/// The start of a response that couldn't be decoded, returned along with the [`ProtocolError`] in
+/// [`Error::ProtocolError`] so that the error can be reported with the bytes that caused it. The number of bytes that
+/// are kept is set with [`Config::with_response_capture_len`](crate::Config::with_response_capture_len)
+/// ([`DEFAULT_RESPONSE_CAPTURE_LEN`] by default), and setting it to `0` disables the capture.
+///
+/// The [`Display`](fmt::Display) rendering shows the bytes as escaped ASCII followed by hex, and leaves them out
+/// unless [`log_redaction`](crate::query::log_redaction) is [`LogRedaction::None`](crate::query::LogRedaction::None).
+/// The same goes for the [`Debug`](fmt::Debug) output
+pub struct CapturedResponse {
+    /// the first bytes of the response
+    pub bytes: Vec<u8>,
+    /// the number of bytes of the response that were received
+    pub received: usize,
+}
+
+#[cfg(any(
+    feature = "sync",
+    feature = "aio",
+    feature = "async-std",
+    feature = "alloc-proto"
+))]
+impl CapturedResponse {
+    /// Capture up to `len` bytes from the start of `response` (the buffer that couldn't be decoded because of `e`). A
+    /// closed connection and trailing data aren't about the content of the response, so they aren't captured
+    pub(crate) fn capture(e: &ProtocolError, response: &[u8], len: usize) -> Option<Self> {
+        if len == 0
+            || matches!(
+                e,
+                ProtocolError::UnexpectedEof { .. } | ProtocolError::TrailingData { .. }
+            )
+        {
+            return None;
         }
+        Some(Self {
+            bytes: response[..response.len().min(len)].to_vec(),
+            received: response.len(),
+        })
+    }
+}
+
+impl fmt::Debug for CapturedResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("CapturedResponse");
+        if crate::query::log_redaction() == crate::query::LogRedaction::None {
+            d.field("bytes", &self.bytes);
+        }
+        d.field("received", &self.received);
+        match crate::query::log_redaction() {
+            crate::query::LogRedaction::None => d.finish(),
+            _ => d.finish_non_exhaustive(),
+        }
+    }
+}
+
+impl fmt::Display for CapturedResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "received {} bytes", self.received)?;
+        if crate::query::log_redaction() != crate::query::LogRedaction::None {
+            return Ok(());
+        }
+        write!(f, ", first {}: \"", self.bytes.len())?;
+        for byte in &self.bytes {
+            write!(f, "{}", core::ascii::escape_default(*byte))?;
+        }
+        write!(f, "\" [")?;
+        for (i, byte) in self.bytes.iter().enumerate() {
+            if i != 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{byte:02x}")?;
+        }
+        write!(f, "]")
     }
 }
 
@@ -118,15 +428,26 @@ impl fmt::Display for ProtocolError {
     from impls
 */
 
+#[cfg(feature = "std")]
 impl From<std::io::Error> for Error {
     fn from(e: std::io::Error) -> Self {
         Self::IoError(e)
     }
 }
 
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+impl Error {
+    /// A protocol error for `response` (the buffer that couldn't be decoded), along with up to `capture_len` bytes from
+    /// its start (see [`CapturedResponse`])
+    pub(crate) fn invalid_response(e: ProtocolError, response: &[u8], capture_len: usize) -> Self {
+        let captured = CapturedResponse::capture(&e, response, capture_len).map(Box::new);
+        Self::ProtocolError(e, captured)
+    }
+}
+
 impl From<ProtocolError> for Error {
     fn from(e: ProtocolError) -> Self {
-        Self::ProtocolError(e)
+        Self::ProtocolError(e, None)
     }
 }
 
@@ -135,3 +456,345 @@ impl From<ConnectionSetupError> for Error {
         Self::ConnectionSetupErr(e)
     }
 }
+
+#[derive(Debug)]
+/// Returned by `execute_pipeline_partial` if a pipeline failed, along with the responses to the queries before the one
+/// that failed (so that the progress can be saved)
+///
+/// Since it isn't known how much of the failed response was received, the connection is not reused as is (see the
+/// documentation of `execute_pipeline_partial` for each connection type). If every response was received but the server
+/// sent more data after them, [`failed_index`](Self::failed_index) is `None` and [`parsed`](Self::parsed) has all the
+/// responses.
+pub struct PipelineError {
+    parsed: Vec<Response>,
+    cause: Error,
+    trailing_data: bool,
+}
+
+impl PipelineError {
+    pub(crate) fn new(parsed: Vec<Response>, cause: Error) -> Self {
+        Self {
+            parsed,
+            cause,
+            trailing_data: false,
+        }
+    }
+    /// Every response was received, but they were followed by unexpected data
+    #[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+    pub(crate) fn trailing_data(parsed: Vec<Response>, cause: Error) -> Self {
+        Self {
+            parsed,
+            cause,
+            trailing_data: true,
+        }
+    }
+    /// Returns the responses that were received before the failure, in the order of the queries
+    pub fn parsed(&self) -> &[Response] {
+        &self.parsed
+    }
+    /// Returns the responses that were received before the failure
+    pub fn into_parsed(self) -> Vec<Response> {
+        self.parsed
+    }
+    /// Returns the index of the query whose response couldn't be received. If the pipeline failed before any response
+    /// was received (for example, because it couldn't be sent), this is `Some(0)`. Returns `None` if every response was
+    /// received, but they were followed by unexpected data
+    pub fn failed_index(&self) -> Option<usize> {
+        (!self.trailing_data).then_some(self.parsed.len())
+    }
+    /// Returns the error that caused the pipeline to fail
+    pub fn cause(&self) -> &Error {
+        &self.cause
+    }
+    /// Returns the error that caused the pipeline to fail
+    pub fn into_cause(self) -> Error {
+        self.cause
+    }
+}
+
+impl From<Error> for PipelineError {
+    fn from(cause: Error) -> Self {
+        Self::new(Vec::new(), cause)
+    }
+}
+
+impl From<PipelineError> for Error {
+    fn from(e: PipelineError) -> Self {
+        e.cause
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PipelineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.cause)
+    }
+}
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.failed_index() {
+            Some(index) => write!(f, "the pipeline failed at query {index}: {}", self.cause),
+            None => write!(
+                f,
+                "the pipeline's responses were followed by unexpected data: {}",
+                self.cause
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+/// Returned by `run_with_compensation` if some of the writes failed, describing the writes that failed and the
+/// compensating queries that were run for the writes that succeeded
+pub struct CompensationReport {
+    pipeline_error: Option<Error>,
+    failed: Vec<(usize, Error)>,
+    compensated: Vec<usize>,
+    compensation_errors: Vec<(usize, Error)>,
+}
+
+impl CompensationReport {
+    #[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+    pub(crate) fn pipeline_failed(e: Error) -> Self {
+        Self {
+            pipeline_error: Some(e),
+            ..Default::default()
+        }
+    }
+    #[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+    pub(crate) fn writes_failed(failed: Vec<(usize, Error)>) -> Self {
+        Self {
+            failed,
+            ..Default::default()
+        }
+    }
+    #[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+    pub(crate) fn record_compensation(&mut self, write: usize, r: ClientResult<()>) {
+        match r {
+            Ok(()) => self.compensated.push(write),
+            Err(e) => self.compensation_errors.push((write, e)),
+        }
+    }
+    /// Returns the error if the pipeline of writes failed as a whole (for example, with an I/O error). In this case it
+    /// is not known which writes were applied, so no compensating queries were run
+    pub fn pipeline_error(&self) -> Option<&Error> {
+        self.pipeline_error.as_ref()
+    }
+    /// Returns the index and error of every write that failed
+    pub fn failed(&self) -> &[(usize, Error)] {
+        &self.failed
+    }
+    /// Returns the indices of the writes that were compensated, in the order that they were compensated (the reverse of
+    /// the order they were written in)
+    pub fn compensated(&self) -> &[usize] {
+        &self.compensated
+    }
+    /// Returns the index of the write and the error for every compensating query that failed
+    pub fn compensation_errors(&self) -> &[(usize, Error)] {
+        &self.compensation_errors
+    }
+    /// Returns true if every write that succeeded was compensated (which leaves the database as it was before the
+    /// writes, if the compensating queries are correct)
+    pub fn is_fully_compensated(&self) -> bool {
+        self.pipeline_error.is_none() && self.compensation_errors.is_empty()
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CompensationReport {}
+impl fmt::Display for CompensationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref e) = self.pipeline_error {
+            return write!(f, "the writes failed and were not compensated: {e}");
+        }
+        write!(
+            f,
+            "{} write(s) failed and {} were compensated",
+            self.failed.len(),
+            self.compensated.len()
+        )?;
+        if let Some((_, e)) = self.failed.first() {
+            write!(f, " (first error: {e})")?;
+        }
+        if let Some((write, e)) = self.compensation_errors.first() {
+            write!(
+                f,
+                "; {} compensation(s) failed (first error, for write {write}: {e})",
+                self.compensation_errors.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A query or a pipeline is already at its client-side limit (see [`Query::set_param_limit`](crate::Query::set_param_limit)
+/// and [`Pipeline::set_query_limit`](crate::Pipeline::set_query_limit)). The limit is included
+pub enum LimitError {
+    /// A query can't have more parameters than this
+    Params(usize),
+    /// A pipeline can't have more queries than this
+    Queries(usize),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LimitError {}
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Params(limit) => write!(f, "a query can't have more than {limit} parameters"),
+            Self::Queries(limit) => write!(f, "a pipeline can't have more than {limit} queries"),
+        }
+    }
+}
+
+#[cfg(test)]
+/// Held by the tests that change the process-wide formatting settings, and by those that depend on their defaults
+pub(crate) static FORMAT_SETTINGS: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+#[test]
+fn invalid_number_redaction() {
+    use crate::query::{set_log_redaction, LogRedaction};
+    let _settings = FORMAT_SETTINGS.lock().unwrap();
+    let e = Error::from(ProtocolError::InvalidNumber {
+        element: Some(1),
+        bytes: b"12x".to_vec(),
+    });
+    assert_eq!(
+        e.to_string(),
+        "protocol error: invalid number \"12x\" in element 1"
+    );
+    set_log_redaction(LogRedaction::ValuesOnly);
+    let redacted = e.to_string();
+    set_log_redaction(LogRedaction::None);
+    assert_eq!(redacted, "protocol error: invalid number in element 1");
+}
+
+#[test]
+fn debug_redaction() {
+    use crate::query::{set_log_redaction, LogRedaction};
+    let _settings = FORMAT_SETTINGS.lock().unwrap();
+    let mismatch = ValueMismatch {
+        expected: "u64".into(),
+        received: "String",
+        element: Some(1),
+        value: "\"hunter2\"".into(),
+    };
+    let invalid_number = ProtocolError::InvalidNumber {
+        element: None,
+        bytes: b"12x".to_vec(),
+    };
+    let captured = CapturedResponse {
+        bytes: b"\x12hunter2".to_vec(),
+        received: 8,
+    };
+    let format = || {
+        [
+            format!("{mismatch:?}"),
+            format!("{invalid_number:?}"),
+            format!("{captured:?}"),
+        ]
+    };
+    let plain = format();
+    set_log_redaction(LogRedaction::ValuesOnly);
+    let values_only = format();
+    set_log_redaction(LogRedaction::All);
+    let all = format();
+    set_log_redaction(LogRedaction::None);
+    assert!(plain[0].contains("hunter2"));
+    assert!(plain[1].contains("[49, 50, 120]"));
+    assert!(plain[2].contains("[18, 104, 117"));
+    for redacted in values_only.iter().chain(&all) {
+        assert!(!redacted.contains("hunter2") && !redacted.contains("[49, 50, 120]"));
+        assert!(!redacted.contains("104, 117"));
+    }
+    assert_eq!(
+        values_only,
+        [
+            "ValueMismatch { expected: \"u64\", received: \"String\", element: Some(1), .. }",
+            "InvalidNumber { element: None, .. }",
+            "CapturedResponse { received: 8, .. }",
+        ]
+    );
+}
+
+#[cfg(any(
+    feature = "sync",
+    feature = "aio",
+    feature = "async-std",
+    feature = "alloc-proto"
+))]
+#[test]
+fn response_capture() {
+    use crate::query::{set_log_redaction, LogRedaction};
+    let _settings = FORMAT_SETTINGS.lock().unwrap();
+    let response = [b'x'; 1000];
+    // the capture is bounded
+    let default = CapturedResponse::capture(
+        &ProtocolError::InvalidPacket,
+        &response,
+        DEFAULT_RESPONSE_CAPTURE_LEN,
+    )
+    .unwrap();
+    assert_eq!(default.bytes.len(), DEFAULT_RESPONSE_CAPTURE_LEN);
+    let captured = CapturedResponse::capture(&ProtocolError::InvalidPacket, &response, 4).unwrap();
+    let escaped = CapturedResponse::capture(
+        &ProtocolError::InvalidServerResponseUnknownDataType,
+        b"\xff\n\"\\\x00a",
+        4,
+    )
+    .unwrap();
+    // unless it's disabled
+    assert_eq!(
+        CapturedResponse::capture(&ProtocolError::InvalidPacket, &response, 0),
+        None
+    );
+    // errors that aren't about the content of the response aren't captured
+    let trailing = CapturedResponse::capture(&ProtocolError::TrailingData { bytes: 1 }, b"\x12", 4);
+    assert_eq!((captured.bytes.len(), captured.received), (4, 1000));
+    assert_eq!(
+        captured.to_string(),
+        "received 1000 bytes, first 4: \"xxxx\" [78 78 78 78]"
+    );
+    assert_eq!(trailing, None);
+    // non-printable bytes are escaped
+    assert_eq!(
+        escaped.to_string(),
+        "received 6 bytes, first 4: \"\\xff\\n\\\"\\\\\" [ff 0a 22 5c]"
+    );
+    // and the error prints them
+    let e = Error::ProtocolError(
+        ProtocolError::InvalidServerResponseUnknownDataType,
+        Some(Box::new(escaped.clone())),
+    );
+    assert!(e
+        .to_string()
+        .ends_with(" (response: received 6 bytes, first 4: \"\\xff\\n\\\"\\\\\" [ff 0a 22 5c])"));
+    // the bytes are left out when redacting
+    set_log_redaction(LogRedaction::ValuesOnly);
+    let redacted = (escaped.to_string(), e.to_string());
+    set_log_redaction(LogRedaction::None);
+    assert_eq!(redacted.0, "received 6 bytes");
+    assert!(redacted.1.ends_with(" (response: received 6 bytes)"));
+}
+
+#[test]
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+fn endpoint_error_eq() {
+    use std::io;
+    let refused = |msg| Error::IoError(io::Error::new(io::ErrorKind::ConnectionRefused, msg));
+    let e = |host: &str, cause| EndpointError::new(host.into(), 2003, cause);
+    // independently created errors are compared by value
+    assert_eq!(e("a", refused("refused")), e("a", refused("refused")));
+    assert_ne!(e("a", refused("refused")), e("b", refused("refused")));
+    assert_ne!(e("a", refused("refused")), e("a", refused("reset")));
+    assert_ne!(
+        e("a", refused("refused")),
+        e(
+            "a",
+            io::Error::new(io::ErrorKind::TimedOut, "refused").into()
+        )
+    );
+    assert_eq!(e("a", Error::ServerError(5)), e("a", Error::ServerError(5)));
+    assert_ne!(e("a", Error::ServerError(5)), e("a", refused("5")));
+}
@@ -23,24 +23,84 @@
 
 use {
     crate::{
-        error::{ClientResult, ConnectionSetupError, Error},
+        config::TlsConfig,
+        error::{
+            ClientResult, CompensationReport, ConnectionSetupError, Error, PipelineError,
+            DEFAULT_RESPONSE_CAPTURE_LEN,
+        },
+        io::{async_common::FedQueries, metrics::Metrics, wire::WireDump, ConnectionInfo},
         protocol::{
+            check_trailing,
             handshake::{ClientHandshake, ServerHandshake},
-            DecodeState, Decoder, MRespState, PipelineResult, RState,
+            DecodeState, Decoder, MRespState, PipelineResult, ProtocolError, RState,
         },
         query::Pipeline,
         response::{FromResponse, Response},
         Config, Query,
     },
-    native_tls::Certificate,
-    std::ops::{Deref, DerefMut},
+    std::{
+        future::Future,
+        io,
+        net::SocketAddr,
+        ops::{Deref, DerefMut},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    },
     tokio::{
-        io::{AsyncReadExt, AsyncWriteExt},
-        net::TcpStream,
+        io::{split, AsyncRead, AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf},
+        net::{lookup_host, TcpStream},
+        task::JoinSet,
+        time::timeout,
     },
     tokio_native_tls::{TlsConnector, TlsStream},
 };
 
+/// Connect to the first address, and if the attempt hasn't completed within `delay` start the next attempt in parallel
+/// (and so on), returning the first connection that is established. The remaining attempts are aborted
+async fn connect_happy_eyeballs<T, F, Fut>(
+    addrs: Vec<SocketAddr>,
+    delay: Duration,
+    connect: F,
+) -> io::Result<T>
+where
+    T: Send + 'static,
+    F: Fn(SocketAddr) -> Fut,
+    Fut: Future<Output = io::Result<T>> + Send + 'static,
+{
+    if let [addr] = addrs[..] {
+        return connect(addr).await;
+    }
+    let mut attempts = JoinSet::new();
+    let mut addrs = addrs.into_iter().peekable();
+    let mut last_error = None;
+    loop {
+        if let Some(addr) = addrs.next() {
+            attempts.spawn(connect(addr));
+        } else if attempts.is_empty() {
+            return Err(last_error.unwrap_or_else(super::no_addresses));
+        }
+        let result = if addrs.peek().is_some() {
+            match timeout(delay, attempts.join_next()).await {
+                Ok(result) => result,
+                // still waiting, so start the next attempt
+                Err(_) => continue,
+            }
+        } else {
+            attempts.join_next().await
+        };
+        match result {
+            // dropping the set aborts the other attempts
+            Some(Ok(Ok(con))) => return Ok(con),
+            Some(Ok(Err(e))) => last_error = Some(e),
+            Some(Err(e)) => last_error = Some(io::Error::other(e)),
+            None => {}
+        }
+    }
+}
+
 #[derive(Debug)]
 /// An async `skyhash/TCP` connection
 ///
@@ -81,43 +141,105 @@ impl DerefMut for ConnectionTlsAsync {
     }
 }
 
+impl ConnectionAsync {
+    /// Split this connection into a [`QuerySink`] that sends queries and a [`ResponseStream`] that receives their
+    /// responses (in order), so that you can send queries from one task while reading responses from another.
+    ///
+    /// Use [`ConnectionAsync::reunite`] to get the connection back.
+    pub fn into_split(self) -> (QuerySink<TcpStream>, ResponseStream<TcpStream>) {
+        self.0.into_split()
+    }
+    /// Reunite the halves returned by [`ConnectionAsync::into_split`]. This fails (and returns the halves in the
+    /// [`ReuniteError`]) if the halves are not from the same connection, if there are responses that haven't been
+    /// received yet or if the stream is [broken](ResponseStream::is_broken).
+    pub fn reunite(
+        sink: QuerySink<TcpStream>,
+        stream: ResponseStream<TcpStream>,
+    ) -> Result<Self, ReuniteError<TcpStream>> {
+        stream.reunite(sink).map(Self)
+    }
+}
+
+impl ConnectionTlsAsync {
+    /// Split this connection into a [`QuerySink`] that sends queries and a [`ResponseStream`] that receives their
+    /// responses (in order), so that you can send queries from one task while reading responses from another.
+    ///
+    /// Use [`ConnectionTlsAsync::reunite`] to get the connection back.
+    pub fn into_split(
+        self,
+    ) -> (
+        QuerySink<TlsStream<TcpStream>>,
+        ResponseStream<TlsStream<TcpStream>>,
+    ) {
+        self.0.into_split()
+    }
+    /// Reunite the halves returned by [`ConnectionTlsAsync::into_split`]. This fails (and returns the halves in the
+    /// [`ReuniteError`]) if the halves are not from the same connection, if there are responses that haven't been
+    /// received yet or if the stream is [broken](ResponseStream::is_broken).
+    pub fn reunite(
+        sink: QuerySink<TlsStream<TcpStream>>,
+        stream: ResponseStream<TlsStream<TcpStream>>,
+    ) -> Result<Self, ReuniteError<TlsStream<TcpStream>>> {
+        stream.reunite(sink).map(Self)
+    }
+}
+
 impl Config {
+    async fn connect_tokio_tcp(&self) -> io::Result<TcpStream> {
+        match self.addr() {
+            Some(addr) => TcpStream::connect(addr).await,
+            None => {
+                let addrs = lookup_host((self.host(), self.port())).await?.collect();
+                connect_happy_eyeballs(
+                    super::interleave_families(addrs),
+                    self.connect_attempt_delay(),
+                    TcpStream::connect,
+                )
+                .await
+            }
+        }
+    }
     /// Establish an async connection to the database using the current configuration
     pub async fn connect_async(&self) -> ClientResult<ConnectionAsync> {
-        TcpStream::connect((self.host(), self.port()))
-            .await
-            .map(TcpConnection::new)?
-            ._handshake(self)
-            .await
-            .map(ConnectionAsync)
+        self.connect_endpoints_async(|cfg| async move {
+            let con = cfg.connect_tokio_tcp().await?;
+            let info = ConnectionInfo::new(&cfg, con.peer_addr()?, con.local_addr()?, false);
+            let metrics = Metrics::new(cfg.metrics_prefix());
+            TcpConnection::new(con, info, metrics)
+                ._handshake(&cfg)
+                .await
+        })
+        .await
+        .map(ConnectionAsync)
     }
     /// Establish an async TLS connection to the database using the current configuration.
-    /// Pass the certificate in PEM format.
+    /// Pass the certificate in PEM format (it is trusted along with the certificate authorities of the operating
+    /// system). The certificate doesn't have to name the host, so use [`Config::connect_tls_async_with`] to verify the host name (or for
+    /// more control over the trusted certificates).
     pub async fn connect_tls_async(&self, cert: &str) -> ClientResult<ConnectionTlsAsync> {
-        let stream = TcpStream::connect((self.host(), self.port())).await?;
-        // set up acceptor
-        let mut builder = native_tls::TlsConnector::builder();
-        builder
-            .add_root_certificate(Certificate::from_pem(cert.as_bytes()).map_err(|e| {
-                ConnectionSetupError::Other(format!("failed to parse certificate: {e}"))
-            })?)
-            .danger_accept_invalid_hostnames(true)
-            .build()
-            .map_err(|e| {
-                ConnectionSetupError::Other(format!("failed to set up TLS acceptor: {e}"))
-            })?;
-        let connector = builder.build().map_err(|e| {
-            ConnectionSetupError::Other(format!("failed to set up TLS acceptor: {e}"))
-        })?;
-        // init and handshake
-        TlsConnector::from(connector)
-            .connect(self.host(), stream)
+        self.connect_tls_async_with(&TlsConfig::from_pem_str(cert)?)
             .await
-            .map(TcpConnection::new)
-            .map_err(|e| ConnectionSetupError::Other(format!("TLS handshake failed: {e}")))?
-            ._handshake(self)
-            .await
-            .map(ConnectionTlsAsync)
+    }
+    /// Establish an async TLS connection to the database using the current configuration, trusting the certificate
+    /// authorities in `tls`
+    pub async fn connect_tls_async_with(
+        &self,
+        tls: &TlsConfig,
+    ) -> ClientResult<ConnectionTlsAsync> {
+        self.connect_endpoints_async(|cfg| async move {
+            let stream = cfg.connect_tokio_tcp().await?;
+            let info = ConnectionInfo::new(&cfg, stream.peer_addr()?, stream.local_addr()?, true);
+            // init and handshake
+            TlsConnector::from(tls.connector().clone())
+                .connect(cfg.host(), stream)
+                .await
+                .map(|con| TcpConnection::new(con, info, Metrics::new(cfg.metrics_prefix())))
+                .map_err(|e| ConnectionSetupError::Other(format!("TLS handshake failed: {e}")))?
+                ._handshake(&cfg)
+                .await
+        })
+        .await
+        .map(ConnectionTlsAsync)
     }
 }
 
@@ -126,97 +248,891 @@ impl Config {
 pub struct TcpConnection<C: AsyncWriteExt + AsyncReadExt + Unpin> {
     con: C,
     buf: Vec<u8>,
+    info: ConnectionInfo,
+    /// set while a query is running (and left set if it doesn't run to completion)
+    broken: bool,
+    space: Option<Box<str>>,
+    /// queries that were fed but not flushed yet
+    fed: FedQueries,
+    /// the number of fed queries whose responses haven't been read yet
+    unread: usize,
+    timeout: Option<Duration>,
+    metrics: Metrics,
+    wire: WireDump,
+    response_capture_len: usize,
 }
 
 impl<C: AsyncWriteExt + AsyncReadExt + Unpin> TcpConnection<C> {
-    fn new(con: C) -> Self {
+    fn new(con: C, info: ConnectionInfo, metrics: Metrics) -> Self {
         Self {
             con,
             buf: Vec::with_capacity(crate::BUFSIZE),
+            info,
+            broken: false,
+            space: None,
+            fed: FedQueries::default(),
+            unread: 0,
+            timeout: None,
+            metrics,
+            wire: WireDump::off(),
+            response_capture_len: DEFAULT_RESPONSE_CAPTURE_LEN,
         }
     }
-    async fn _handshake(mut self, cfg: &Config) -> ClientResult<Self> {
-        let handshake = ClientHandshake::new(cfg);
-        self.con.write_all(handshake.inner()).await?;
-        let mut resp = [0u8; 4];
-        self.con.read_exact(&mut resp).await?;
-        match ServerHandshake::parse(resp)? {
-            ServerHandshake::Error(e) => return Err(ConnectionSetupError::HandshakeError(e).into()),
-            ServerHandshake::Okay(_suggestion) => return Ok(self),
-        }
+    /// Returns information about this connection, such as the address of the server
+    pub fn info(&self) -> Option<&ConnectionInfo> {
+        Some(&self.info)
     }
-    /// Execute a pipeline. The server returns the queries in the order they were sent (unless otherwise set).
-    pub async fn execute_pipeline(&mut self, pipeline: &Pipeline) -> ClientResult<Vec<Response>> {
+    fn into_split(mut self) -> (QuerySink<C>, ResponseStream<C>) {
+        let (read, write) = split(self.con);
+        // the buffer still holds the last response
+        self.buf.clear();
+        let outstanding = Arc::new(AtomicUsize::new(0));
+        (
+            QuerySink {
+                con: write,
+                buf: vec![],
+                outstanding: outstanding.clone(),
+                metrics: self.metrics.clone(),
+                wire: self.wire.clone(),
+            },
+            ResponseStream {
+                con: read,
+                buf: self.buf,
+                info: self.info,
+                outstanding,
+                broken: false,
+                cursor: 0,
+                state: RState::default(),
+                space: self.space,
+                metrics: self.metrics,
+                wire: self.wire,
+                response_capture_len: self.response_capture_len,
+            },
+        )
+    }
+}
+
+impl_async_connection!({ AsyncWriteExt + AsyncReadExt + Unpin }, {
+    AsyncRead + Unpin
+});
+
+/// Close the write side of `con`
+async fn shutdown<C: AsyncWriteExt + Unpin>(con: &mut C) -> io::Result<()> {
+    con.shutdown().await
+}
+
+/// Run `f`, failing with [`TimedOut`](io::ErrorKind::TimedOut) if it doesn't complete within `limit`
+async fn with_timeout<T, E: From<Error>>(
+    limit: Option<Duration>,
+    f: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    match limit {
+        Some(limit) => timeout(limit, f)
+            .await
+            .unwrap_or_else(|_| Err(Error::IoError(io::ErrorKind::TimedOut.into()).into())),
+        None => f.await,
+    }
+}
+
+#[derive(Debug)]
+/// The sending half of a split connection. See [`ConnectionAsync::into_split`]
+pub struct QuerySink<C: AsyncWriteExt + AsyncReadExt + Unpin> {
+    con: WriteHalf<C>,
+    buf: Vec<u8>,
+    outstanding: Arc<AtomicUsize>,
+    metrics: Metrics,
+    wire: WireDump,
+}
+
+impl<C: AsyncWriteExt + AsyncReadExt + Unpin> QuerySink<C> {
+    /// Send a query. The response can be received from the [`ResponseStream`]
+    pub async fn send(&mut self, q: &Query) -> ClientResult<()> {
+        self.buf.clear();
+        q.write_packet(&mut self.buf);
+        self.write(1).await
+    }
+    /// Send a pipeline. The responses for each query can be received from the [`ResponseStream`]
+    pub async fn send_pipeline(&mut self, pipeline: &Pipeline) -> ClientResult<()> {
+        pipeline.check_not_empty()?;
         self.buf.clear();
         self.buf.push(b'P');
-        // packet size
         self.buf
             .extend(itoa::Buffer::new().format(pipeline.buf().len()).as_bytes());
         self.buf.push(b'\n');
-        // write
-        self.con.write_all(&self.buf).await?;
-        self.con.write_all(pipeline.buf()).await?;
-        self.buf.clear();
-        // read
-        let mut cursor = 0;
-        let mut state = MRespState::default();
-        loop {
-            let mut buf = [0u8; crate::BUFSIZE];
-            let n = self.con.read(&mut buf).await?;
-            if n == 0 {
-                return Err(Error::IoError(std::io::ErrorKind::ConnectionReset.into()));
-            }
-            self.buf.extend_from_slice(&buf[..n]);
-            let (_state, _position) =
-                Decoder::new(&self.buf, cursor).validate_pipe(pipeline.query_count(), state);
-            match _state {
-                PipelineResult::Completed(r) => return Ok(r),
-                PipelineResult::Pending(_state) => {
-                    cursor = _position;
-                    state = _state;
-                }
-                PipelineResult::Error(e) => return Err(e.into()),
+        self.buf.extend_from_slice(pipeline.buf());
+        self.write(pipeline.query_count()).await
+    }
+    async fn write(&mut self, responses: usize) -> ClientResult<()> {
+        // count the responses before sending, so that the stream doesn't think that it's idle if the response is quick
+        self.outstanding.fetch_add(responses, Ordering::AcqRel);
+        self.wire.written(&self.buf);
+        let r = match self.con.write_all(&self.buf).await {
+            Ok(()) => self.con.flush().await,
+            Err(e) => Err(e),
+        };
+        match r {
+            Ok(()) => self.metrics.written(self.buf.len()),
+            Err(_) => {
+                self.outstanding.fetch_sub(responses, Ordering::AcqRel);
             }
         }
+        r.map_err(From::from)
     }
-    /// Run a query and return a raw [`Response`]
-    pub async fn query(&mut self, q: &Query) -> ClientResult<Response> {
-        self.buf.clear();
-        q.write_packet(&mut self.buf).unwrap();
-        self.con.write_all(&self.buf).await?;
-        self.buf.clear();
-        let mut state = RState::default();
-        let mut cursor = 0;
-        let mut expected = Decoder::MIN_READBACK;
+}
+
+#[derive(Debug)]
+/// The receiving half of a split connection. See [`ConnectionAsync::into_split`]
+pub struct ResponseStream<C: AsyncWriteExt + AsyncReadExt + Unpin> {
+    con: ReadHalf<C>,
+    buf: Vec<u8>,
+    info: ConnectionInfo,
+    outstanding: Arc<AtomicUsize>,
+    /// set once a response couldn't be received, since the responses that follow can't be told apart
+    broken: bool,
+    cursor: usize,
+    state: RState,
+    /// the space of the connection (restored when it is reunited)
+    space: Option<Box<str>>,
+    metrics: Metrics,
+    wire: WireDump,
+    response_capture_len: usize,
+}
+
+impl<C: AsyncWriteExt + AsyncReadExt + Unpin> ResponseStream<C> {
+    /// Returns the number of responses that haven't been received yet
+    pub fn outstanding(&self) -> usize {
+        self.outstanding.load(Ordering::Acquire)
+    }
+    /// Returns true if a response couldn't be received (because of an I/O error or a response that couldn't be
+    /// decoded). The responses that follow can't be received either, so [`next`](Self::next) returns `None` from then
+    /// on and the halves can't be reunited.
+    pub fn is_broken(&self) -> bool {
+        self.broken
+    }
+    /// Returns the number of bytes that were received but not returned as a response yet
+    pub fn pending_read_bytes(&self) -> usize {
+        self.buf.len()
+    }
+    /// Receive the next response, in the order that the queries were sent. Returns `None` if there are no outstanding
+    /// responses (you can call this again after sending more queries) or if the stream is [broken](Self::is_broken).
+    pub async fn next(&mut self) -> Option<ClientResult<Response>> {
+        if self.broken || self.outstanding() == 0 {
+            return None;
+        }
+        // left set if the future is dropped before the response is received, since we can't tell where it left off
+        self.broken = true;
+        let r = self.next_response().await;
+        self.metrics.outcome(r.as_ref());
+        if r.is_ok() {
+            self.broken = false;
+            self.outstanding.fetch_sub(1, Ordering::AcqRel);
+        }
+        Some(r)
+    }
+    async fn next_response(&mut self) -> ClientResult<Response> {
         loop {
-            let mut buf = [0u8; crate::BUFSIZE];
-            let n = self.con.read(&mut buf).await?;
-            if n == 0 {
-                return Err(Error::IoError(std::io::ErrorKind::ConnectionReset.into()));
-            }
-            if n < expected {
-                continue;
+            if self.cursor < self.buf.len() {
+                let (state, position) = Decoder::new(&self.buf, self.cursor)
+                    .validate_response(core::mem::take(&mut self.state));
+                match state {
+                    DecodeState::Completed(resp) => {
+                        self.buf.drain(..position);
+                        self.cursor = 0;
+                        return Ok(resp);
+                    }
+                    DecodeState::ChangeState(state) => {
+                        self.state = state;
+                        self.cursor = position;
+                    }
+                    DecodeState::Error(e) => {
+                        return Err(Error::invalid_response(
+                            e,
+                            &self.buf,
+                            self.response_capture_len,
+                        ));
+                    }
+                }
             }
-            self.buf.extend_from_slice(&buf[..n]);
-            let (_state, _position) = Decoder::new(&self.buf, cursor).validate_response(state);
-            match _state {
-                DecodeState::Completed(resp) => return Ok(resp),
-                DecodeState::ChangeState(_state) => {
-                    expected = 1;
-                    state = _state;
-                    cursor = _position;
+            // read into the end of the buffer (see `TcpConnection::read_more`)
+            let len = self.buf.len();
+            self.buf.resize(len + crate::BUFSIZE, 0);
+            let r = self.con.read(&mut self.buf[len..]).await;
+            self.buf.truncate(len + *r.as_ref().unwrap_or(&0));
+            let n = r?;
+            self.metrics.read(n);
+            self.wire.read(&self.buf[len..]);
+            if n == 0 {
+                return Err(ProtocolError::UnexpectedEof {
+                    received: self.buf.len(),
+                    parsing: self.state.parsing(),
                 }
-                DecodeState::Error(e) => return Err(Error::ProtocolError(e)),
+                .into());
             }
         }
     }
-    /// Run and parse a query into the indicated type. The type must implement [`FromResponse`]
-    pub async fn query_parse<T: FromResponse>(&mut self, q: &Query) -> ClientResult<T> {
-        self.query(q).await.and_then(FromResponse::from_response)
+    fn reunite(self, sink: QuerySink<C>) -> Result<TcpConnection<C>, ReuniteError<C>> {
+        let kind = if !self.con.is_pair_of(&sink.con) {
+            Some(ReuniteErrorKind::Mismatched)
+        } else if self.broken {
+            Some(ReuniteErrorKind::Broken)
+        } else if self.outstanding() != 0 || !self.buf.is_empty() {
+            Some(ReuniteErrorKind::Outstanding)
+        } else {
+            None
+        };
+        if let Some(kind) = kind {
+            return Err(ReuniteError {
+                kind,
+                halves: Box::new((sink, self)),
+            });
+        }
+        let mut con = TcpConnection::new(self.con.unsplit(sink.con), self.info, self.metrics);
+        con.buf = self.buf;
+        con.space = self.space;
+        con.wire = self.wire;
+        con.response_capture_len = self.response_capture_len;
+        Ok(con)
     }
-    /// Call this if the internally allocated buffer is growing too large and impacting your performance. However, normally
-    /// you will not need to call this
-    pub fn reset_buffer(&mut self) {
-        self.buf.shrink_to_fit()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Why the halves of a split connection couldn't be reunited (see [`ReuniteError`])
+pub enum ReuniteErrorKind {
+    /// The halves are not from the same connection
+    Mismatched,
+    /// There are responses that haven't been received yet
+    Outstanding,
+    /// The response stream is [broken](ResponseStream::is_broken), so the connection can't be used again
+    Broken,
+}
+
+#[derive(Debug)]
+/// The error returned when the halves of a split connection can't be reunited. It holds on to the halves, so that they
+/// can still be used (for example, to receive the outstanding responses and try again)
+pub struct ReuniteError<C: AsyncWriteExt + AsyncReadExt + Unpin> {
+    kind: ReuniteErrorKind,
+    /// boxed to keep the error small
+    halves: Box<(QuerySink<C>, ResponseStream<C>)>,
+}
+
+impl<C: AsyncWriteExt + AsyncReadExt + Unpin> ReuniteError<C> {
+    /// Returns why the halves couldn't be reunited
+    pub fn kind(&self) -> ReuniteErrorKind {
+        self.kind
+    }
+    /// Returns the halves that couldn't be reunited
+    pub fn into_parts(self) -> (QuerySink<C>, ResponseStream<C>) {
+        *self.halves
     }
 }
+
+impl<C: AsyncWriteExt + AsyncReadExt + Unpin + std::fmt::Debug> std::error::Error
+    for ReuniteError<C>
+{
+}
+impl<C: AsyncWriteExt + AsyncReadExt + Unpin> std::fmt::Display for ReuniteError<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            ReuniteErrorKind::Mismatched => {
+                write!(
+                    f,
+                    "the query sink and response stream are not from the same connection"
+                )
+            }
+            ReuniteErrorKind::Outstanding => {
+                write!(
+                    f,
+                    "can't reunite a connection that has outstanding responses"
+                )
+            }
+            ReuniteErrorKind::Broken => {
+                write!(
+                    f,
+                    "can't reunite a connection whose response stream is broken"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+/// Create a connection over `stream` and complete the handshake (so `stream` should start with
+/// [`HANDSHAKE_OKAY`](crate::io::mock::HANDSHAKE_OKAY)), using the default test configuration
+async fn mock_connection(
+    stream: crate::io::mock::MockStream,
+) -> TcpConnection<crate::io::mock::MockStream> {
+    mock_connection_with(&Config::new_default("username", "password"), stream).await
+}
+
+#[cfg(test)]
+/// Same as [`mock_connection`], but with `cfg`
+async fn mock_connection_with(
+    cfg: &Config,
+    stream: crate::io::mock::MockStream,
+) -> TcpConnection<crate::io::mock::MockStream> {
+    let addr = "127.0.0.1:2003".parse().unwrap();
+    TcpConnection::new(
+        stream,
+        ConnectionInfo::new(cfg, addr, addr, false),
+        Metrics::new(cfg.metrics_prefix()),
+    )
+    ._handshake(cfg)
+    .await
+    .unwrap()
+}
+
+#[cfg(test)]
+/// Start a server that accepts a connection and responds to each of the first `queries` queries with its index (as soon
+/// as it is received). Returns the configuration to connect to it
+async fn indexed_server(queries: usize) -> Config {
+    use tokio::{
+        io::{AsyncBufReadExt, BufReader},
+        net::TcpListener,
+    };
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let cfg = Config::new("127.0.0.1", port, "username", "password");
+    let _cfg = cfg.clone();
+    tokio::spawn(async move {
+        let (con, _) = listener.accept().await.unwrap();
+        let (read, mut write) = con.into_split();
+        let mut read = BufReader::new(read);
+        let mut hs = vec![0; ClientHandshake::new(&_cfg).inner().len()];
+        read.read_exact(&mut hs).await.unwrap();
+        write.write_all(&[b'H', 0, 0, 0]).await.unwrap();
+        for i in 0..queries {
+            // S<size>\n<packet>
+            let mut size = vec![];
+            read.read_until(b'\n', &mut size).await.unwrap();
+            let size: usize = std::str::from_utf8(&size[1..size.len() - 1])
+                .unwrap()
+                .parse()
+                .unwrap();
+            let mut packet = vec![0; size];
+            read.read_exact(&mut packet).await.unwrap();
+            write
+                .write_all(format!("\x05{i}\n").as_bytes())
+                .await
+                .unwrap();
+        }
+    });
+    cfg
+}
+
+#[test]
+fn split_full_duplex() {
+    use crate::response::Value;
+    const QUERIES: usize = 1000;
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let cfg = indexed_server(QUERIES).await;
+        let port = cfg.port();
+        let con = cfg.connect_async().await.unwrap();
+        let (mut sink, mut stream) = con.into_split();
+        // a permit for each query that was sent, so that the reader waits for the writer instead of polling
+        let sent = Arc::new(tokio::sync::Semaphore::new(0));
+        let _sent = sent.clone();
+        let writer = tokio::spawn(async move {
+            let q = query!(
+                "select * from myspace.mymodel where username = ?",
+                "x".repeat(512)
+            );
+            for _ in 0..QUERIES {
+                sink.send(&q).await.unwrap();
+                _sent.add_permits(1);
+            }
+            sink
+        });
+        for i in 0..QUERIES {
+            sent.acquire().await.unwrap().forget();
+            assert_eq!(
+                stream.next().await.unwrap().unwrap(),
+                Response::Value(Value::UInt64(i as u64))
+            );
+        }
+        let sink = writer.await.unwrap();
+        assert!(stream.next().await.is_none());
+        assert_eq!(stream.pending_read_bytes(), 0);
+        let con = ConnectionAsync::reunite(sink, stream).unwrap();
+        assert_eq!(con.info().unwrap().peer_addr().port(), port);
+    })
+}
+
+#[test]
+fn split_errors() {
+    use crate::{io::mock::MockStream, response::Value};
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let connect =
+            |incoming: &'static [u8]| mock_connection(MockStream::with_handshake(incoming));
+        let q = query!("sysctl report status");
+        // halves from different connections
+        let (sink, _) = connect(b"").await.into_split();
+        let (_, stream) = connect(b"").await.into_split();
+        let e = stream.reunite(sink).unwrap_err();
+        assert_eq!(e.kind(), ReuniteErrorKind::Mismatched);
+        // the halves are returned along with the error, so that the outstanding responses can still be received
+        let (mut sink, stream) = connect(b"\x05100\n").await.into_split();
+        sink.send(&q).await.unwrap();
+        let e = stream.reunite(sink).unwrap_err();
+        assert_eq!(e.kind(), ReuniteErrorKind::Outstanding);
+        let (sink, mut stream) = e.into_parts();
+        assert_eq!(
+            stream.next().await.unwrap().unwrap(),
+            Response::Value(Value::UInt64(100))
+        );
+        stream.reunite(sink).unwrap();
+        // a response that can't be decoded breaks the stream, along with the responses that follow it
+        let (mut sink, mut stream) = connect(b"\x05100\n\xff\x05200\n").await.into_split();
+        for _ in 0..3 {
+            sink.send(&q).await.unwrap();
+        }
+        assert_eq!(
+            stream.next().await.unwrap().unwrap(),
+            Response::Value(Value::UInt64(100))
+        );
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(Error::ProtocolError(..)))
+        ));
+        assert!(stream.is_broken());
+        assert!(stream.next().await.is_none());
+        assert_eq!(stream.outstanding(), 2);
+        let e = stream.reunite(sink).unwrap_err();
+        assert_eq!(e.kind(), ReuniteErrorKind::Broken);
+    })
+}
+
+#[test]
+fn happy_eyeballs() {
+    use {std::time::Instant, tokio::net::TcpListener};
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let good = listener.local_addr().unwrap();
+        // an address whose route blackholes our connection attempts
+        let bad: SocketAddr = "[::1]:1".parse().unwrap();
+        let start = Instant::now();
+        let con = connect_happy_eyeballs(
+            vec![bad, good],
+            Duration::from_millis(50),
+            |addr| async move {
+                if addr == bad {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                }
+                TcpStream::connect(addr).await
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(con.peer_addr().unwrap(), good);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    })
+}
+
+#[test]
+fn split_reads() {
+    use crate::io::mock::{self, rows_response, ROWS_RESPONSE};
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let q = query!("select * from myspace.mymodel");
+        for (split, stream) in mock::split_reads(ROWS_RESPONSE) {
+            let mut con = mock_connection(stream).await;
+            assert_eq!(con.query(&q).await.unwrap(), rows_response(), "{}", split);
+            assert!(!con.is_broken());
+        }
+        let pipeline = pipe!(
+            query!("select * from myspace.mymodel"),
+            query!("use $current")
+        );
+        for (split, stream) in mock::split_reads(&[ROWS_RESPONSE, b"\x12"].concat()) {
+            let mut con = mock_connection(stream).await;
+            assert_eq!(
+                con.execute_pipeline(&pipeline).await.unwrap(),
+                vec![rows_response(), Response::Empty],
+                "{}",
+                split
+            );
+        }
+    })
+}
+
+#[test]
+fn fed_queries_pending_bytes() {
+    use crate::{
+        io::mock::{MockStream, HANDSHAKE_OKAY},
+        response::Value,
+    };
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        // the first read has the first response and the start of the second one
+        let stream = MockStream::with_handshake(b"\x05100\n\x0D5\nsayan")
+            .split_at([HANDSHAKE_OKAY.len(), HANDSHAKE_OKAY.len() + 8]);
+        let mut con = mock_connection(stream).await;
+        let q = query!("select * from myspace.mymodel");
+        con.feed_query(&q);
+        con.feed_query(&q);
+        assert_eq!(con.pending_write_bytes(), 2 * q.debug_encode_packet().len());
+        assert_eq!(con.pending_read_bytes(), 0);
+        assert_eq!(
+            con.read_response().await.unwrap(),
+            Response::Value(Value::UInt64(100))
+        );
+        assert_eq!(con.pending_write_bytes(), 0);
+        assert_eq!(con.pending_read_bytes(), 3);
+        assert_eq!(
+            con.read_response().await.unwrap(),
+            Response::Value(Value::String("sayan".into()))
+        );
+        assert_eq!(con.pending_read_bytes(), 0);
+    })
+}
+
+#[test]
+fn configured_space() {
+    use crate::io::mock::MockStream;
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let cfg = Config::new_default("username", "password").with_space("myspace");
+        let con = mock_connection_with(&cfg, MockStream::with_handshake(b"\x12")).await;
+        assert_eq!(con.current_space(), Some("myspace"));
+        assert_eq!(
+            con.con.written(),
+            [
+                ClientHandshake::new(&cfg).inner(),
+                &query!("use myspace").debug_encode_packet()
+            ]
+            .concat()
+        );
+        // the space is kept when the connection is split and reunited
+        let (sink, stream) = con.into_split();
+        let con = stream.reunite(sink).unwrap();
+        assert_eq!(con.current_space(), Some("myspace"));
+    })
+}
+
+#[test]
+fn writes_are_flushed() {
+    use crate::io::mock::MockStream;
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let cfg = Config::new_default("username", "password");
+        let mut con =
+            mock_connection(MockStream::with_handshake(b"\x12\x12\x10\x05\x00\x12").chunked(1))
+                .await;
+        let q = query!("use $current");
+        let pipeline = pipe!(query!("use $current"), query!("use nonexistent"));
+        con.query(&q).await.unwrap();
+        con.execute_pipeline(&pipeline).await.unwrap();
+        let (mut sink, mut stream) = con.into_split();
+        sink.send(&q).await.unwrap();
+        stream.next().await.unwrap().unwrap();
+        let con = stream.reunite(sink).unwrap();
+        // the handshake, every query and every pipeline is flushed as soon as it's written
+        let handshake = ClientHandshake::new(&cfg).inner().len();
+        let query = q.debug_encode_packet().len();
+        let pipeline = format!("P{}\n", pipeline.buf().len()).len() + pipeline.buf().len();
+        assert_eq!(
+            con.con.flushed_at(),
+            [
+                handshake,
+                handshake + query,
+                handshake + query + pipeline,
+                handshake + 2 * query + pipeline
+            ]
+        );
+    })
+}
+
+#[test]
+fn run_with_compensation() {
+    use crate::io::mock::MockStream;
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        // the second write fails, so the first one is compensated
+        let mut con =
+            mock_connection(MockStream::with_handshake(b"\x12\x10\x05\x00\x12").chunked(1)).await;
+        let writes = vec![
+            (
+                query!("insert into m(?)", 1u64),
+                query!("delete from m where k = ?", 1u64),
+            ),
+            (
+                query!("insert into m(?)", 2u64),
+                query!("delete from m where k = ?", 2u64),
+            ),
+        ];
+        let report = con.run_with_compensation(writes).await.unwrap_err();
+        assert_eq!(report.failed().len(), 1);
+        assert!(matches!(report.failed()[0], (1, Error::ServerError(5))));
+        assert_eq!(report.compensated(), [0]);
+        assert!(report.is_fully_compensated());
+        assert!(con
+            .con
+            .written()
+            .ends_with(&query!("delete from m where k = ?", 1u64).debug_encode_packet()));
+    })
+}
+
+#[test]
+fn fed_queries() {
+    use crate::response::Value;
+    const QUERIES: usize = 10_000;
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let mut con = indexed_server(QUERIES + 2)
+            .await
+            .connect_async()
+            .await
+            .unwrap();
+        let q = query!("select * from myspace.mymodel where username = ?", "sayan");
+        assert!(con.read_response().await.is_err());
+        for _ in 0..QUERIES {
+            con.feed_query(&q);
+        }
+        assert!(con.is_broken());
+        // can't run other queries until every response has been read
+        assert!(matches!(
+            con.query(&q).await,
+            Err(Error::IoError(e)) if e.kind() == io::ErrorKind::InvalidInput
+        ));
+        con.flush().await.unwrap();
+        for i in 0..QUERIES {
+            assert_eq!(
+                con.read_response().await.unwrap(),
+                Response::Value(Value::UInt64(i as u64))
+            );
+        }
+        assert_eq!(con.unread_responses(), 0);
+        assert!(!con.is_broken());
+        assert_eq!(
+            con.query(&q).await.unwrap(),
+            Response::Value(Value::UInt64(QUERIES as u64))
+        );
+        // and queries can be fed again after running a query
+        con.feed_query(&q);
+        assert_eq!(
+            con.read_response().await.unwrap(),
+            Response::Value(Value::UInt64(QUERIES as u64 + 1))
+        );
+    })
+}
+
+#[test]
+fn partial_pipeline() {
+    use crate::io::mock::MockStream;
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let mut con =
+            mock_connection(MockStream::with_handshake(b"\x12\x10\x05\x00\xFF\x12").chunked(1))
+                .await;
+        let pipeline = pipe!(
+            query!("use $current"),
+            query!("use nonexistent"),
+            query!("use $current"),
+            query!("use $current")
+        );
+        let e = con.execute_pipeline_partial(&pipeline).await.unwrap_err();
+        // server errors are responses, and don't stop the pipeline
+        assert_eq!(e.parsed(), [Response::Empty, Response::Error(5)]);
+        assert_eq!(e.failed_index(), Some(2));
+        assert!(matches!(e.cause(), Error::ProtocolError(..)));
+        assert!(con.is_broken());
+    })
+}
+
+#[test]
+fn failover() {
+    use super::closing_port;
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let (primary, standby) = (closing_port(), crate::pool::mock_server(b"\x12"));
+        let cfg = Config::new("127.0.0.1", primary, "username", "password")
+            .add_fallback("127.0.0.1", standby);
+        let mut con = cfg.connect_async().await.unwrap();
+        assert_eq!(con.info().unwrap().peer_addr().port(), standby);
+        con.query_parse::<()>(&query!("sysctl report status"))
+            .await
+            .unwrap();
+        let cfg = Config::new("127.0.0.1", primary, "username", "password")
+            .add_fallback("127.0.0.1", closing_port());
+        assert!(matches!(
+            cfg.connect_async().await.unwrap_err(),
+            Error::ConnectionSetupErr(ConnectionSetupError::AllEndpointsFailed(errors))
+                if errors.len() == 2
+        ));
+    })
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn metrics() {
+    use {
+        crate::io::mock::MockStream,
+        metrics_util::debugging::{DebugValue, DebuggingRecorder},
+    };
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    metrics::with_local_recorder(&recorder, || {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let mut con =
+                mock_connection(MockStream::with_handshake(b"\x12\x10\x05\x00\x12").chunked(1))
+                    .await;
+            let q = query!("sysctl report status");
+            con.query(&q).await.unwrap();
+            con.feed_query(&q);
+            con.read_response().await.unwrap();
+            let (mut sink, mut stream) = con.into_split();
+            sink.send(&q).await.unwrap();
+            stream.next().await.unwrap().unwrap();
+        })
+    });
+    let counters: Vec<_> = snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .filter_map(|(key, _, _, value)| match value {
+            DebugValue::Counter(n) => Some((key.key().to_string(), n)),
+            _ => None,
+        })
+        .collect();
+    let counter = |key: &str| counters.iter().find(|(k, _)| k == key).unwrap().1;
+    // the responses to the query, the fed query and the query sent by the sink
+    assert_eq!(
+        counter("Key(skytable_client_queries_total, [outcome = ok])"),
+        2
+    );
+    assert_eq!(
+        counter("Key(skytable_client_queries_total, [outcome = server_error])"),
+        1
+    );
+    assert_eq!(counter("Key(skytable_client_bytes_read_total)"), 5);
+}
+
+#[test]
+fn wire_debug() {
+    use crate::{
+        io::wire::{CapturedDump, QUERY_DUMP},
+        response::Value,
+    };
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let dump = CapturedDump::default();
+        let mut con = Config::new(
+            "127.0.0.1",
+            crate::pool::mock_server(b"\x05100\n"),
+            "username",
+            "password",
+        )
+        .with_wire_debug_sink(dump.clone())
+        .connect_async()
+        .await
+        .unwrap();
+        assert_eq!(dump.take(), "");
+        assert_eq!(
+            con.query_parse::<u64>(&query!("sysctl report status"))
+                .await
+                .unwrap(),
+            100
+        );
+        assert_eq!(dump.take(), QUERY_DUMP);
+        // the halves of a split connection dump what they send and receive
+        let (mut sink, mut stream) = con.into_split();
+        sink.send(&query!("sysctl report status")).await.unwrap();
+        assert_eq!(
+            stream.next().await.unwrap().unwrap(),
+            Response::Value(Value::UInt64(100))
+        );
+        assert_eq!(dump.take(), QUERY_DUMP);
+    })
+}
+
+#[test]
+fn query_with_reader() {
+    use crate::{io::mock::MockStream, response::Value};
+    const LEN: usize = 8 * 1024 * 1024;
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let cfg = Config::new_default("username", "password");
+        let handshake_len = ClientHandshake::new(&cfg).inner().len();
+        let value: Vec<u8> = (0..LEN).map(|i| i as u8).collect();
+        let q = query!("insert into myspace.mymodel(?, ?)", "sayan");
+        let connect = || mock_connection(MockStream::with_handshake(b"\x05100\n").chunked(7));
+        let mut con = connect().await;
+        assert_eq!(
+            con.query_with_reader(&q, LEN as u64, &value[..])
+                .await
+                .unwrap(),
+            Response::Value(Value::UInt64(100))
+        );
+        // exactly the same as if the value was part of the query
+        let expected = query!("insert into myspace.mymodel(?, ?)", "sayan", &value[..]);
+        assert_eq!(
+            con.con.written()[handshake_len..],
+            expected.debug_encode_packet()
+        );
+        assert!(!con.is_broken());
+        // a reader that ends early
+        let mut con = connect().await;
+        let e = con
+            .query_with_reader(&q, LEN as u64 + 1, &value[..])
+            .await
+            .unwrap_err();
+        assert!(matches!(e, Error::IoError(ref e) if e.kind() == io::ErrorKind::UnexpectedEof));
+        assert!(con.is_broken());
+    })
+}
+
+#[test]
+fn unsolicited_data() {
+    use crate::{io::mock::MockStream, response::Value};
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let q = query!("select followers from myspace.mymodel");
+        let stream = MockStream::with_handshake(b"\x05200\n").stale(b"\x05100\n");
+        let mut con = mock_connection(stream).await;
+        assert!(matches!(
+            con.query(&q).await.unwrap_err(),
+            Error::ProtocolError(ProtocolError::TrailingData { bytes: 5 }, _)
+        ));
+        assert!(con.is_broken());
+        // nothing is sent
+        let handshake_len = ClientHandshake::new(&Config::new_default("username", "password"))
+            .inner()
+            .len();
+        assert_eq!(con.con.written().len(), handshake_len);
+        // the check doesn't wait for the response that's still on its way
+        let mut con = mock_connection(MockStream::with_handshake(b"\x05200\n")).await;
+        assert_eq!(
+            con.query(&q).await.unwrap(),
+            Response::Value(Value::UInt64(200))
+        );
+        // and neither do pipelines
+        let stream = MockStream::with_handshake(b"\x12").stale(b"\x12");
+        let mut con = mock_connection(stream).await;
+        let e = con
+            .execute_pipeline(&pipe!(query!("use $current")))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            e,
+            Error::ProtocolError(ProtocolError::TrailingData { bytes: 1 }, _)
+        ));
+    })
+}
+
+#[test]
+fn query_timeout() {
+    use tokio::net::TcpListener;
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let cfg = Config::new("127.0.0.1", port, "username", "password");
+        let handshake_len = ClientHandshake::new(&cfg).inner().len();
+        let server = tokio::spawn(async move {
+            let (mut con, _) = listener.accept().await.unwrap();
+            let mut handshake = vec![0; handshake_len];
+            con.read_exact(&mut handshake).await.unwrap();
+            con.write_all(&[b'H', 0, 0, 0]).await.unwrap();
+            // never respond, but keep the connection open until the client goes away
+            let mut buf = [0; 1024];
+            while con.read(&mut buf).await.unwrap_or(0) != 0 {}
+        });
+        let mut con = cfg.connect_async().await.unwrap();
+        con.set_timeout(Some(Duration::from_millis(50)));
+        assert_eq!(con.timeout(), Some(Duration::from_millis(50)));
+        match con.query(&query!("sysctl report status")).await {
+            Err(Error::IoError(e)) => assert_eq!(e.kind(), io::ErrorKind::TimedOut),
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert!(con.is_broken());
+        drop(con);
+        server.await.unwrap();
+    })
+}
@@ -0,0 +1,457 @@
+/*
+ * Copyright 2024, Sayan Nandan <nandansayan@outlook.com>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+*/
+
+//! # Asynchronous database I/O (`async-std`)
+//!
+//! This module provides the necessary items to establish an asynchronous connection to the database server if you're
+//! using the [`async-std`](https://docs.rs/async-std) runtime. It requires the `async-std` feature to be enabled.
+//!
+//! Unlike the [`aio`](crate::aio) module (which is built on Tokio), connections in this module are built on the
+//! [`futures-io`](https://docs.rs/futures-io) `AsyncRead` and `AsyncWrite` traits so you can use them over any stream
+//! that implements them with [`Config::connect_async_std_stream`].
+//!
+//! ## Example
+//!
+//! ```no_run
+//! use skytable::{Config, query};
+//!
+//! async fn run() {
+//!     let mut db = Config::new_default("username", "password").connect_async_std().await.unwrap();
+//!     db.query_parse::<()>(&query!("sysctl report status")).await.unwrap();
+//! }
+//! ```
+
+use {
+    crate::{
+        error::{
+            ClientResult, CompensationReport, ConnectionSetupError, Error, PipelineError,
+            DEFAULT_RESPONSE_CAPTURE_LEN,
+        },
+        io::{async_common::FedQueries, metrics::Metrics, wire::WireDump, ConnectionInfo},
+        protocol::{
+            check_trailing,
+            handshake::{ClientHandshake, ServerHandshake},
+            DecodeState, Decoder, MRespState, PipelineResult, ProtocolError, RState,
+        },
+        query::Pipeline,
+        response::{FromResponse, Response},
+        Config, Query,
+    },
+    async_std::{
+        future::timeout,
+        io::{Read, ReadExt, Write, WriteExt},
+        net::TcpStream,
+    },
+    std::{
+        future::{self, Future},
+        io,
+        ops::{Deref, DerefMut},
+        pin::Pin,
+        time::{Duration, Instant},
+    },
+};
+
+#[derive(Debug)]
+/// An async `skyhash/TCP` connection for the `async-std` runtime
+///
+/// **Specification**
+/// - Protocol version: `Skyhash/2.0`
+/// - Query mode: `QTDEX-1A/BQL-S1`
+/// - Authentication plugin: `pwd`
+pub struct ConnectionAsyncStd(TcpConnection<TcpStream>);
+
+impl Deref for ConnectionAsyncStd {
+    type Target = TcpConnection<TcpStream>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl DerefMut for ConnectionAsyncStd {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// A connection over any stream that implements the `futures-io` `AsyncRead` and `AsyncWrite` traits. Establish one
+/// with [`Connection::new`] (or [`Config::connect_async_std_stream`])
+pub type Connection<C> = TcpConnection<C>;
+
+impl Config {
+    /// Establish an async connection to the database on the `async-std` runtime, using the current configuration
+    pub async fn connect_async_std(&self) -> ClientResult<ConnectionAsyncStd> {
+        self.connect_endpoints_async(|cfg| async move {
+            match cfg.addr() {
+                Some(addr) => TcpStream::connect(addr).await,
+                None => TcpStream::connect((cfg.host(), cfg.port())).await,
+            }
+            .and_then(|con| {
+                let info = ConnectionInfo::new(&cfg, con.peer_addr()?, con.local_addr()?, false);
+                let mut con = TcpConnection::from_stream(con, Metrics::new(cfg.metrics_prefix()));
+                con.info = Some(info);
+                Ok(con)
+            })?
+            ._handshake(&cfg)
+            .await
+        })
+        .await
+        .map(ConnectionAsyncStd)
+    }
+    /// Establish an async connection to the database over an already connected stream, using the current configuration
+    /// for the handshake. The stream can be anything that implements the `futures-io` `AsyncRead` and `AsyncWrite`
+    /// traits (for example, a TLS stream or a Tokio stream wrapped in a compatibility layer).
+    pub async fn connect_async_std_stream<C: Read + Write + Unpin>(
+        &self,
+        stream: C,
+    ) -> ClientResult<TcpConnection<C>> {
+        TcpConnection::new(stream, self).await
+    }
+}
+
+#[derive(Debug)]
+/// The underlying socket type
+pub struct TcpConnection<C: Read + Write + Unpin> {
+    con: C,
+    buf: Vec<u8>,
+    /// set while a query is running (and left set if it doesn't run to completion)
+    broken: bool,
+    space: Option<Box<str>>,
+    /// queries that were fed but not flushed yet
+    fed: FedQueries,
+    /// the number of fed queries whose responses haven't been read yet
+    unread: usize,
+    timeout: Option<Duration>,
+    /// not available for connections over a caller-provided stream
+    info: Option<ConnectionInfo>,
+    metrics: Metrics,
+    wire: WireDump,
+    response_capture_len: usize,
+}
+
+impl<C: Read + Write + Unpin> TcpConnection<C> {
+    /// Establish a connection over an already connected stream, using `cfg` for the handshake
+    pub async fn new(stream: C, cfg: &Config) -> ClientResult<Self> {
+        Self::from_stream(stream, Metrics::new(cfg.metrics_prefix()))
+            ._handshake(cfg)
+            .await
+    }
+    fn from_stream(con: C, metrics: Metrics) -> Self {
+        Self {
+            con,
+            buf: Vec::with_capacity(crate::BUFSIZE),
+            broken: false,
+            space: None,
+            fed: FedQueries::default(),
+            unread: 0,
+            timeout: None,
+            info: None,
+            metrics,
+            wire: WireDump::off(),
+            response_capture_len: DEFAULT_RESPONSE_CAPTURE_LEN,
+        }
+    }
+    /// Returns information about this connection, such as the address of the server. This is not available for
+    /// connections created with [`Connection::new`] or [`Config::connect_async_std_stream`]
+    pub fn info(&self) -> Option<&ConnectionInfo> {
+        self.info.as_ref()
+    }
+}
+
+impl_async_connection!({ Read + Write + Unpin }, { Read + Unpin });
+
+/// Close the write side of `con`
+async fn shutdown<C: Write + Unpin>(con: &mut C) -> io::Result<()> {
+    future::poll_fn(|cx| Pin::new(&mut *con).poll_close(cx)).await
+}
+
+/// Run `f`, failing with [`TimedOut`](io::ErrorKind::TimedOut) if it doesn't complete within `limit`
+async fn with_timeout<T, E: From<Error>>(
+    limit: Option<Duration>,
+    f: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    match limit {
+        Some(limit) => timeout(limit, f)
+            .await
+            .unwrap_or_else(|_| Err(Error::IoError(io::ErrorKind::TimedOut.into()).into())),
+        None => f.await,
+    }
+}
+
+#[test]
+fn futures_io_query() {
+    use crate::{io::mock::MockStream, response::Value};
+    async_std::task::block_on(async {
+        let cfg = Config::new_default("username", "password");
+        let q = query!(
+            "select followers from myspace.mymodel where username = ?",
+            "sayan"
+        );
+        // respond with a u64 one byte at a time
+        let mut con = cfg
+            .connect_async_std_stream(MockStream::with_handshake(b"\x05100\n").chunked(1))
+            .await
+            .unwrap();
+        assert_eq!(
+            con.query(&q).await.unwrap(),
+            Response::Value(Value::UInt64(100))
+        );
+        assert_eq!(
+            con.con.written(),
+            [ClientHandshake::new(&cfg).inner(), &q.debug_encode_packet()].concat()
+        );
+        // the handshake and the query are flushed as soon as they're written
+        let handshake_len = ClientHandshake::new(&cfg).inner().len();
+        assert_eq!(
+            con.con.flushed_at(),
+            [handshake_len, handshake_len + q.debug_encode_packet().len()]
+        );
+    })
+}
+
+#[test]
+fn futures_io_unsolicited_data() {
+    use crate::io::mock::MockStream;
+    async_std::task::block_on(async {
+        let cfg = Config::new_default("username", "password");
+        let stream = MockStream::with_handshake(b"\x05200\n").stale(b"\x05100\n");
+        let mut con = Connection::new(stream, &cfg).await.unwrap();
+        assert!(matches!(
+            con.query(&query!("select followers from myspace.mymodel"))
+                .await
+                .unwrap_err(),
+            Error::ProtocolError(ProtocolError::TrailingData { bytes: 5 }, _)
+        ));
+        assert!(con.is_broken());
+    })
+}
+
+#[test]
+fn futures_io_pipeline() {
+    use crate::io::mock::MockStream;
+    async_std::task::block_on(async {
+        let cfg = Config::new_default("username", "password");
+        let mut con = Connection::new(MockStream::with_handshake(b"\x12\x10\x05\x00"), &cfg)
+            .await
+            .unwrap();
+        assert!(con.info().is_none());
+        let pipeline = pipe!(query!("use $current"), query!("use nonexistent"));
+        assert_eq!(
+            con.execute_pipeline(&pipeline).await.unwrap(),
+            vec![Response::Empty, Response::Error(5)]
+        );
+    })
+}
+
+#[test]
+fn futures_io_query_with_reader() {
+    use crate::{io::mock::MockStream, response::Value};
+    const LEN: usize = 1024 * 1024;
+    async_std::task::block_on(async {
+        let cfg = Config::new_default("username", "password");
+        let handshake_len = ClientHandshake::new(&cfg).inner().len();
+        let value: Vec<u8> = (0..LEN).map(|i| i as u8).collect();
+        let q = query!("insert into myspace.mymodel(?, ?)", "sayan");
+        let mut con = Connection::new(MockStream::with_handshake(b"\x05100\n"), &cfg)
+            .await
+            .unwrap();
+        assert_eq!(
+            con.query_with_reader(&q, LEN as u64, &value[..])
+                .await
+                .unwrap(),
+            Response::Value(Value::UInt64(100))
+        );
+        let expected = query!("insert into myspace.mymodel(?, ?)", "sayan", &value[..]);
+        assert_eq!(
+            con.con.written()[handshake_len..],
+            expected.debug_encode_packet()
+        );
+        let mut con = Connection::new(MockStream::with_handshake(b"\x05100\n"), &cfg)
+            .await
+            .unwrap();
+        let e = con
+            .query_with_reader(&q, LEN as u64 + 1, &value[..])
+            .await
+            .unwrap_err();
+        assert!(matches!(e, Error::IoError(ref e) if e.kind() == io::ErrorKind::UnexpectedEof));
+        assert!(con.is_broken());
+    })
+}
+
+#[test]
+fn futures_io_compensation() {
+    use crate::io::mock::MockStream;
+    let writes = || {
+        vec![
+            (
+                query!("insert into m(?)", 1u64),
+                query!("delete from m where k = ?", 1u64),
+            ),
+            (
+                query!("insert into m(?)", 2u64),
+                query!("delete from m where k = ?", 2u64),
+            ),
+        ]
+    };
+    async_std::task::block_on(async {
+        let cfg = Config::new_default("username", "password");
+        // the second write fails and so does the compensation of the first one
+        let mut con = Connection::new(
+            MockStream::with_handshake(b"\x12\x10\x05\x00\x10\x06\x00").chunked(1),
+            &cfg,
+        )
+        .await
+        .unwrap();
+        let report = con.run_with_compensation(writes()).await.unwrap_err();
+        assert!(report.pipeline_error().is_none());
+        assert!(matches!(report.failed(), [(1, Error::ServerError(5))]));
+        assert!(report.compensated().is_empty());
+        assert!(matches!(
+            report.compensation_errors(),
+            [(0, Error::ServerError(6))]
+        ));
+        assert!(!report.is_fully_compensated());
+        // the pipeline fails as a whole, so nothing is compensated
+        let mut con = Connection::new(MockStream::with_handshake(b"\x12"), &cfg)
+            .await
+            .unwrap();
+        let report = con.run_with_compensation(writes()).await.unwrap_err();
+        assert!(report.pipeline_error().is_some());
+        assert!(report.failed().is_empty());
+        assert!(!report.is_fully_compensated());
+        assert!(con.is_broken());
+    })
+}
+
+#[test]
+fn futures_io_unexpected_eof() {
+    use crate::io::mock::MockStream;
+    async_std::task::block_on(async {
+        let cfg = Config::new_default("username", "password");
+        // the server goes away halfway through a row
+        let mut con = Connection::new(
+            MockStream::with_handshake(b"\x115\n\x00\x01\x01\x0D5\nsay"),
+            &cfg,
+        )
+        .await
+        .unwrap();
+        let q = query!("select * from myspace.mymodel where username = ?", "sayan");
+        match con.query(&q).await.unwrap_err() {
+            Error::ProtocolError(ProtocolError::UnexpectedEof { received, parsing }, _) => {
+                assert_eq!(received, 12);
+                assert_eq!(parsing, "row");
+            }
+            e => panic!("expected unexpected eof, got {:?}", e),
+        }
+        assert!(con.is_broken());
+    })
+}
+
+#[test]
+fn futures_io_partial_pipeline() {
+    use crate::io::mock::MockStream;
+    async_std::task::block_on(async {
+        let cfg = Config::new_default("username", "password");
+        let pipeline = pipe!(
+            query!("use $current"),
+            query!("use nonexistent"),
+            query!("use $current"),
+            query!("use $current")
+        );
+        // the third response is corrupt
+        let mut con = Connection::new(
+            MockStream::with_handshake(b"\x12\x10\x05\x00\xFF\x12").chunked(1),
+            &cfg,
+        )
+        .await
+        .unwrap();
+        let e = con.execute_pipeline_partial(&pipeline).await.unwrap_err();
+        assert_eq!(e.parsed(), [Response::Empty, Response::Error(5)]);
+        assert_eq!(e.failed_index(), Some(2));
+        assert!(matches!(e.cause(), Error::ProtocolError(..)));
+        assert!(con.is_broken());
+        // the server hung up halfway through
+        let mut con = Connection::new(MockStream::with_handshake(b"\x12"), &cfg)
+            .await
+            .unwrap();
+        let e = con.execute_pipeline_partial(&pipeline).await.unwrap_err();
+        assert_eq!(e.failed_index(), Some(1));
+        assert!(matches!(
+            e.cause(),
+            Error::ProtocolError(ProtocolError::UnexpectedEof { .. }, _)
+        ));
+        assert!(con.is_broken());
+    })
+}
+
+#[test]
+fn futures_io_handshake_error() {
+    use crate::io::mock::MockStream;
+    async_std::task::block_on(async {
+        let e = Config::new_default("username", "password")
+            .connect_async_std_stream(MockStream::new([b'H', 0, 1, 10]))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            e,
+            Error::ConnectionSetupErr(ConnectionSetupError::HandshakeError(10))
+        ));
+    })
+}
+
+#[test]
+fn futures_io_split_reads() {
+    use crate::io::mock::{self, rows_response, ROWS_RESPONSE};
+    async_std::task::block_on(async {
+        let cfg = Config::new_default("username", "password");
+        let q = query!("select * from myspace.mymodel");
+        for (split, stream) in mock::split_reads(ROWS_RESPONSE) {
+            let mut con = cfg.connect_async_std_stream(stream).await.unwrap();
+            assert_eq!(con.query(&q).await.unwrap(), rows_response(), "{}", split);
+        }
+    })
+}
+
+#[test]
+fn futures_io_timeout() {
+    use async_std::net::TcpListener;
+    async_std::task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let cfg = Config::new("127.0.0.1", port, "username", "password");
+        let handshake_len = ClientHandshake::new(&cfg).inner().len();
+        let server = async_std::task::spawn(async move {
+            let (mut con, _) = listener.accept().await.unwrap();
+            let mut handshake = vec![0; handshake_len];
+            con.read_exact(&mut handshake).await.unwrap();
+            con.write_all(&[b'H', 0, 0, 0]).await.unwrap();
+            // never respond, but keep the connection open until the client goes away
+            let mut buf = [0; 1024];
+            while con.read(&mut buf).await.unwrap_or(0) != 0 {}
+        });
+        let mut con = cfg.connect_async_std().await.unwrap();
+        let info = con.info().unwrap();
+        assert_eq!(info.peer_addr().port(), port);
+        assert_eq!(info.protocol_version(), "Skyhash/2.0");
+        assert!(!info.is_tls());
+        con.set_timeout(Some(Duration::from_millis(50)));
+        match con.query(&query!("sysctl report status")).await {
+            Err(Error::IoError(e)) => assert_eq!(e.kind(), io::ErrorKind::TimedOut),
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert!(con.is_broken());
+        drop(con);
+        server.await;
+    })
+}
@@ -0,0 +1,555 @@
+/*
+ * Copyright 2024, Sayan Nandan <nandansayan@outlook.com>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+*/
+
+//! The query and response handling that is shared by the async connections of every runtime. The I/O extension traits
+//! of Tokio and `futures-io` have methods with the same names (`read`, `read_exact`, `write_all` and `flush`), so the
+//! same code is expanded in the [`aio`](super::aio) and [`astd`](super::astd) modules. Each module provides what does
+//! differ between the runtimes:
+//!
+//! - a `TcpConnection<C>` struct with the fields used here (the fed queries are kept in a [`FedQueries`])
+//! - `async fn shutdown(con: &mut C) -> io::Result<()>`, which closes the write side of the stream
+//! - `async fn with_timeout(limit: Option<Duration>, f: impl Future<Output = Result<T, E>>) -> Result<T, E>`, which
+//!   fails with [`TimedOut`](std::io::ErrorKind::TimedOut) if `f` doesn't complete within `limit`
+
+use core::ops::{Deref, DerefMut};
+
+#[derive(Debug, Default)]
+/// The queries that were fed to a connection but not flushed yet. There's no async drop, so they can't be sent when the
+/// connection is dropped: with the `tracing` feature, a warning is emitted instead. (This is a separate type so that
+/// the connection itself can still be taken apart, as `into_split` does.)
+pub(super) struct FedQueries(Vec<u8>);
+
+impl Deref for FedQueries {
+    type Target = Vec<u8>;
+    fn deref(&self) -> &Vec<u8> {
+        &self.0
+    }
+}
+
+impl DerefMut for FedQueries {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.0
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for FedQueries {
+    fn drop(&mut self) {
+        if !self.0.is_empty() {
+            tracing::warn!(
+                "dropped a connection with {} bytes of fed queries that weren't flushed",
+                self.0.len()
+            );
+        }
+    }
+}
+
+/// Implement the query methods of an async `TcpConnection<C>`, for every `C` that satisfies the first bounds. The second
+/// bounds are the ones of the readers accepted by `query_with_reader`.
+macro_rules! impl_async_connection {
+    ({ $($bounds:tt)+ }, { $($reader:tt)+ }) => {
+        impl<C: $($bounds)+> TcpConnection<C> {
+            /// Returns true if this connection can no longer be used, because it was [closed](Self::close) or because a
+            /// query didn't run to completion (for example, if it failed with an I/O error or if its future was dropped
+            /// before the response was received). The connection pool discards broken connections instead of reusing
+            /// them.
+            ///
+            /// Connections with [fed](Self::feed_query) queries that weren't [flushed](Self::flush) or whose responses
+            /// haven't been read are also considered broken, since the next user would send those queries or receive
+            /// their responses.
+            pub fn is_broken(&self) -> bool {
+                self.broken || self.unread != 0 || !self.fed.is_empty()
+            }
+            /// Switch to `space`, so that later queries can use model names without the space. The connection remembers
+            /// the space (see [`current_space`](Self::current_space)).
+            ///
+            /// **Note**: Only spaces set with this method (or [`Config::with_space`]) are tracked, and not ones set by
+            /// running a `use` query directly.
+            pub async fn use_space(&mut self, space: &str) -> ClientResult<()> {
+                self.query_parse::<()>(&super::use_space_query(space)?)
+                    .await?;
+                self.space = Some(space.into());
+                Ok(())
+            }
+            /// Returns the space that was last set with [`use_space`](Self::use_space) (or [`Config::with_space`])
+            pub fn current_space(&self) -> Option<&str> {
+                self.space.as_deref()
+            }
+            /// Returns the protocol error for the response that couldn't be decoded because of `e`, along with its
+            /// start (see [`CapturedResponse`](crate::error::CapturedResponse))
+            fn invalid_response(&self, e: ProtocolError) -> Error {
+                Error::invalid_response(e, &self.buf, self.response_capture_len)
+            }
+            /// Set the time that a query, a pipeline or [reading a response](Self::read_response) can take to complete,
+            /// including the time spent sending it. Set to `None` (the default) to wait indefinitely.
+            ///
+            /// If it expires, an [`Error::IoError`] with [`TimedOut`](std::io::ErrorKind::TimedOut) is returned and the
+            /// connection is [broken](Self::is_broken), since the response may still be in flight.
+            pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+                self.timeout = timeout;
+            }
+            /// Returns the time that a query can take to complete (see [`set_timeout`](Self::set_timeout))
+            pub fn timeout(&self) -> Option<Duration> {
+                self.timeout
+            }
+            /// Flush any pending data and gracefully shut down the connection.
+            ///
+            /// Since a connection can't flush (or tell the server that it's going away) when it is dropped, call this
+            /// when you're done with a connection. Pooled connections that are closed are discarded (and not returned
+            /// to the pool).
+            pub async fn close(&mut self) -> ClientResult<()> {
+                self.broken = true;
+                self.con.flush().await?;
+                shutdown(&mut self.con).await?;
+                Ok(())
+            }
+            async fn _handshake(mut self, cfg: &Config) -> ClientResult<Self> {
+                let handshake = ClientHandshake::new(cfg);
+                self.con.write_all(handshake.inner()).await?;
+                self.con.flush().await?;
+                let mut resp = [0u8; 4];
+                self.con.read_exact(&mut resp).await?;
+                match ServerHandshake::parse(resp)? {
+                    ServerHandshake::Error(e) => {
+                        Err(ConnectionSetupError::HandshakeError(e).into())
+                    }
+                    ServerHandshake::Okay(_suggestion) => {
+                        // the handshake has the credentials, so it isn't dumped
+                        self.wire = WireDump::new(cfg);
+                        self.response_capture_len = cfg.response_capture_len();
+                        if let Some(space) = cfg.space() {
+                            self.use_space(space).await?;
+                        }
+                        Ok(self)
+                    }
+                }
+            }
+            /// Encode a query and add it to the write buffer, without sending it. This is useful to send a large number
+            /// of queries without waiting for each response (and without building a [`Pipeline`] for all of them):
+            ///
+            /// 1. Feed queries with this method
+            /// 2. Send them with [`flush`](Self::flush)
+            /// 3. Read each response (in the order that the queries were fed) with
+            ///    [`read_response`](Self::read_response)
+            ///
+            /// Until every response has been read, [`query`](Self::query) and
+            /// [`execute_pipeline`](Self::execute_pipeline) return an
+            /// [`InvalidInput`](std::io::ErrorKind::InvalidInput) error (since they would receive the wrong responses).
+            pub fn feed_query(&mut self, q: &Query) {
+                if self.unread == 0 {
+                    // the buffer still holds the response to the last query (or pipeline)
+                    self.buf.clear();
+                }
+                q.write_packet(&mut self.fed);
+                self.unread += 1;
+            }
+            /// Send all the queries that were [fed](Self::feed_query) and not sent yet
+            pub async fn flush(&mut self) -> ClientResult<()> {
+                if self.fed.is_empty() {
+                    return Ok(());
+                }
+                self.broken = true;
+                self.wire.written(&self.fed);
+                self.con.write_all(&self.fed).await?;
+                self.con.flush().await?;
+                self.metrics.written(self.fed.len());
+                self.fed.clear();
+                self.broken = false;
+                Ok(())
+            }
+            /// Returns the number of [fed](Self::feed_query) queries whose responses haven't been read yet
+            pub fn unread_responses(&self) -> usize {
+                self.unread
+            }
+            /// Returns the number of bytes of the [fed](Self::feed_query) queries that weren't [flushed](Self::flush)
+            /// yet
+            pub fn pending_write_bytes(&self) -> usize {
+                self.fed.len()
+            }
+            /// Returns the number of bytes that were received but not decoded into a response yet (such as the
+            /// responses to [fed](Self::feed_query) queries that arrived along with an earlier response)
+            pub fn pending_read_bytes(&self) -> usize {
+                // otherwise, the buffer only holds the last query or response (which was already
+                // used)
+                if self.unread == 0 {
+                    0
+                } else {
+                    self.buf.len()
+                }
+            }
+            /// Read the response to the oldest [fed](Self::feed_query) query whose response hasn't been read yet, first
+            /// [flushing](Self::flush) any queries that weren't sent. If there are no such queries, an
+            /// [`InvalidInput`](std::io::ErrorKind::InvalidInput) error is returned.
+            pub async fn read_response(&mut self) -> ClientResult<Response> {
+                if self.unread == 0 {
+                    return Err(Error::IoError(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "there are no responses to read",
+                    )));
+                }
+                let r = with_timeout(self.timeout, self._read_response()).await;
+                self.metrics.outcome(r.as_ref());
+                r
+            }
+            async fn _read_response(&mut self) -> ClientResult<Response> {
+                self.flush().await?;
+                self.broken = true;
+                let mut state = RState::default();
+                let mut cursor = 0;
+                loop {
+                    if cursor < self.buf.len() {
+                        let (_state, _position) =
+                            Decoder::new(&self.buf, cursor).validate_response(state);
+                        match _state {
+                            DecodeState::Completed(resp) => {
+                                // the rest of the buffer belongs to the next responses
+                                self.buf.drain(.._position);
+                                self.unread -= 1;
+                                if self.unread == 0 {
+                                    check_trailing(&self.buf, 0)?;
+                                }
+                                self.broken = false;
+                                return Ok(resp);
+                            }
+                            DecodeState::ChangeState(_state) => {
+                                state = _state;
+                                cursor = _position;
+                            }
+                            DecodeState::Error(e) => return Err(self.invalid_response(e)),
+                        }
+                    }
+                    if self.read_more().await? == 0 {
+                        return Err(ProtocolError::UnexpectedEof {
+                            received: self.buf.len(),
+                            parsing: state.parsing(),
+                        }
+                        .into());
+                    }
+                }
+            }
+            /// Read from the connection into the end of the buffer, returning the number of bytes read. Reading into a
+            /// buffer on the stack instead would make every future that reads a response much larger. If the future is
+            /// dropped while reading, the buffer is left with garbage at the end, but the connection is broken anyway.
+            async fn read_more(&mut self) -> io::Result<usize> {
+                let len = self.buf.len();
+                self.buf.resize(len + crate::BUFSIZE, 0);
+                let r = self.con.read(&mut self.buf[len..]).await;
+                self.buf.truncate(len + *r.as_ref().unwrap_or(&0));
+                let n = r?;
+                self.metrics.read(n);
+                self.wire.read(&self.buf[len..]);
+                Ok(n)
+            }
+            /// Make sure that the server hasn't sent anything that we didn't ask for, since it would be mistaken for
+            /// the response to the next query. This only reads what was already received: the read is polled once
+            /// with a no-op waker (so that the task isn't woken up for a read that is abandoned), and read futures
+            /// don't lose any data when they're dropped before completing.
+            ///
+            /// A connection that the server closed is left as it is, so the query fails when reading its response.
+            fn check_unsolicited(&mut self) -> ClientResult<()> {
+                let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+                self.buf.clear();
+                loop {
+                    let len = self.buf.len();
+                    self.buf.resize(len + crate::BUFSIZE, 0);
+                    let read = core::pin::pin!(self.con.read(&mut self.buf[len..]));
+                    let n = match core::future::Future::poll(read, &mut cx) {
+                        std::task::Poll::Ready(Ok(n)) => n,
+                        std::task::Poll::Ready(Err(e)) => {
+                            self.buf.truncate(len);
+                            self.broken = true;
+                            return Err(e.into());
+                        }
+                        std::task::Poll::Pending => 0,
+                    };
+                    self.buf.truncate(len + n);
+                    if n == 0 {
+                        break;
+                    }
+                    self.metrics.read(n);
+                    self.wire.read(&self.buf[len..]);
+                }
+                if self.buf.is_empty() {
+                    return Ok(());
+                }
+                self.broken = true;
+                Err(ProtocolError::TrailingData {
+                    bytes: self.buf.len(),
+                }
+                .into())
+            }
+            fn check_no_unread(&self) -> ClientResult<()> {
+                if self.unread != 0 {
+                    return Err(Error::IoError(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "can't run a query while the responses to fed queries haven't been read",
+                    )));
+                }
+                Ok(())
+            }
+            /// Execute a pipeline. The server returns the queries in the order they were sent (unless otherwise set).
+            pub async fn execute_pipeline(
+                &mut self,
+                pipeline: &Pipeline,
+            ) -> ClientResult<Vec<Response>> {
+                self.execute_pipeline_partial(pipeline)
+                    .await
+                    .map_err(PipelineError::into_cause)
+            }
+            /// Same as [`execute_pipeline`](Self::execute_pipeline), but if the pipeline fails, the error also has the
+            /// responses to the queries before the one that failed (see [`PipelineError`]). The connection is
+            /// [broken](Self::is_broken) after a failure, since the rest of the responses may still be in flight.
+            pub async fn execute_pipeline_partial(
+                &mut self,
+                pipeline: &Pipeline,
+            ) -> Result<Vec<Response>, PipelineError> {
+                pipeline.check_not_empty()?;
+                self.check_no_unread()?;
+                let start = Instant::now();
+                let r = with_timeout(self.timeout, self._execute_pipeline(pipeline)).await;
+                self.metrics.pipeline(pipeline.query_count(), &r, start);
+                r
+            }
+            async fn _execute_pipeline(
+                &mut self,
+                pipeline: &Pipeline,
+            ) -> Result<Vec<Response>, PipelineError> {
+                self.check_unsolicited()?;
+                self.broken = true;
+                self.write_pipeline(pipeline).await?;
+                // read
+                let mut cursor = 0;
+                let mut state = MRespState::default();
+                loop {
+                    let n = match self.read_more().await {
+                        Ok(n) => n,
+                        Err(e) => return Err(PipelineError::new(state.into_processed(), e.into())),
+                    };
+                    if n == 0 {
+                        let e = ProtocolError::UnexpectedEof {
+                            received: self.buf.len(),
+                            parsing: state.parsing(),
+                        };
+                        return Err(PipelineError::new(state.into_processed(), e.into()));
+                    }
+                    let (_state, _position) =
+                        Decoder::new(&self.buf, cursor)
+                            .validate_pipe(pipeline.query_count(), state);
+                    match _state {
+                        PipelineResult::Completed(r) => {
+                            if let Err(e) = check_trailing(&self.buf, _position) {
+                                return Err(PipelineError::trailing_data(r, e.into()));
+                            }
+                            self.broken = false;
+                            return Ok(r);
+                        }
+                        PipelineResult::Pending(_state) => {
+                            cursor = _position;
+                            state = _state;
+                        }
+                        PipelineResult::Error(e, parsed) => {
+                            return Err(PipelineError::new(parsed, self.invalid_response(e)));
+                        }
+                    }
+                }
+            }
+            async fn write_pipeline(&mut self, pipeline: &Pipeline) -> ClientResult<()> {
+                self.buf.clear();
+                self.buf.push(b'P');
+                // packet size
+                self.buf
+                    .extend(itoa::Buffer::new().format(pipeline.buf().len()).as_bytes());
+                self.buf.push(b'\n');
+                // write
+                self.wire.written(&self.buf);
+                self.con.write_all(&self.buf).await?;
+                self.wire.written(pipeline.buf());
+                self.con.write_all(pipeline.buf()).await?;
+                self.con.flush().await?;
+                self.metrics.written(self.buf.len() + pipeline.buf().len());
+                self.buf.clear();
+                Ok(())
+            }
+            /// Run a query and return a raw [`Response`]
+            pub async fn query(&mut self, q: &Query) -> ClientResult<Response> {
+                self.check_no_unread()?;
+                let start = Instant::now();
+                let r = with_timeout(self.timeout, self._query(q)).await;
+                self.metrics.query(r.as_ref(), start);
+                r
+            }
+            async fn _query(&mut self, q: &Query) -> ClientResult<Response> {
+                self.check_unsolicited()?;
+                self.broken = true;
+                self.buf.clear();
+                q.write_packet(&mut self.buf);
+                self.wire.written(&self.buf);
+                self.con.write_all(&self.buf).await?;
+                self.con.flush().await?;
+                self.metrics.written(self.buf.len());
+                self.buf.clear();
+                self.read_single_response().await
+            }
+            /// Run a query with an additional binary parameter (following the parameters of the query) that is streamed
+            /// from `reader` directly to the connection, instead of being copied into the query first. This avoids
+            /// holding very large values in memory twice.
+            ///
+            /// The reader must produce at least `len` bytes (only `len` bytes are read). If it ends early, an
+            /// [`Error::IoError`] with [`UnexpectedEof`](std::io::ErrorKind::UnexpectedEof) is returned and the
+            /// connection is [broken](Self::is_broken), since an incomplete query was sent.
+            pub async fn query_with_reader(
+                &mut self,
+                q: &Query,
+                len: u64,
+                reader: impl $($reader)+,
+            ) -> ClientResult<Response> {
+                self.check_no_unread()?;
+                let start = Instant::now();
+                let r = with_timeout(self.timeout, self._query_with_reader(q, len, reader)).await;
+                self.metrics.query(r.as_ref(), start);
+                r
+            }
+            async fn _query_with_reader(
+                &mut self,
+                q: &Query,
+                len: u64,
+                mut reader: impl $($reader)+,
+            ) -> ClientResult<Response> {
+                self.check_unsolicited()?;
+                self.broken = true;
+                self.buf.clear();
+                q.write_packet_with_streamed_param(&mut self.buf, len);
+                self.wire.written(&self.buf);
+                self.con.write_all(&self.buf).await?;
+                self.metrics.written(self.buf.len());
+                self.buf.clear();
+                let mut remaining = len;
+                let mut chunk = vec![0u8; crate::BUFSIZE];
+                while remaining != 0 {
+                    let max = (chunk.len() as u64).min(remaining) as usize;
+                    let n = reader.read(&mut chunk[..max]).await?;
+                    if n == 0 {
+                        return Err(Error::IoError(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "the reader ended before the declared length of the parameter",
+                        )));
+                    }
+                    self.wire.written(&chunk[..n]);
+                    self.con.write_all(&chunk[..n]).await?;
+                    self.metrics.written(n);
+                    remaining -= n as u64;
+                }
+                self.con.flush().await?;
+                self.read_single_response().await
+            }
+            /// Read the response to a query that was just sent (into an empty buffer)
+            async fn read_single_response(&mut self) -> ClientResult<Response> {
+                let mut state = RState::default();
+                let mut cursor = 0;
+                loop {
+                    if self.read_more().await? == 0 {
+                        return Err(ProtocolError::UnexpectedEof {
+                            received: self.buf.len(),
+                            parsing: state.parsing(),
+                        }
+                        .into());
+                    }
+                    let (_state, _position) =
+                        Decoder::new(&self.buf, cursor).validate_response(state);
+                    match _state {
+                        DecodeState::Completed(resp) => {
+                            check_trailing(&self.buf, _position)?;
+                            self.broken = false;
+                            return Ok(resp);
+                        }
+                        DecodeState::ChangeState(_state) => {
+                            state = _state;
+                            cursor = _position;
+                        }
+                        DecodeState::Error(e) => return Err(self.invalid_response(e)),
+                    }
+                }
+            }
+            /// Run the queries one after the other (each query is only sent once the response to the previous one has
+            /// been received) and return all the responses.
+            ///
+            /// If a query fails with an I/O or protocol error (or returns a server error, if `stop_on_error` is set),
+            /// the remaining queries are not run and the index of the failed query is returned along with the error and
+            /// the responses to the queries before it. Otherwise, server errors are returned as [`Response::Error`].
+            pub async fn execute_many(
+                &mut self,
+                queries: &[Query],
+                stop_on_error: bool,
+            ) -> Result<Vec<Response>, (usize, Error, Vec<Response>)> {
+                let mut responses = Vec::with_capacity(queries.len());
+                for (i, q) in queries.iter().enumerate() {
+                    match self.query(q).await {
+                        Ok(Response::Error(code)) if stop_on_error => {
+                            return Err((i, Error::ServerError(code), responses))
+                        }
+                        Ok(resp) => responses.push(resp),
+                        Err(e) => return Err((i, e, responses)),
+                    }
+                }
+                Ok(responses)
+            }
+            /// Run the writes in `writes` as a pipeline, and if any of them fails, run the compensating query (the
+            /// second query of each pair) for every write that succeeded, in reverse order. This is a client-side
+            /// emulation of a transaction: for example, each `insert` can be paired with a `delete` of the same row.
+            ///
+            /// A write succeeds if it returns an empty response. The compensating queries are run one by one (on a
+            /// best-effort basis, so a failed compensation doesn't stop the rest) and the returned
+            /// [`CompensationReport`] describes the failed writes along with the outcome of each compensation. If the
+            /// pipeline fails as a whole (for example, with an I/O error) it isn't known which writes were applied, so
+            /// nothing is compensated.
+            pub async fn run_with_compensation(
+                &mut self,
+                writes: Vec<(Query, Query)>,
+            ) -> Result<(), CompensationReport> {
+                if writes.is_empty() {
+                    return Ok(());
+                }
+                let pipeline: Pipeline = writes.iter().map(|(write, _)| write).collect();
+                let responses = self
+                    .execute_pipeline(&pipeline)
+                    .await
+                    .map_err(CompensationReport::pipeline_failed)?;
+                let (succeeded, failed) = super::check_writes(responses);
+                if failed.is_empty() {
+                    return Ok(());
+                }
+                let mut report = CompensationReport::writes_failed(failed);
+                for i in succeeded.into_iter().rev() {
+                    let r = self
+                        .query(&writes[i].1)
+                        .await
+                        .and_then(Response::ensure_empty);
+                    report.record_compensation(i, r);
+                }
+                Err(report)
+            }
+            /// Run and parse a query into the indicated type. The type must implement [`FromResponse`]
+            pub async fn query_parse<T: FromResponse>(&mut self, q: &Query) -> ClientResult<T> {
+                self.query(q).await.and_then(FromResponse::from_response)
+            }
+            /// Call this if the internally allocated buffer is growing too large and impacting your performance.
+            /// However, normally you will not need to call this
+            pub fn reset_buffer(&mut self) {
+                self.buf.shrink_to_fit()
+            }
+        }
+    };
+}
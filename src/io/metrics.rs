@@ -0,0 +1,125 @@
+/*
+ * Copyright 2024, Sayan Nandan <nandansayan@outlook.com>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+*/
+
+//! Connection metrics, emitted through the [`metrics`](https://docs.rs/metrics) facade if the `metrics` feature is
+//! enabled. Without the feature, [`Metrics`] does nothing (so that the connections don't need to check for it).
+
+use {
+    crate::{
+        error::{Error, PipelineError},
+        response::Response,
+    },
+    std::time::Instant,
+};
+
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone)]
+/// The metric handles of a connection. They are registered with the recorder that is installed when the connection is
+/// established
+pub(crate) struct Metrics {
+    queries_ok: metrics::Counter,
+    queries_server_error: metrics::Counter,
+    queries_error: metrics::Counter,
+    query_duration: metrics::Histogram,
+    bytes_read: metrics::Counter,
+    bytes_written: metrics::Counter,
+    /// only sync connections are re-established
+    #[cfg(feature = "sync")]
+    reconnects: metrics::Counter,
+}
+
+#[cfg(feature = "metrics")]
+impl Metrics {
+    pub(crate) fn new(prefix: &str) -> Self {
+        let queries = format!("{prefix}_queries_total");
+        Self {
+            queries_ok: metrics::counter!(queries.clone(), "outcome" => "ok"),
+            queries_server_error: metrics::counter!(queries.clone(), "outcome" => "server_error"),
+            queries_error: metrics::counter!(queries, "outcome" => "error"),
+            query_duration: metrics::histogram!(format!("{prefix}_query_duration_seconds")),
+            bytes_read: metrics::counter!(format!("{prefix}_bytes_read_total")),
+            bytes_written: metrics::counter!(format!("{prefix}_bytes_written_total")),
+            #[cfg(feature = "sync")]
+            reconnects: metrics::counter!(format!("{prefix}_reconnects_total")),
+        }
+    }
+    pub(crate) fn read(&self, bytes: usize) {
+        self.bytes_read.increment(bytes as u64);
+    }
+    pub(crate) fn written(&self, bytes: usize) {
+        self.bytes_written.increment(bytes as u64);
+    }
+    #[cfg(feature = "sync")]
+    pub(crate) fn reconnected(&self) {
+        self.reconnects.increment(1);
+    }
+    /// Count the outcome of a query whose response was received (or that failed)
+    pub(crate) fn outcome(&self, r: Result<&Response, &Error>) {
+        match r {
+            Ok(Response::Error(_)) | Err(Error::ServerError(_)) => {
+                self.queries_server_error.increment(1)
+            }
+            Ok(_) => self.queries_ok.increment(1),
+            Err(_) => self.queries_error.increment(1),
+        }
+    }
+    fn elapsed(&self, start: Instant) {
+        self.query_duration.record(start.elapsed());
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+#[derive(Debug, Clone)]
+pub(crate) struct Metrics;
+
+#[cfg(not(feature = "metrics"))]
+impl Metrics {
+    pub(crate) fn new(_: &str) -> Self {
+        Self
+    }
+    pub(crate) fn read(&self, _: usize) {}
+    pub(crate) fn written(&self, _: usize) {}
+    #[cfg(feature = "sync")]
+    pub(crate) fn reconnected(&self) {}
+    pub(crate) fn outcome(&self, _: Result<&Response, &Error>) {}
+    fn elapsed(&self, _: Instant) {}
+}
+
+impl Metrics {
+    /// Record the outcome and duration of a query that was started at `start`
+    pub(crate) fn query(&self, r: Result<&Response, &Error>, start: Instant) {
+        self.outcome(r);
+        self.elapsed(start);
+    }
+    /// Record the outcome of every query in a pipeline (the queries that didn't get a response count as errors) and
+    /// the duration of the pipeline
+    pub(crate) fn pipeline(
+        &self,
+        queries: usize,
+        r: &Result<Vec<Response>, PipelineError>,
+        start: Instant,
+    ) {
+        let (responses, e) = match r {
+            Ok(responses) => (&responses[..], None),
+            Err(e) => (e.parsed(), Some(e.cause())),
+        };
+        responses.iter().for_each(|resp| self.outcome(Ok(resp)));
+        if let Some(e) = e {
+            (responses.len()..queries).for_each(|_| self.outcome(Err(e)));
+        }
+        self.elapsed(start);
+    }
+}
@@ -0,0 +1,345 @@
+/*
+ * Copyright 2024, Sayan Nandan <nandansayan@outlook.com>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+*/
+
+//! A scripted, in-memory stream used to test the connection read/write loops without a running server
+
+#[cfg(feature = "sync")]
+use std::{cell::Cell, collections::VecDeque, time::Duration};
+#[cfg(any(feature = "aio", feature = "async-std"))]
+use std::{
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+use {
+    crate::response::{Response, Row, Value},
+    std::{io, ops::Range},
+};
+
+/// The handshake packet that the server sends back on a successful connection
+pub const HANDSHAKE_OKAY: [u8; 4] = [b'H', 0, 0, 0];
+
+/// A multi-row response with nested lists and strings containing LFs, used to check that responses are decoded
+/// correctly no matter where the reads are split (see [`rows_response`] for the decoded response)
+pub const ROWS_RESPONSE: &[u8] =
+    b"\x132\n3\n\x0D5\nsa\nyn\x0E2\n\x0220\n\x00\x0B-0.25\n\x0D5\nelana\x0E0\n\x0B1.5\n";
+
+/// The decoded [`ROWS_RESPONSE`]
+pub fn rows_response() -> Response {
+    Response::Rows(vec![
+        Row::new(vec![
+            Value::String("sa\nyn".into()),
+            Value::List(vec![Value::UInt8(20), Value::Null]),
+            Value::Float64(-0.25),
+        ]),
+        Row::new(vec![
+            Value::String("elana".into()),
+            Value::List(vec![]),
+            Value::Float64(1.5),
+        ]),
+    ])
+}
+
+/// Streams that send the handshake followed by `response`, one byte at a time, seven bytes at a time and split at every
+/// possible offset in the response (along with a description of how the reads are split)
+pub fn split_reads(response: &[u8]) -> Vec<(String, MockStream)> {
+    let mut streams = vec![
+        (
+            "1 byte reads".to_owned(),
+            MockStream::with_handshake(response).chunked(1),
+        ),
+        (
+            "7 byte reads".to_owned(),
+            MockStream::with_handshake(response).chunked(7),
+        ),
+    ];
+    streams.extend((1..response.len()).map(|i| {
+        (
+            format!("split at {i}"),
+            MockStream::with_handshake(response)
+                .split_at([HANDSHAKE_OKAY.len(), HANDSHAKE_OKAY.len() + i]),
+        )
+    }));
+    streams
+}
+
+#[derive(Debug)]
+pub struct MockStream {
+    /// the bytes that the "server" will send to the client
+    incoming: Vec<u8>,
+    cursor: usize,
+    /// the maximum number of bytes returned by a single read
+    chunk: usize,
+    /// offsets in the incoming data that a single read never crosses
+    splits: Vec<usize>,
+    /// the bytes that the client wrote
+    outgoing: Vec<u8>,
+    /// the number of bytes that the client had written at each flush
+    flushes: Vec<usize>,
+    /// the length of the handshake at the start of the incoming data
+    handshake: usize,
+    /// the incoming bytes that are available before the client sends anything (the only ones that a read which
+    /// doesn't wait can return)
+    stale: Range<usize>,
+    /// whether reads that don't wait return EOF (instead of blocking) after the stale bytes
+    stale_eof: bool,
+    #[cfg(feature = "sync")]
+    nonblocking: Cell<bool>,
+    #[cfg(feature = "sync")]
+    read_timeout: Cell<Option<Duration>>,
+    /// the maximum number of bytes that the client can write (as if the server stopped reading)
+    #[cfg(feature = "sync")]
+    write_quota: usize,
+    /// how long each blocking read waits before returning data (as if the server took that long to respond)
+    #[cfg(feature = "sync")]
+    read_delays: VecDeque<Duration>,
+}
+
+impl MockStream {
+    /// Create a new stream that will send `incoming` to the client and then return EOF
+    pub fn new(incoming: impl AsRef<[u8]>) -> Self {
+        Self {
+            incoming: incoming.as_ref().to_owned(),
+            cursor: 0,
+            chunk: usize::MAX,
+            splits: vec![],
+            outgoing: vec![],
+            flushes: vec![],
+            handshake: 0,
+            stale: 0..0,
+            stale_eof: false,
+            #[cfg(feature = "sync")]
+            nonblocking: Cell::new(false),
+            #[cfg(feature = "sync")]
+            read_timeout: Cell::new(None),
+            #[cfg(feature = "sync")]
+            write_quota: usize::MAX,
+            #[cfg(feature = "sync")]
+            read_delays: VecDeque::new(),
+        }
+    }
+    /// Same as [`Self::new`], but prefixes the incoming data with an okay handshake
+    pub fn with_handshake(incoming: impl AsRef<[u8]>) -> Self {
+        Self {
+            handshake: HANDSHAKE_OKAY.len(),
+            ..Self::new([&HANDSHAKE_OKAY[..], incoming.as_ref()].concat())
+        }
+    }
+    /// Limit the number of bytes returned by a single read
+    pub fn chunked(mut self, chunk: usize) -> Self {
+        self.chunk = chunk;
+        self
+    }
+    /// Split the reads at the given offsets in the incoming data (including the handshake, if any)
+    pub fn split_at(mut self, offsets: impl IntoIterator<Item = usize>) -> Self {
+        self.splits = offsets.into_iter().collect();
+        self
+    }
+    /// Send `stale` right after the handshake, before the client sends anything (as if the server sent unsolicited
+    /// data). Unlike the rest of the incoming data, it can be read without waiting
+    pub fn stale(mut self, stale: impl AsRef<[u8]>) -> Self {
+        let (start, stale) = (self.handshake, stale.as_ref());
+        self.incoming.splice(start..start, stale.iter().copied());
+        for split in self.splits.iter_mut().filter(|split| **split > start) {
+            *split += stale.len();
+        }
+        self.stale = start..start + stale.len();
+        self
+    }
+    /// Return EOF from reads that don't wait after the stale bytes (as if the server closed the connection while it
+    /// was idle)
+    #[cfg(feature = "sync")]
+    pub fn closed_while_idle(mut self) -> Self {
+        self.stale_eof = true;
+        self
+    }
+    /// Accept at most `quota` bytes from the client. Writes after that fail as if the write timed out
+    #[cfg(feature = "sync")]
+    pub fn write_quota(mut self, quota: usize) -> Self {
+        self.write_quota = quota;
+        self
+    }
+    /// Wait for the next of `delays` in each blocking read (the reads after that aren't delayed)
+    #[cfg(feature = "sync")]
+    pub fn read_delays(mut self, delays: impl IntoIterator<Item = Duration>) -> Self {
+        self.read_delays = delays.into_iter().collect();
+        self
+    }
+    /// Returns the number of bytes that haven't been sent to the client yet
+    #[cfg(feature = "sync")]
+    pub fn remaining(&self) -> usize {
+        self.incoming.len() - self.cursor
+    }
+    /// Returns everything that the client wrote
+    pub fn written(&self) -> &[u8] {
+        &self.outgoing
+    }
+    /// Returns the number of bytes that the client had written at each flush
+    pub fn flushed_at(&self) -> &[usize] {
+        &self.flushes
+    }
+    fn record_flush(&mut self) {
+        self.flushes.push(self.outgoing.len());
+    }
+    /// Read without waiting: only the stale bytes are available, and after them this fails with `WouldBlock` (or
+    /// returns EOF, see [`Self::closed_while_idle`])
+    fn read_stale(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.stale.contains(&self.cursor) {
+            return match self.stale_eof {
+                true => Ok(0),
+                false => Err(io::ErrorKind::WouldBlock.into()),
+            };
+        }
+        let n = (self.stale.end - self.cursor).min(buf.len());
+        buf[..n].copy_from_slice(&self.incoming[self.cursor..][..n]);
+        self.cursor += n;
+        Ok(n)
+    }
+    /// An async read that is polled with a no-op waker (which can never be woken up) doesn't wait (see
+    /// [`Self::read_stale`])
+    #[cfg(any(feature = "aio", feature = "async-std"))]
+    fn poll_read_into(&mut self, cx: &Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        if !cx.waker().will_wake(Waker::noop()) {
+            return Poll::Ready(Ok(self.read_into(buf)));
+        }
+        match self.read_stale(buf) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+            r => Poll::Ready(r),
+        }
+    }
+    fn read_into(&mut self, buf: &mut [u8]) -> usize {
+        let cursor = self.cursor;
+        let remaining = &self.incoming[cursor..];
+        let split = self
+            .splits
+            .iter()
+            .filter(|split| **split > cursor)
+            .min()
+            .map_or(usize::MAX, |split| split - cursor);
+        let n = remaining.len().min(buf.len()).min(self.chunk).min(split);
+        buf[..n].copy_from_slice(&remaining[..n]);
+        self.cursor += n;
+        n
+    }
+}
+
+#[cfg(feature = "sync")]
+impl io::Read for MockStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // a read timeout this short only returns what has already been received
+        let short_timeout =
+            matches!(self.read_timeout.get(), Some(t) if t < Duration::from_millis(1));
+        if self.nonblocking.get() || short_timeout {
+            return self.read_stale(buf);
+        }
+        if let Some(delay) = self.read_delays.pop_front() {
+            std::thread::sleep(delay);
+        }
+        Ok(self.read_into(buf))
+    }
+}
+
+#[cfg(feature = "sync")]
+impl io::Write for MockStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.write_quota - self.outgoing.len());
+        if n == 0 && !buf.is_empty() {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        self.outgoing.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.record_flush();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sync")]
+impl super::SyncSocket for MockStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.read_timeout.set(timeout);
+        Ok(())
+    }
+    fn set_write_timeout(&self, _: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.nonblocking.set(nonblocking);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async-std")]
+impl async_std::io::Read for MockStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_read_into(cx, buf)
+    }
+}
+
+#[cfg(feature = "async-std")]
+impl async_std::io::Write for MockStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().outgoing.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().record_flush();
+        Poll::Ready(Ok(()))
+    }
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "aio")]
+impl tokio::io::AsyncRead for MockStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let n = std::task::ready!(self.get_mut().poll_read_into(cx, buf.initialize_unfilled()))?;
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "aio")]
+impl tokio::io::AsyncWrite for MockStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().outgoing.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().record_flush();
+        Poll::Ready(Ok(()))
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
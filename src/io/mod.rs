@@ -14,5 +14,234 @@
  * limitations under the License.
 */
 
+#[cfg(any(feature = "aio", feature = "async-std"))]
+#[macro_use]
+mod async_common;
+#[cfg(feature = "aio")]
 pub mod aio;
+#[cfg(feature = "async-std")]
+pub mod astd;
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+mod metrics;
+#[cfg(all(test, any(feature = "sync", feature = "aio", feature = "async-std")))]
+pub(crate) mod mock;
+#[cfg(feature = "sync")]
 pub mod sync;
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+mod wire;
+
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+use {
+    crate::{protocol::handshake::ProtocolVersion, Config},
+    std::net::SocketAddr,
+};
+
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+#[derive(Debug, Clone, PartialEq)]
+/// Information about an established connection
+///
+/// This is collected when the connection is established (and refreshed if the connection is re-established), so it
+/// is useful to find out which server a (pooled) connection is talking to.
+pub struct ConnectionInfo {
+    host: Box<str>,
+    peer_addr: SocketAddr,
+    local_addr: SocketAddr,
+    tls: bool,
+    protocol: ProtocolVersion,
+}
+
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+impl ConnectionInfo {
+    pub(crate) fn new(
+        cfg: &Config,
+        peer_addr: SocketAddr,
+        local_addr: SocketAddr,
+        tls: bool,
+    ) -> Self {
+        Self {
+            host: cfg.host().into(),
+            peer_addr,
+            local_addr,
+            tls,
+            protocol: cfg.protocol,
+        }
+    }
+    /// Returns the host that was configured for this connection
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+    /// Returns the address of the server that this connection is connected to
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+    /// Returns the local address of this connection
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+    /// Returns true if this connection is using TLS
+    pub fn is_tls(&self) -> bool {
+        self.tls
+    }
+    /// Returns the protocol version negotiated during the handshake (for example, `Skyhash/2.0`)
+    pub fn protocol_version(&self) -> &'static str {
+        self.protocol.name()
+    }
+}
+
+#[cfg(any(feature = "sync", feature = "aio"))]
+/// Order the resolved addresses so that we alternate between address families, starting with the family of the first
+/// address (as recommended by RFC 8305)
+pub(crate) fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_v6 = match addrs.first() {
+        Some(addr) => addr.is_ipv6(),
+        None => return addrs,
+    };
+    let (mut preferred, mut other): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_v6);
+    let mut ret = Vec::with_capacity(preferred.len() + other.len());
+    let (mut preferred, mut other) = (preferred.drain(..), other.drain(..));
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => break ret,
+            (a, b) => ret.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+#[cfg(any(feature = "sync", feature = "aio"))]
+fn no_addresses() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "could not resolve to any addresses",
+    )
+}
+
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+/// Returns the query that switches to `space`. The space can't be passed as a parameter, so only valid identifiers are
+/// accepted
+pub(crate) fn use_space_query(space: &str) -> std::io::Result<crate::Query> {
+    let mut chars = space.chars();
+    let valid = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("invalid space name {space:?}"),
+        ));
+    }
+    Ok(crate::Query::new_string(format!("use {space}")))
+}
+
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+/// Split the responses to a pipeline of writes into the indices of the writes that succeeded (returned an empty
+/// response) and the errors of the ones that didn't
+pub(crate) fn check_writes(
+    responses: Vec<crate::response::Response>,
+) -> (Vec<usize>, Vec<(usize, crate::error::Error)>) {
+    let mut succeeded = vec![];
+    let mut failed = vec![];
+    for (i, resp) in responses.into_iter().enumerate() {
+        match resp.ensure_empty() {
+            Ok(()) => succeeded.push(i),
+            Err(e) => failed.push((i, e)),
+        }
+    }
+    (succeeded, failed)
+}
+
+#[cfg(feature = "sync")]
+/// A blocking stream that a sync connection can be established over
+pub trait SyncSocket: std::io::Read + std::io::Write {
+    /// Set the read timeout for the underlying socket
+    fn set_read_timeout(&self, timeout: Option<std::time::Duration>) -> std::io::Result<()>;
+    /// Set the write timeout for the underlying socket. The default implementation returns an
+    /// [`Unsupported`](std::io::ErrorKind::Unsupported) error, and connections over such sockets only apply
+    /// deadlines to reading the response (so a write can block past the deadline if the server stops reading)
+    fn set_write_timeout(&self, _timeout: Option<std::time::Duration>) -> std::io::Result<()> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+    /// Move the underlying socket into or out of nonblocking mode. This is used to check for data that the server sent
+    /// while the connection was idle. The default implementation returns an
+    /// [`Unsupported`](std::io::ErrorKind::Unsupported) error, and connections over such sockets skip that check
+    fn set_nonblocking(&self, _nonblocking: bool) -> std::io::Result<()> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+}
+
+#[cfg(feature = "sync")]
+impl SyncSocket for std::net::TcpStream {
+    fn set_read_timeout(&self, timeout: Option<std::time::Duration>) -> std::io::Result<()> {
+        std::net::TcpStream::set_read_timeout(self, timeout)
+    }
+    fn set_write_timeout(&self, timeout: Option<std::time::Duration>) -> std::io::Result<()> {
+        std::net::TcpStream::set_write_timeout(self, timeout)
+    }
+    fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()> {
+        std::net::TcpStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+#[cfg(feature = "sync")]
+impl SyncSocket for native_tls::TlsStream<std::net::TcpStream> {
+    fn set_read_timeout(&self, timeout: Option<std::time::Duration>) -> std::io::Result<()> {
+        self.get_ref().set_read_timeout(timeout)
+    }
+    fn set_write_timeout(&self, timeout: Option<std::time::Duration>) -> std::io::Result<()> {
+        self.get_ref().set_write_timeout(timeout)
+    }
+    fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()> {
+        self.get_ref().set_nonblocking(nonblocking)
+    }
+}
+
+#[test]
+#[cfg(any(feature = "sync", feature = "aio"))]
+fn interleave_address_families() {
+    let addrs: Vec<SocketAddr> = ["[::1]:1", "[::2]:1", "[::3]:1", "10.0.0.1:1", "10.0.0.2:1"]
+        .iter()
+        .map(|addr| addr.parse().unwrap())
+        .collect();
+    let expected: Vec<SocketAddr> = ["[::1]:1", "10.0.0.1:1", "[::2]:1", "10.0.0.2:1", "[::3]:1"]
+        .iter()
+        .map(|addr| addr.parse().unwrap())
+        .collect();
+    assert_eq!(interleave_families(addrs), expected);
+    assert_eq!(interleave_families(vec![]), vec![]);
+}
+
+#[test]
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+fn use_space_query_validation() {
+    assert_eq!(
+        use_space_query("my_space2").unwrap().debug_encode_packet(),
+        query!("use my_space2").debug_encode_packet()
+    );
+    for space in [
+        "",
+        "2space",
+        "my space",
+        "myspace; drop space x",
+        "$current",
+    ] {
+        assert_eq!(
+            use_space_query(space).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+    }
+}
+
+#[cfg(all(test, any(feature = "sync", feature = "aio")))]
+/// Returns the port of a server that reads the handshake and closes the connection without responding. The listener is
+/// kept open (unlike an unused port, which another test could start listening on before the connection attempt)
+pub(crate) fn closing_port() -> u16 {
+    use std::io::Read;
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for con in listener.incoming() {
+            let _ = con.unwrap().read(&mut [0u8; 128]);
+        }
+    });
+    port
+}
@@ -0,0 +1,368 @@
+/*
+ * Copyright 2024, Sayan Nandan <nandansayan@outlook.com>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+*/
+
+//! # Response caching
+//!
+//! A [`CachedConnection`] keeps the responses to `select` queries for a while, so that data that is read much more
+//! often than it changes (such as configuration) isn't fetched from the server every time:
+//!
+//! ```no_run
+//! use {
+//!     skytable::{query, syncio::cache::{CacheConfig, CachedConnection}, Config},
+//!     std::time::Duration,
+//! };
+//!
+//! let db = Config::new_default("username", "password").connect().unwrap();
+//! let mut db = CachedConnection::new(
+//!     db,
+//!     CacheConfig {
+//!         ttl: Duration::from_secs(30),
+//!         max_entries: 1000,
+//!     },
+//! );
+//! let q = query!("select value from myspace.config where key = ?", "max_users");
+//! let max_users: u64 = db.query_parse(&q).unwrap(); // from the server
+//! let max_users: u64 = db.query_parse(&q).unwrap(); // from the cache
+//! ```
+//!
+//! A response is kept for at most the TTL, which bounds how stale it can be: changes made through other connections
+//! aren't seen until then. Queries are cached by their query string and parameters (see [`Query`]'s equality), so the
+//! same query with different parameters is cached separately.
+//!
+//! The client can't tell which rows a write affects, so every query that isn't a `select` (such as an `insert`,
+//! `update` or `delete`) empties the whole cache. Queries sent through [`CachedConnection::connection`] bypass the
+//! cache completely, so call [`CachedConnection::invalidate`] after writing through it.
+
+use {
+    super::{SyncSocket, TcpConnection},
+    crate::{
+        error::ClientResult,
+        response::{FromResponse, Response},
+        Query,
+    },
+    std::{
+        collections::{BTreeMap, HashMap},
+        ops::DerefMut,
+        time::{Duration, Instant},
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The configuration of a [`CachedConnection`]
+pub struct CacheConfig {
+    /// how long a response is kept (a TTL that is too long to be represented, such as [`Duration::MAX`], keeps
+    /// responses until they are evicted)
+    pub ttl: Duration,
+    /// the maximum number of responses that are kept (the least recently used one is dropped to make room for a new
+    /// one)
+    pub max_entries: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// The number of `select` queries that were answered from the cache (hits) and by the server (misses)
+pub struct CacheStats {
+    /// queries that were answered from the cache
+    pub hits: u64,
+    /// queries that were sent to the server
+    pub misses: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    response: Response,
+    /// `None` if the entry never expires
+    expires: Option<Instant>,
+    /// when this entry was last used (its key in the LRU order)
+    used: u64,
+}
+
+#[derive(Debug)]
+/// A connection that caches the responses to `select` queries (see the [module documentation](self))
+pub struct CachedConnection<C> {
+    con: C,
+    cfg: CacheConfig,
+    entries: HashMap<Query, Entry>,
+    /// the cached queries, from the least to the most recently used
+    lru: BTreeMap<u64, Query>,
+    uses: u64,
+    stats: CacheStats,
+    clock: fn() -> Instant,
+}
+
+impl<C> CachedConnection<C> {
+    /// Wrap `con` (a [`Connection`](crate::Connection), a [`ConnectionTls`](crate::ConnectionTls) or anything else
+    /// that dereferences to a [`TcpConnection`]) in a cache
+    pub fn new(con: C, cfg: CacheConfig) -> Self {
+        Self::with_clock(con, cfg, Instant::now)
+    }
+    fn with_clock(con: C, cfg: CacheConfig, clock: fn() -> Instant) -> Self {
+        Self {
+            con,
+            cfg,
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+            uses: 0,
+            stats: CacheStats::default(),
+            clock,
+        }
+    }
+    /// Returns the connection, to send queries that bypass the cache. Writes sent this way don't empty the cache, so
+    /// call [`Self::invalidate`] after them
+    pub fn connection(&mut self) -> &mut C {
+        &mut self.con
+    }
+    /// Returns the connection, dropping the cache
+    pub fn into_inner(self) -> C {
+        self.con
+    }
+    /// Drop every cached response
+    pub fn invalidate(&mut self) {
+        self.entries.clear();
+        self.lru.clear();
+    }
+    /// Returns the number of cached responses (including the ones that expired but weren't dropped yet)
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    /// Returns true if no responses are cached
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// Returns the number of cache hits and misses so far
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+    /// Returns the cached response to `q`, unless it expired
+    fn get(&mut self, q: &Query) -> Option<Response> {
+        let now = (self.clock)();
+        let entry = self.entries.get_mut(q)?;
+        let q = self.lru.remove(&entry.used).unwrap();
+        if entry.expires.is_some_and(|expires| expires <= now) {
+            self.entries.remove(&q);
+            return None;
+        }
+        self.uses += 1;
+        entry.used = self.uses;
+        let response = entry.response.clone();
+        self.lru.insert(self.uses, q);
+        Some(response)
+    }
+    fn insert(&mut self, q: &Query, response: Response) {
+        if self.cfg.max_entries == 0 {
+            return;
+        }
+        while self.entries.len() >= self.cfg.max_entries {
+            let (_, oldest) = self.lru.pop_first().unwrap();
+            self.entries.remove(&oldest);
+        }
+        self.uses += 1;
+        let entry = Entry {
+            response,
+            expires: (self.clock)().checked_add(self.cfg.ttl),
+            used: self.uses,
+        };
+        self.entries.insert(q.clone(), entry);
+        self.lru.insert(self.uses, q.clone());
+    }
+}
+
+impl<C, S> CachedConnection<C>
+where
+    C: DerefMut<Target = TcpConnection<S>>,
+    S: SyncSocket,
+{
+    /// Run a query. The response to a `select` is returned from the cache if it's there (and added to it otherwise),
+    /// while any other query empties the cache. Error responses aren't cached
+    pub fn query(&mut self, q: &Query) -> ClientResult<Response> {
+        if !is_select(q) {
+            self.invalidate();
+            return self.con.query(q);
+        }
+        if let Some(response) = self.get(q) {
+            self.stats.hits += 1;
+            return Ok(response);
+        }
+        self.stats.misses += 1;
+        let response = self.con.query(q)?;
+        if !matches!(response, Response::Error(_)) {
+            self.insert(q, response.clone());
+        }
+        Ok(response)
+    }
+    /// Same as [`Self::query`], but parses the response into the indicated type
+    pub fn query_parse<T: FromResponse>(&mut self, q: &Query) -> ClientResult<T> {
+        self.query(q).and_then(FromResponse::from_response)
+    }
+}
+
+/// Returns true if `q` is a `select` (which is the only query that is cached)
+fn is_select(q: &Query) -> bool {
+    q.query_str()
+        .split_whitespace()
+        .next()
+        .is_some_and(|word| word.eq_ignore_ascii_case("select"))
+}
+
+#[cfg(test)]
+thread_local! {
+    /// How far the test clock is ahead of the real one (every test runs on its own thread)
+    static CLOCK_OFFSET: std::cell::Cell<Duration> = const { std::cell::Cell::new(Duration::ZERO) };
+}
+
+#[cfg(test)]
+fn test_clock() -> Instant {
+    Instant::now() + CLOCK_OFFSET.with(|offset| offset.get())
+}
+
+#[cfg(test)]
+fn advance_clock(by: Duration) {
+    CLOCK_OFFSET.with(|offset| offset.set(offset.get() + by))
+}
+
+#[cfg(test)]
+fn cached(
+    responses: &[u8],
+    max_entries: usize,
+) -> CachedConnection<Box<TcpConnection<crate::io::mock::MockStream>>> {
+    let con =
+        TcpConnection::from_raw_stream(crate::io::mock::MockStream::new(responses).chunked(1));
+    let cfg = CacheConfig {
+        ttl: Duration::from_secs(10),
+        max_entries,
+    };
+    CachedConnection::with_clock(Box::new(con), cfg, test_clock)
+}
+
+#[cfg(test)]
+/// The number of queries that were sent to the server
+fn sent(con: &mut CachedConnection<Box<TcpConnection<crate::io::mock::MockStream>>>) -> usize {
+    con.connection().con.flushed_at().len()
+}
+
+#[test]
+fn hits_and_misses() {
+    use crate::response::Value;
+    let mut con = cached(b"\x05100\n\x05200\n\x10\x05\x00\x05300\n", 10);
+    let (a, b) = (
+        query!(
+            "select followers from myspace.users where username = ?",
+            "a"
+        ),
+        query!(
+            "select followers from myspace.users where username = ?",
+            "b"
+        ),
+    );
+    assert_eq!(con.query_parse::<u64>(&a).unwrap(), 100);
+    assert_eq!(con.query_parse::<u64>(&a).unwrap(), 100);
+    assert_eq!(con.query_parse::<u64>(&b).unwrap(), 200);
+    assert_eq!(con.query_parse::<u64>(&b).unwrap(), 200);
+    assert_eq!(con.query_parse::<u64>(&a).unwrap(), 100);
+    assert_eq!(con.stats(), CacheStats { hits: 3, misses: 2 });
+    assert_eq!((con.len(), sent(&mut con)), (2, 2));
+    // errors aren't cached
+    let c = query!(
+        "SELECT followers from myspace.users where username = ?",
+        "c"
+    );
+    assert_eq!(con.query(&c).unwrap(), Response::Error(5));
+    assert_eq!(con.query(&c).unwrap(), Response::Value(Value::UInt64(300)));
+    assert_eq!(con.query(&c).unwrap(), Response::Value(Value::UInt64(300)));
+    assert_eq!(con.stats(), CacheStats { hits: 4, misses: 4 });
+    assert_eq!((con.len(), sent(&mut con)), (3, 4));
+}
+
+#[test]
+fn writes_invalidate() {
+    let mut con = cached(b"\x05100\n\x12\x05101\n\x05102\n", 10);
+    let q = query!(
+        "select followers from myspace.users where username = ?",
+        "a"
+    );
+    assert_eq!(con.query_parse::<u64>(&q).unwrap(), 100);
+    con.query_parse::<()>(&query!(
+        "update myspace.users set followers += ? where username = ?",
+        1u64,
+        "a"
+    ))
+    .unwrap();
+    assert!(con.is_empty());
+    assert_eq!(con.query_parse::<u64>(&q).unwrap(), 101);
+    assert_eq!(con.query_parse::<u64>(&q).unwrap(), 101);
+    // queries sent through the connection bypass the cache
+    assert_eq!(con.connection().query_parse::<u64>(&q).unwrap(), 102);
+    assert_eq!(con.query_parse::<u64>(&q).unwrap(), 101);
+    con.invalidate();
+    assert!(con.is_empty());
+    assert_eq!(con.stats(), CacheStats { hits: 2, misses: 2 });
+    assert_eq!(sent(&mut con), 4);
+}
+
+#[test]
+fn ttl_expiry() {
+    let mut con = cached(b"\x05100\n\x05200\n", 10);
+    let q = query!(
+        "select followers from myspace.users where username = ?",
+        "a"
+    );
+    assert_eq!(con.query_parse::<u64>(&q).unwrap(), 100);
+    advance_clock(Duration::from_secs(9));
+    assert_eq!(con.query_parse::<u64>(&q).unwrap(), 100);
+    // using an entry doesn't extend it
+    advance_clock(Duration::from_secs(1));
+    assert_eq!(con.query_parse::<u64>(&q).unwrap(), 200);
+    assert_eq!(con.stats(), CacheStats { hits: 1, misses: 2 });
+    assert_eq!(con.len(), 1);
+}
+
+#[test]
+fn unbounded_ttl() {
+    let con = TcpConnection::from_raw_stream(crate::io::mock::MockStream::new(b"\x05100\n"));
+    let cfg = CacheConfig {
+        ttl: Duration::MAX,
+        max_entries: 10,
+    };
+    let mut con = CachedConnection::with_clock(Box::new(con), cfg, test_clock);
+    let q = query!(
+        "select followers from myspace.users where username = ?",
+        "a"
+    );
+    assert_eq!(con.query_parse::<u64>(&q).unwrap(), 100);
+    advance_clock(Duration::from_secs(100 * 365 * 24 * 60 * 60));
+    assert_eq!(con.query_parse::<u64>(&q).unwrap(), 100);
+    assert_eq!(con.stats(), CacheStats { hits: 1, misses: 1 });
+}
+
+#[test]
+fn lru_eviction() {
+    let mut con = cached(b"\x051\n\x052\n\x053\n\x054\n", 2);
+    let q = |i: u64| query!("select value from myspace.config where id = ?", i);
+    assert_eq!(con.query_parse::<u64>(&q(1)).unwrap(), 1);
+    assert_eq!(con.query_parse::<u64>(&q(2)).unwrap(), 2);
+    // 1 is used again, so 2 is the least recently used when 3 needs room
+    assert_eq!(con.query_parse::<u64>(&q(1)).unwrap(), 1);
+    assert_eq!(con.query_parse::<u64>(&q(3)).unwrap(), 3);
+    assert_eq!(con.len(), 2);
+    assert_eq!(con.query_parse::<u64>(&q(1)).unwrap(), 1);
+    assert_eq!(con.query_parse::<u64>(&q(3)).unwrap(), 3);
+    assert_eq!(con.query_parse::<u64>(&q(2)).unwrap(), 4);
+    assert_eq!(con.stats(), CacheStats { hits: 3, misses: 4 });
+    // nothing is cached without room for it
+    let mut con = cached(b"\x051\n\x052\n", 0);
+    assert_eq!(con.query_parse::<u64>(&q(1)).unwrap(), 1);
+    assert_eq!(con.query_parse::<u64>(&q(1)).unwrap(), 2);
+    assert!(con.is_empty());
+}
@@ -0,0 +1,172 @@
+/*
+ * Copyright 2024, Sayan Nandan <nandansayan@outlook.com>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+*/
+
+//! Hex dumps of the bytes that connections write and read (see [wire dumps](crate::config#wire-dumps)). Like
+//! [`Metrics`](super::metrics::Metrics), a [`WireDump`] does nothing unless it was enabled, so connections don't need to
+//! check for it.
+
+use {
+    crate::config::{Config, WireSink},
+    core::fmt,
+    std::io::{self, Write},
+};
+
+const BYTES_PER_LINE: usize = 16;
+
+#[derive(Clone)]
+enum Target {
+    /// `tracing` events if the feature is enabled, or the standard error
+    Default,
+    Sink(WireSink),
+}
+
+#[derive(Clone)]
+pub(crate) struct WireDump(Option<Target>);
+
+impl fmt::Debug for WireDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("WireDump").field(&self.0.is_some()).finish()
+    }
+}
+
+impl WireDump {
+    /// A dump that does nothing
+    pub(crate) const fn off() -> Self {
+        Self(None)
+    }
+    pub(crate) fn new(cfg: &Config) -> Self {
+        if !cfg.wire_debug() {
+            return Self::off();
+        }
+        match cfg.wire_sink() {
+            Some(sink) => Self(Some(Target::Sink(sink.clone()))),
+            None => Self(Some(Target::Default)),
+        }
+    }
+    /// Dump a packet that was written
+    pub(crate) fn written(&self, data: &[u8]) {
+        self.dump("write", data)
+    }
+    /// Dump a buffer that was read
+    pub(crate) fn read(&self, data: &[u8]) {
+        self.dump("read", data)
+    }
+    fn dump(&self, direction: &str, data: &[u8]) {
+        // the dump is only for debugging, so failing to write it doesn't fail the query
+        match self.0 {
+            None => {}
+            Some(Target::Sink(ref sink)) => {
+                if let Ok(mut sink) = sink.lock() {
+                    let _ = hex_dump(&mut *sink, direction, data).and_then(|_| sink.flush());
+                }
+            }
+            #[cfg(feature = "tracing")]
+            Some(Target::Default) => {
+                if tracing::enabled!(target: "skytable::wire", tracing::Level::TRACE) {
+                    let mut dump = Vec::with_capacity(dump_len(data.len()));
+                    let _ = hex_dump(&mut dump, direction, data);
+                    tracing::trace!(target: "skytable::wire", "{}", String::from_utf8_lossy(&dump));
+                }
+            }
+            #[cfg(not(feature = "tracing"))]
+            Some(Target::Default) => {
+                let _ = hex_dump(&mut io::stderr().lock(), direction, data);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+/// The size of the dump of `len` bytes
+fn dump_len(len: usize) -> usize {
+    32 + len.div_ceil(BYTES_PER_LINE) * (9 + BYTES_PER_LINE * 4 + 5)
+}
+
+/// Write a hex dump of `data`, formatted straight from the buffer:
+///
+/// ```text
+/// skytable wire: write 27 bytes
+/// 00000000  53 31 37 0a 31 35 0a 73 79 73 63 74 6c 20 72 65  |S17.15.sysctl re|
+/// 00000010  70 6f 72 74 20 73 74 61 74 75 73                 |port status|
+/// ```
+fn hex_dump(out: &mut dyn Write, direction: &str, data: &[u8]) -> io::Result<()> {
+    writeln!(out, "skytable wire: {direction} {} bytes", data.len())?;
+    for (i, line) in data.chunks(BYTES_PER_LINE).enumerate() {
+        write!(out, "{:08x} ", i * BYTES_PER_LINE)?;
+        for byte in line {
+            write!(out, " {byte:02x}")?;
+        }
+        for _ in line.len()..BYTES_PER_LINE {
+            out.write_all(b"   ")?;
+        }
+        out.write_all(b"  |")?;
+        for &byte in line {
+            let c = if byte.is_ascii_graphic() || byte == b' ' {
+                byte
+            } else {
+                b'.'
+            };
+            out.write_all(&[c])?;
+        }
+        out.write_all(b"|\n")?;
+    }
+    Ok(())
+}
+
+#[cfg(all(test, any(feature = "sync", feature = "aio")))]
+#[derive(Clone, Default)]
+/// A sink that keeps the dumps so that tests can check them
+pub(crate) struct CapturedDump(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+#[cfg(all(test, any(feature = "sync", feature = "aio")))]
+impl CapturedDump {
+    pub(crate) fn take(&self) -> String {
+        String::from_utf8(core::mem::take(&mut *self.0.lock().unwrap())).unwrap()
+    }
+}
+
+#[cfg(all(test, any(feature = "sync", feature = "aio")))]
+impl Write for CapturedDump {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The dump of `sysctl report status` (which the tests run) and of a `100` response
+#[cfg(all(test, any(feature = "sync", feature = "aio")))]
+pub(crate) const QUERY_DUMP: &str = "skytable wire: write 27 bytes\n\
+    00000000  53 32 33 0a 32 30 0a 73 79 73 63 74 6c 20 72 65  |S23.20.sysctl re|\n\
+    00000010  70 6f 72 74 20 73 74 61 74 75 73                 |port status|\n\
+    skytable wire: read 5 bytes\n\
+    00000000  05 31 30 30 0a                                   |.100.|\n";
+
+#[test]
+fn hex_framing() {
+    let mut out = vec![];
+    hex_dump(&mut out, "write", b"S17\n15\nsysctl report status").unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "skytable wire: write 27 bytes\n\
+         00000000  53 31 37 0a 31 35 0a 73 79 73 63 74 6c 20 72 65  |S17.15.sysctl re|\n\
+         00000010  70 6f 72 74 20 73 74 61 74 75 73                 |port status|\n"
+    );
+    let mut out = vec![];
+    hex_dump(&mut out, "read", &[]).unwrap();
+    assert_eq!(out, b"skytable wire: read 0 bytes\n");
+}
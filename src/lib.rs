@@ -106,6 +106,29 @@
 //! - Custom [`response`] parsing
 //! - [`Connection pooling`](pool)
 //!
+//! ## Feature flags
+//!
+//! - `sync` (default): the blocking API ([`syncio`]) and sync connection pooling with [`r2d2`](https://docs.rs/r2d2)
+//! - `aio` (default): the Tokio based async API ([`aio`]) and async connection pooling with [`bb8`](https://docs.rs/bb8)
+//! - `async-std`: the `async-std` based async API (`astd`)
+//! - `pool-deadpool`: async connection pooling with [`deadpool`](https://docs.rs/deadpool)
+//! - `tower`: [`tower`](https://docs.rs/tower) services for async connections and pools (`service`)
+//! - `metrics`: emit query, byte and reconnect counters through the [`metrics`](https://docs.rs/metrics) facade (see
+//!   [`Config::with_metrics_prefix`])
+//! - `tracing`: emit [wire dumps](config#wire-dumps) as [`tracing`](https://docs.rs/tracing) events instead of writing
+//!   them to the standard error
+//! - `zeroize`: wipe passwords (in [`Config`] and in handshake buffers) from memory when they are dropped
+//! - `simd`: find the end of sizes and numbers in responses with [`memchr`](https://docs.rs/memchr), which uses SIMD
+//!   instructions where the target has them, instead of one byte at a time
+//! - `std` (default, implied by all of the above): configuration, TLS and everything else that needs the standard
+//!   library
+//! - `alloc-proto`: the `proto` module, which encodes queries and decodes responses without doing any I/O
+//!
+//! The query encoder and response decoder do not depend on any of these, so for a small, sync-only client you can use
+//! `default-features = false, features = ["sync"]`. Without `std`, the crate is `no_std` (it only needs `alloc`), so
+//! `default-features = false, features = ["alloc-proto"]` can be used to encode and decode Skyhash in constrained
+//! environments (for example, a WASM plugin) and do the I/O elsewhere.
+//!
 //! ## Need help? Get help!
 //!
 //! Jump into [Skytable's official Discord server](https://discord.com/invite/QptWFdx) where maintainers, developers and fellow
@@ -113,33 +136,59 @@
 //!
 
 #![deny(missing_docs)]
+// tests always need the standard library
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 
 // internal modules
 #[macro_use]
 mod macros;
 mod protocol;
 // public modules
+#[cfg(feature = "std")]
 pub mod config;
 pub mod error;
+#[cfg(any(feature = "sync", feature = "aio"))]
 pub mod pool;
+#[cfg(feature = "alloc-proto")]
+pub mod proto;
 pub mod query;
+pub mod render;
 pub mod response;
+#[cfg(feature = "tower")]
+pub mod service;
 /// The `Query` derive macro enables you to directly pass complex types as parameters into queries
 pub use sky_derive::Query;
 /// The `Response` derive macro enables you to directly pass complex types as parameters into queries
 pub use sky_derive::Response;
 // re-exports
+#[cfg(feature = "std")]
+pub use config::Config;
+#[cfg(feature = "aio")]
+pub use io::aio::{self, ConnectionAsync, ConnectionTlsAsync};
+#[cfg(feature = "async-std")]
+pub use io::astd::{self, ConnectionAsyncStd};
+#[cfg(feature = "sync")]
+pub use io::sync::{self as syncio, Connection, ConnectionTls};
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+pub use io::ConnectionInfo;
+#[doc(hidden)]
+#[cfg(any(
+    feature = "sync",
+    feature = "aio",
+    feature = "async-std",
+    feature = "alloc-proto"
+))]
+pub use protocol::test_vectors;
 pub use {
-    config::Config,
     error::ClientResult,
-    io::{
-        aio::{self, ConnectionAsync, ConnectionTlsAsync},
-        sync::{self as syncio, Connection, ConnectionTls},
-    },
     query::{Pipeline, Query},
 };
 // private
+#[cfg(feature = "std")]
 mod io;
 
 /// we use a 8KB read buffer by default; allow this to be changed
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
 const BUFSIZE: usize = 8 * 1024;
@@ -36,6 +36,8 @@ macro_rules! query {
     }};
 }
 
+// only used by the handshake (parameters are written by `query::append_sized`)
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
 macro_rules! pushlen {
     ($buf:expr, $len:expr) => {{
         let mut buf = ::itoa::Buffer::new();
@@ -61,3 +63,24 @@ macro_rules! pipe {
         $(p.push_owned($query);)*p
     }}
 }
+
+#[macro_export]
+/// Get the value at a path of indices in a [`Response`](crate::response::Response) or a
+/// [`Value`](crate::response::Value), as with their `get_path` methods. Evaluates to an `Option<&Value>`
+///
+/// ## Example
+/// ```
+/// use skytable::{response::{Response, Row, Value}, value_path};
+///
+/// let resp = Response::Row(Row::from(vec![
+///     Value::String("sayan".into()),
+///     Value::List(vec![Value::List(vec![Value::UInt8(1), Value::UInt8(2)])]),
+/// ]));
+/// assert_eq!(value_path!(resp, 1, 0, 1), Some(&Value::UInt8(2)));
+/// assert_eq!(value_path!(resp, 1, 1, 0), None);
+/// ```
+macro_rules! value_path {
+    ($v:expr $(, $index:expr)* $(,)?) => {
+        $v.get_path(&[$($index),*])
+    };
+}
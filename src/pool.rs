@@ -25,6 +25,7 @@
 //! use skytable::{pool, Config};
 //!
 //! const POOL_SIZE: u32 = 32; // we'll have atmost 32 connections in the pool
+//! # #[cfg(feature = "aio")]
 //! async fn pool() {
 //!     let pool = pool::get_async(POOL_SIZE, Config::new_default("username", "password")).await.unwrap();
 //!     let mut db = pool.get().await.unwrap();
@@ -35,25 +36,142 @@
 //! use skytable::{pool, Config};
 //!
 //! const POOL_SIZE: u32 = 32; // we'll have atmost 32 connections in the pool
+//! # #[cfg(feature = "sync")]
 //! fn pool() {
 //!     let pool = pool::get(POOL_SIZE, Config::new_default("username", "password")).unwrap();
 //!     let mut db = pool.get().unwrap();
 //! }
 //! ```
 //!
-//! To create a pool of TLS connections you can use the [`get_tls`] and [`get_tls_async`] methods, passing a PEM certificate
+//! To create a pool of TLS connections you can use the `get_tls` and `get_tls_async` methods, passing a PEM certificate
 //! as a string.
 //!
+//! The sync pools require the `sync` feature and the async pools require the `aio` feature (both are enabled by default).
+//!
+//! If you're already using [`deadpool`](https://docs.rs/deadpool) for other databases, you can enable the `pool-deadpool`
+//! feature and use the managers in the `deadpool` submodule instead.
+//!
+//! ## One-off queries
+//!
+//! To run a single query without holding on to a connection, use `query_async` or `query_parse_async` (or
+//! `deadpool::query` and `deadpool::query_parse`), which check out a connection, run the query and return the
+//! connection to the pool:
+//!
+//! ```no_run
+//! use skytable::{pool, query, Config};
+//!
+//! # #[cfg(feature = "aio")]
+//! async fn followers() -> Result<u64, Box<dyn std::error::Error>> {
+//!     let pool = pool::get_async(32, Config::new_default("username", "password")).await?;
+//!     let q = query!("select followers from myspace.mymodel where username = ?", "sayan");
+//!     Ok(pool::query_parse_async(&pool, &q).await?)
+//! }
+//! ```
+//!
+//! ## Waiting for a connection
+//!
+//! When all connections are in use, `get()` waits until one is returned (until the pool's connection timeout for
+//! `r2d2` and `bb8`, and without a time limit for `deadpool`). To choose the timeout for each checkout, use
+//! [`Acquire::acquire`] for sync pools or [`AcquireAsync::acquire`] for async pools. If no connection became available
+//! in time, the error has a [`PoolTimeout`] with a snapshot of the pool:
+//!
+//! ```no_run
+//! use {skytable::{pool, Config}, std::time::Duration};
+//!
+//! # #[cfg(feature = "aio")]
+//! async fn pool() -> Result<(), Box<dyn std::error::Error>> {
+//!     use skytable::pool::AcquireAsync;
+//!     let pool = pool::get_async(32, Config::new_default("username", "password")).await?;
+//!     let mut db = pool.acquire(Duration::from_secs(5)).await?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ## Spaces
+//!
+//! If the [`Config`] has a space (see [`Config::with_space`]), every connection is switched to it when it is
+//! established. A connection that was switched to a different space with `use_space` is not returned to the `r2d2` or
+//! `bb8` pools (so the next user can't accidentally run queries in the wrong space), while the `deadpool` managers
+//! switch it back to the configured space before reusing it. If different parts of your application use different
+//! spaces, create a pool for each space, or use `deadpool::get_with_space` (which skips the switch when the connection
+//! is already in the space).
+//!
+//! ## Metrics
+//!
+//! To keep track of what a pool is doing, create a [`PoolMetrics`], attach it to the connection manager with
+//! `with_metrics` and build the pool yourself. [`PoolMetrics::snapshot_of`] returns the counters along with the
+//! current size of the pool:
+//!
+//! ```no_run
+//! use skytable::{pool::{ConnectionMgrTcp, PoolMetrics}, Config};
+//!
+//! # #[cfg(feature = "sync")]
+//! fn pool() {
+//!     let metrics = PoolMetrics::with_event_handler(|event| println!("pool event: {event:?}"));
+//!     let mgr = ConnectionMgrTcp::new(Config::new_default("username", "password")).with_metrics(metrics.clone());
+//!     // the event handler also records how long checkouts had to wait
+//!     let pool = r2d2::Pool::builder()
+//!         .max_size(32)
+//!         .event_handler(Box::new(metrics.clone()))
+//!         .build(mgr)
+//!         .unwrap();
+//!     let stats = metrics.snapshot_of(&pool);
+//!     println!(
+//!         "size={:?} idle={:?} in_flight={:?} created={} discarded={}",
+//!         stats.size(), stats.idle(), stats.in_flight(), stats.created(), stats.discarded()
+//!     );
+//! }
+//! ```
+//!
+//! `bb8` doesn't report checkouts, so their wait times (and timeouts) are only recorded for connections that are checked
+//! out with `PoolMetrics::checkout_async`. Checkouts made with `get`, `AcquireAsync::acquire` or `query_async` aren't
+//! part of the wait statistics.
+//!
+
+#[cfg(feature = "aio")]
+use {
+    crate::{
+        aio::TcpConnection,
+        query::Query,
+        response::{FromResponse, Response},
+        ConnectionAsync, ConnectionTlsAsync,
+    },
+    std::ops::DerefMut,
+    tokio::io::{AsyncRead, AsyncWrite},
+};
+use {
+    crate::{
+        config::TlsConfig,
+        error::{ClientResult, Error},
+        Config,
+    },
+    core::fmt,
+    std::{
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+        time::{Duration, Instant},
+    },
+};
+#[cfg(feature = "sync")]
+use {
+    crate::{Connection, ConnectionTls},
+    core::convert::Infallible,
+};
 
-use crate::{error::Error, Config, Connection, ConnectionAsync, ConnectionTls, ConnectionTlsAsync};
+#[cfg(feature = "pool-deadpool")]
+pub mod deadpool;
 
 const QUERY_SYSCTL_STATUS: &str = "sysctl report status";
 
+#[cfg(feature = "sync")]
 /// Returns a TCP (skyhash/TCP) connection pool using [`r2d2`]'s default settings and the given maximum pool size
 pub fn get(pool_size: u32, config: Config) -> Result<r2d2::Pool<ConnectionMgrTcp>, r2d2::Error> {
     let mgr = ConnectionMgrTcp::new(config);
     r2d2::Pool::builder().max_size(pool_size).build(mgr)
 }
+#[cfg(feature = "aio")]
 /// Returns an async TCP (skyhash/TCP) connection pool using [`bb8`]'s default settings and the given maximum pool size
 pub async fn get_async(
     pool_size: u32,
@@ -62,6 +180,7 @@ pub async fn get_async(
     let mgr = ConnectionMgrTcp::new(config);
     bb8::Pool::builder().max_size(pool_size).build(mgr).await
 }
+#[cfg(feature = "sync")]
 /// Returns a TLS (skyhash/TLS) connection pool using [`r2d2`]'s default settings and the given maximum pool size
 pub fn get_tls(
     pool_size: u32,
@@ -71,6 +190,7 @@ pub fn get_tls(
     let mgr = ConnectionMgrTls::new(config, pem_cert.into());
     r2d2::Pool::builder().max_size(pool_size).build(mgr)
 }
+#[cfg(feature = "aio")]
 /// Returns an async TLS (skyhash/TCP) connection pool using [`bb8`]'s default settings and the given maximum pool size
 pub async fn get_tls_async(
     pool_size: u32,
@@ -80,54 +200,222 @@ pub async fn get_tls_async(
     let mgr = ConnectionMgrTls::new(config, pem_cert.into());
     bb8::Pool::builder().max_size(pool_size).build(mgr).await
 }
+#[cfg(feature = "aio")]
+/// Check out a connection from an async pool, run `q` on it and return it to the pool. Connections that break while
+/// running the query (for example, because of an I/O error) are discarded by the pool.
+pub async fn query_async<M, S>(
+    pool: &bb8::Pool<M>,
+    q: &Query,
+) -> Result<Response, bb8::RunError<Error>>
+where
+    M: bb8::ManageConnection<Error = Error>,
+    M::Connection: DerefMut<Target = TcpConnection<S>>,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut con = pool.get().await?;
+    con.query(q).await.map_err(bb8::RunError::User)
+}
+#[cfg(feature = "aio")]
+/// Same as [`query_async`], but parses the response into `T`. The connection is returned to the pool even if the
+/// response can't be parsed (since it can still be used).
+pub async fn query_parse_async<M, S, T>(
+    pool: &bb8::Pool<M>,
+    q: &Query,
+) -> Result<T, bb8::RunError<Error>>
+where
+    M: bb8::ManageConnection<Error = Error>,
+    M::Connection: DerefMut<Target = TcpConnection<S>>,
+    S: AsyncRead + AsyncWrite + Unpin,
+    T: FromResponse,
+{
+    query_async(pool, q)
+        .await
+        .and_then(|resp| resp.parse().map_err(bb8::RunError::User))
+}
 
-#[derive(Debug, Clone, PartialEq)]
-/// A connection manager for Skyhash/TCP connections
+#[cfg(feature = "sync")]
+/// Check out a connection from a sync pool, giving up after a timeout
+pub trait Acquire {
+    /// The connection that is checked out
+    type Connection;
+    /// The error returned if the pool couldn't create a connection
+    type Error;
+    /// Check out a connection, waiting at most `timeout` for one to become available. If none did, an
+    /// [`AcquireError::Timeout`] with the state of the pool at that moment is returned.
+    fn acquire(&self, timeout: Duration) -> Result<Self::Connection, AcquireError<Self::Error>>;
+}
+
+#[cfg(feature = "sync")]
+/// [`r2d2`] reports connection errors to the pool's error handler and keeps retrying until the timeout elapses, so a
+/// failed checkout is always an [`AcquireError::Timeout`]. Waiters aren't guaranteed to be served in FIFO order.
+impl<M: r2d2::ManageConnection> Acquire for r2d2::Pool<M> {
+    type Connection = r2d2::PooledConnection<M>;
+    type Error = Infallible;
+    fn acquire(&self, timeout: Duration) -> Result<Self::Connection, AcquireError<Self::Error>> {
+        let start = Instant::now();
+        self.get_timeout(timeout).map_err(|_| {
+            let state = self.state();
+            AcquireError::Timeout(PoolTimeout {
+                waited: start.elapsed(),
+                max_size: Some(self.max_size() as usize),
+                size: state.connections as usize,
+                in_use: (state.connections - state.idle_connections) as usize,
+                waiting: None,
+            })
+        })
+    }
+}
+
+#[cfg(feature = "aio")]
+#[async_trait::async_trait]
+/// Check out a connection from an async pool, giving up after a timeout
+pub trait AcquireAsync {
+    /// The connection that is checked out
+    type Connection;
+    /// The error returned if the pool couldn't create or recycle a connection
+    type Error;
+    /// Check out a connection, waiting at most `timeout` for one to become available. If none did, an
+    /// [`AcquireError::Timeout`] with the state of the pool at that moment is returned.
+    async fn acquire(
+        &self,
+        timeout: Duration,
+    ) -> Result<Self::Connection, AcquireError<Self::Error>>;
+}
+
+#[cfg(feature = "aio")]
+#[async_trait::async_trait]
+/// Waiters are woken up in FIFO order, but a checkout that starts just as a connection is returned can still take it
+/// first. [`bb8`] doesn't expose the maximum size of the pool or the number of waiters, so these aren't part of the
+/// [`PoolTimeout`].
+impl<M: bb8::ManageConnection> AcquireAsync for bb8::Pool<M> {
+    type Connection = bb8::PooledConnection<'static, M>;
+    type Error = M::Error;
+    async fn acquire(
+        &self,
+        timeout: Duration,
+    ) -> Result<Self::Connection, AcquireError<Self::Error>> {
+        let start = Instant::now();
+        match tokio::time::timeout(timeout, self.get_owned()).await {
+            Ok(Ok(con)) => Ok(con),
+            Ok(Err(bb8::RunError::User(e))) => Err(AcquireError::Pool(e)),
+            Ok(Err(bb8::RunError::TimedOut)) | Err(_) => {
+                let state = self.state();
+                Err(AcquireError::Timeout(PoolTimeout {
+                    waited: start.elapsed(),
+                    max_size: None,
+                    size: state.connections as usize,
+                    in_use: (state.connections - state.idle_connections) as usize,
+                    waiting: None,
+                }))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A connection manager for Skyhash/TCP connections. Managers are equal if they have the same configuration, no matter
+/// which metrics they record to
 pub struct ConnectionMgrTcp {
     config: Config,
+    metrics: PoolMetrics,
+}
+
+impl PartialEq for ConnectionMgrTcp {
+    fn eq(&self, other: &Self) -> bool {
+        self.config == other.config
+    }
 }
 
 impl ConnectionMgrTcp {
     /// Create a new connection manager for Skyhash/TCP connections
     pub fn new(config: Config) -> Self {
-        Self { config }
+        Self {
+            config,
+            metrics: PoolMetrics::new(),
+        }
+    }
+    /// Record the activity of this manager in `metrics`
+    pub fn with_metrics(mut self, metrics: PoolMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+    /// Returns the metrics for this manager
+    pub fn metrics(&self) -> &PoolMetrics {
+        &self.metrics
     }
 }
 
+#[cfg(feature = "sync")]
 impl r2d2::ManageConnection for ConnectionMgrTcp {
     type Connection = Connection;
     type Error = Error;
     fn connect(&self) -> Result<Self::Connection, Self::Error> {
-        self.config.connect()
+        self.metrics.connected(self.config.connect())
     }
     fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
-        conn.query_parse::<()>(&query!(QUERY_SYSCTL_STATUS))
+        self.metrics
+            .validated(conn.query_parse::<()>(&query!(QUERY_SYSCTL_STATUS)))
     }
-    fn has_broken(&self, _: &mut Self::Connection) -> bool {
-        false
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        conn.current_space() != self.config.space()
     }
 }
 
+#[cfg(feature = "aio")]
 #[async_trait::async_trait]
 impl bb8::ManageConnection for ConnectionMgrTcp {
     type Connection = ConnectionAsync;
     type Error = Error;
     async fn connect(&self) -> Result<Self::Connection, Self::Error> {
-        self.config.connect_async().await
+        self.metrics.connected(self.config.connect_async().await)
     }
     async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
-        conn.query_parse::<()>(&query!(QUERY_SYSCTL_STATUS)).await
+        self.metrics
+            .validated(conn.query_parse::<()>(&query!(QUERY_SYSCTL_STATUS)).await)
     }
-    fn has_broken(&self, _: &mut Self::Connection) -> bool {
-        false
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        conn.is_broken() || conn.current_space() != self.config.space()
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
-/// A connection manager for Skyhash/TLS connections
+/// The certificates trusted by a TLS connection manager
+enum TlsTrust {
+    /// a PEM certificate that is trusted along with the system roots (parsed on every connect)
+    Pem(String),
+    Config(TlsConfig),
+}
+
+impl TlsTrust {
+    #[cfg(feature = "sync")]
+    fn connect(&self, config: &Config) -> ClientResult<ConnectionTls> {
+        match self {
+            Self::Pem(pem) => config.connect_tls(pem),
+            Self::Config(tls) => config.connect_tls_with(tls),
+        }
+    }
+    #[cfg(feature = "aio")]
+    async fn connect_async(&self, config: &Config) -> ClientResult<ConnectionTlsAsync> {
+        match self {
+            Self::Pem(pem) => config.connect_tls_async(pem).await,
+            Self::Config(tls) => config.connect_tls_async_with(tls).await,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A connection manager for Skyhash/TLS connections. Managers are equal if they have the same configuration and trust
+/// the same certificates, no matter which metrics they record to
 pub struct ConnectionMgrTls {
     config: Config,
-    pem_cert: String,
+    tls: TlsTrust,
+    metrics: PoolMetrics,
+}
+
+impl PartialEq for ConnectionMgrTls {
+    fn eq(&self, other: &Self) -> bool {
+        self.config == other.config && self.tls == other.tls
+    }
 }
 
 impl ConnectionMgrTls {
@@ -136,35 +424,810 @@ impl ConnectionMgrTls {
     /// The `pem_cert` argument must contain your TLS certificate in a PEM format.
     /// **NOTE: The `pem_cert` argument does NOT accept a file path!**
     pub fn new(config: Config, pem_cert: String) -> Self {
-        Self { config, pem_cert }
+        Self {
+            config,
+            tls: TlsTrust::Pem(pem_cert),
+            metrics: PoolMetrics::new(),
+        }
+    }
+    /// Create a new connection manager for Skyhash/TLS connections that trust the certificate authorities in `tls`
+    pub fn with_tls_config(config: Config, tls: TlsConfig) -> Self {
+        Self {
+            config,
+            tls: TlsTrust::Config(tls),
+            metrics: PoolMetrics::new(),
+        }
+    }
+    /// Record the activity of this manager in `metrics`
+    pub fn with_metrics(mut self, metrics: PoolMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+    /// Returns the metrics for this manager
+    pub fn metrics(&self) -> &PoolMetrics {
+        &self.metrics
     }
 }
 
+#[cfg(feature = "sync")]
 impl r2d2::ManageConnection for ConnectionMgrTls {
     type Connection = ConnectionTls;
     type Error = Error;
     fn connect(&self) -> Result<Self::Connection, Self::Error> {
-        self.config.connect_tls(&self.pem_cert)
+        self.metrics.connected(self.tls.connect(&self.config))
     }
     fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
-        conn.query_parse::<()>(&query!(QUERY_SYSCTL_STATUS))
+        self.metrics
+            .validated(conn.query_parse::<()>(&query!(QUERY_SYSCTL_STATUS)))
     }
-    fn has_broken(&self, _: &mut Self::Connection) -> bool {
-        false
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        conn.current_space() != self.config.space()
     }
 }
 
+#[cfg(feature = "aio")]
 #[async_trait::async_trait]
 impl bb8::ManageConnection for ConnectionMgrTls {
     type Connection = ConnectionTlsAsync;
     type Error = Error;
     async fn connect(&self) -> Result<Self::Connection, Self::Error> {
-        self.config.connect_tls_async(&self.pem_cert).await
+        self.metrics
+            .connected(self.tls.connect_async(&self.config).await)
     }
     async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
-        conn.query_parse::<()>(&query!(QUERY_SYSCTL_STATUS)).await
+        self.metrics
+            .validated(conn.query_parse::<()>(&query!(QUERY_SYSCTL_STATUS)).await)
+    }
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        conn.is_broken() || conn.current_space() != self.config.space()
+    }
+}
+
+/*
+    metrics
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An event reported to the handler passed to [`PoolMetrics::with_event_handler`]
+pub enum PoolEvent {
+    /// A new connection was established
+    Created,
+    /// A new connection couldn't be established
+    ConnectFailed,
+    /// A connection passed validation and was handed out
+    Recycled,
+    /// A connection failed validation and was discarded
+    Discarded,
+}
+
+type EventHandler = Box<dyn Fn(PoolEvent) + Send + Sync>;
+
+#[derive(Default)]
+struct MetricsInner {
+    created: AtomicU64,
+    connect_errors: AtomicU64,
+    recycled: AtomicU64,
+    discarded: AtomicU64,
+    timeouts: AtomicU64,
+    waits: Mutex<WaitStats>,
+    on_event: Option<EventHandler>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct WaitStats {
+    count: u64,
+    total: Duration,
+    min: Option<Duration>,
+    max: Option<Duration>,
+}
+
+#[derive(Clone, Default)]
+/// Counters for a connection pool, shared by all clones of this value (and the managers that they're attached to).
+///
+/// The counters are updated by the connection manager. The checkout wait times and timeouts are only recorded if the
+/// metrics are also set as the event handler of an [`r2d2`] pool, or if connections are checked out of a [`bb8`] pool
+/// with [`Self::checkout_async`] (since `bb8` doesn't report them). The size of the pool is read from the pool itself
+/// by [`Self::snapshot_of`].
+pub struct PoolMetrics {
+    inner: Arc<MetricsInner>,
+}
+
+impl PoolMetrics {
+    /// Create a new set of metrics with all counters set to zero
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Same as [`Self::new`], but also calls `f` for every [`PoolEvent`]. The handler runs on the task (or thread) that
+    /// caused the event, so it should return quickly
+    pub fn with_event_handler(f: impl Fn(PoolEvent) + Send + Sync + 'static) -> Self {
+        Self {
+            inner: Arc::new(MetricsInner {
+                on_event: Some(Box::new(f)),
+                ..Default::default()
+            }),
+        }
+    }
+    /// Returns the current value of the counters along with the checkout wait times since the last call
+    ///
+    /// The [size](PoolStats::size) of the pool isn't known, so use [`Self::snapshot_of`] to include it.
+    pub fn snapshot(&self) -> PoolStats {
+        let waits = std::mem::take(&mut *self.inner.waits.lock().unwrap());
+        self.stats(waits)
+    }
+    /// Same as [`Self::snapshot`], but also includes the current size of `pool` (the pool that these metrics are
+    /// attached to)
+    pub fn snapshot_of(&self, pool: &impl PoolSize) -> PoolStats {
+        let (size, idle) = pool.size_and_idle();
+        PoolStats {
+            size: Some(size),
+            idle: Some(idle),
+            ..self.snapshot()
+        }
+    }
+    fn stats(&self, waits: WaitStats) -> PoolStats {
+        PoolStats {
+            size: None,
+            idle: None,
+            created: self.inner.created.load(Ordering::Relaxed),
+            connect_errors: self.inner.connect_errors.load(Ordering::Relaxed),
+            recycled: self.inner.recycled.load(Ordering::Relaxed),
+            discarded: self.inner.discarded.load(Ordering::Relaxed),
+            timeouts: self.inner.timeouts.load(Ordering::Relaxed),
+            waits,
+        }
+    }
+    #[cfg(feature = "aio")]
+    /// Check out a connection from `pool`, recording how long the checkout waited (or that it timed out) in these
+    /// metrics
+    pub async fn checkout_async<'a, M>(
+        &self,
+        pool: &'a bb8::Pool<M>,
+    ) -> Result<bb8::PooledConnection<'a, M>, bb8::RunError<M::Error>>
+    where
+        M: bb8::ManageConnection,
+    {
+        let start = Instant::now();
+        let r = pool.get().await;
+        match r {
+            Ok(_) => self.waited(start.elapsed()),
+            Err(bb8::RunError::TimedOut) => {
+                self.inner.timeouts.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(bb8::RunError::User(_)) => {}
+        }
+        r
+    }
+    fn waited(&self, wait: Duration) {
+        let mut waits = self.inner.waits.lock().unwrap();
+        waits.count += 1;
+        waits.total = waits.total.saturating_add(wait);
+        waits.min = Some(waits.min.map_or(wait, |min| min.min(wait)));
+        waits.max = Some(waits.max.map_or(wait, |max| max.max(wait)));
+    }
+    fn event(&self, counter: &AtomicU64, event: PoolEvent) {
+        counter.fetch_add(1, Ordering::Relaxed);
+        if let Some(ref f) = self.inner.on_event {
+            f(event)
+        }
+    }
+    fn connected<T>(&self, r: Result<T, Error>) -> Result<T, Error> {
+        match r {
+            Ok(_) => self.event(&self.inner.created, PoolEvent::Created),
+            Err(_) => self.event(&self.inner.connect_errors, PoolEvent::ConnectFailed),
+        }
+        r
     }
-    fn has_broken(&self, _: &mut Self::Connection) -> bool {
-        false
+    fn validated(&self, r: Result<(), Error>) -> Result<(), Error> {
+        match r {
+            Ok(()) => self.event(&self.inner.recycled, PoolEvent::Recycled),
+            Err(_) => self.event(&self.inner.discarded, PoolEvent::Discarded),
+        }
+        r
     }
 }
+
+impl PartialEq for PoolMetrics {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl fmt::Debug for PoolMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let waits = *self.inner.waits.lock().unwrap();
+        f.debug_tuple("PoolMetrics")
+            .field(&self.stats(waits))
+            .finish()
+    }
+}
+
+#[cfg(feature = "sync")]
+impl r2d2::HandleEvent for PoolMetrics {
+    fn handle_checkout(&self, event: r2d2::event::CheckoutEvent) {
+        self.waited(event.duration())
+    }
+    fn handle_timeout(&self, _: r2d2::event::TimeoutEvent) {
+        self.inner.timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A pool whose current size can be included in a [`PoolStats`] (see [`PoolMetrics::snapshot_of`])
+pub trait PoolSize {
+    /// Returns the number of connections in the pool, and how many of them are idle
+    fn size_and_idle(&self) -> (usize, usize);
+}
+
+#[cfg(feature = "sync")]
+impl<M: r2d2::ManageConnection> PoolSize for r2d2::Pool<M> {
+    fn size_and_idle(&self) -> (usize, usize) {
+        let state = self.state();
+        (state.connections as usize, state.idle_connections as usize)
+    }
+}
+
+#[cfg(feature = "aio")]
+impl<M: bb8::ManageConnection> PoolSize for bb8::Pool<M> {
+    fn size_and_idle(&self) -> (usize, usize) {
+        let state = self.state();
+        (state.connections as usize, state.idle_connections as usize)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A snapshot of [`PoolMetrics`]
+pub struct PoolStats {
+    size: Option<usize>,
+    idle: Option<usize>,
+    created: u64,
+    connect_errors: u64,
+    recycled: u64,
+    discarded: u64,
+    timeouts: u64,
+    waits: WaitStats,
+}
+
+impl PoolStats {
+    /// Returns the number of connections in the pool, if the snapshot was taken with [`PoolMetrics::snapshot_of`]
+    pub fn size(&self) -> Option<usize> {
+        self.size
+    }
+    /// Returns the number of idle connections in the pool, if the snapshot was taken with
+    /// [`PoolMetrics::snapshot_of`]
+    pub fn idle(&self) -> Option<usize> {
+        self.idle
+    }
+    /// Returns the number of connections that are checked out of the pool, if the snapshot was taken with
+    /// [`PoolMetrics::snapshot_of`]
+    pub fn in_flight(&self) -> Option<usize> {
+        Some(self.size?.saturating_sub(self.idle?))
+    }
+    /// Returns the number of connections that were established
+    pub fn created(&self) -> u64 {
+        self.created
+    }
+    /// Returns the number of times a new connection couldn't be established
+    pub fn connect_errors(&self) -> u64 {
+        self.connect_errors
+    }
+    /// Returns the number of times a connection passed validation and was handed out
+    pub fn recycled(&self) -> u64 {
+        self.recycled
+    }
+    /// Returns the number of connections that failed validation and were discarded
+    pub fn discarded(&self) -> u64 {
+        self.discarded
+    }
+    /// Returns the number of checkouts that timed out waiting for a connection (see [`PoolMetrics`])
+    pub fn timeouts(&self) -> u64 {
+        self.timeouts
+    }
+    /// Returns the number of successful checkouts since the last snapshot (see [`PoolMetrics`])
+    pub fn checkouts(&self) -> u64 {
+        self.waits.count
+    }
+    /// Returns the shortest time a checkout had to wait for a connection since the last snapshot (see [`PoolMetrics`])
+    pub fn wait_min(&self) -> Option<Duration> {
+        self.waits.min
+    }
+    /// Returns the longest time a checkout had to wait for a connection since the last snapshot (see [`PoolMetrics`])
+    pub fn wait_max(&self) -> Option<Duration> {
+        self.waits.max
+    }
+    /// Returns the average time a checkout had to wait for a connection since the last snapshot (see [`PoolMetrics`])
+    pub fn wait_mean(&self) -> Option<Duration> {
+        if self.waits.count == 0 {
+            None
+        } else {
+            // `count as u32` could truncate, so divide the nanoseconds instead
+            let mean = self.waits.total.as_nanos() / u128::from(self.waits.count);
+            Some(Duration::new(
+                (mean / 1_000_000_000) as u64,
+                (mean % 1_000_000_000) as u32,
+            ))
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Returned by [`Acquire::acquire`] and [`AcquireAsync::acquire`] if a connection couldn't be checked out
+pub enum AcquireError<E> {
+    /// No connection became available in time
+    Timeout(PoolTimeout),
+    /// The pool failed to create or recycle a connection
+    Pool(E),
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for AcquireError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Timeout(_) => None,
+            Self::Pool(e) => Some(e),
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for AcquireError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout(t) => write!(f, "{t}"),
+            Self::Pool(e) => write!(f, "failed to check out a connection: {e}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The state of the pool when a checkout gave up waiting for a connection
+pub struct PoolTimeout {
+    waited: Duration,
+    max_size: Option<usize>,
+    size: usize,
+    in_use: usize,
+    waiting: Option<usize>,
+}
+
+impl PoolTimeout {
+    /// Returns how long the checkout waited
+    pub fn waited(&self) -> Duration {
+        self.waited
+    }
+    /// Returns the maximum number of connections in the pool (if the pool exposes it)
+    pub fn max_size(&self) -> Option<usize> {
+        self.max_size
+    }
+    /// Returns the number of connections in the pool (idle or in use)
+    pub fn size(&self) -> usize {
+        self.size
+    }
+    /// Returns the number of connections that were checked out
+    pub fn in_use(&self) -> usize {
+        self.in_use
+    }
+    /// Returns the number of other tasks that were still waiting for a connection (if the pool exposes it)
+    pub fn waiting(&self) -> Option<usize> {
+        self.waiting
+    }
+}
+
+impl fmt::Display for PoolTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "timed out after {:?} waiting for a connection ({} of {} in use",
+            self.waited, self.in_use, self.size
+        )?;
+        if let Some(max_size) = self.max_size {
+            write!(f, ", max {max_size}")?;
+        }
+        if let Some(waiting) = self.waiting {
+            write!(f, ", {waiting} other waiters")?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(all(test, any(feature = "sync", feature = "aio")))]
+/// Start a server that accepts connections, completes the handshake and then replies to every read with `response` (an
+/// empty response means that queries are never answered). Returns the port
+pub(crate) fn mock_server(response: &'static [u8]) -> u16 {
+    counting_mock_server(response).0
+}
+
+#[cfg(all(test, any(feature = "sync", feature = "aio")))]
+/// Same as [`mock_server`], but also returns the number of reads (queries) that were answered across all connections
+fn counting_mock_server(response: &'static [u8]) -> (u16, Arc<AtomicU64>) {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let queries = Arc::new(AtomicU64::new(0));
+    let queries_ = queries.clone();
+    thread::spawn(move || {
+        for con in listener.incoming() {
+            let mut con = con.unwrap();
+            let queries = queries_.clone();
+            thread::spawn(move || {
+                let mut buf = [0u8; 128];
+                let _ = con.read(&mut buf).unwrap();
+                con.write_all(&[b'H', 0, 0, 0]).unwrap();
+                while let Ok(n) = con.read(&mut buf) {
+                    if n == 0 {
+                        break;
+                    }
+                    queries.fetch_add(1, Ordering::SeqCst);
+                    if con.write_all(response).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    (port, queries)
+}
+
+#[test]
+#[cfg(feature = "sync")]
+fn metrics_pool_exhaustion() {
+    let events = Arc::new(Mutex::new(vec![]));
+    let events_ = events.clone();
+    let metrics = PoolMetrics::with_event_handler(move |e| events_.lock().unwrap().push(e));
+    let mgr = ConnectionMgrTcp::new(Config::new(
+        "127.0.0.1",
+        mock_server(b"\x12"),
+        "user",
+        "pass",
+    ))
+    .with_metrics(metrics.clone());
+    let pool = r2d2::Pool::builder()
+        .max_size(2)
+        .min_idle(Some(0))
+        .connection_timeout(Duration::from_millis(100))
+        .event_handler(Box::new(metrics.clone()))
+        .build(mgr)
+        .unwrap();
+    let a = pool.get().unwrap();
+    let b = pool.get().unwrap();
+    // exhausted
+    assert!(pool.get().is_err());
+    let stats = metrics.snapshot_of(&pool);
+    assert_eq!(
+        (stats.size(), stats.idle(), stats.in_flight()),
+        (Some(2), Some(0), Some(2))
+    );
+    assert_eq!(stats.created(), 2);
+    assert_eq!(stats.recycled(), 2);
+    assert_eq!(stats.timeouts(), 1);
+    assert_eq!(stats.checkouts(), 2);
+    assert!(stats.wait_min().unwrap() <= stats.wait_max().unwrap());
+    // return the connections and check one out again
+    drop((a, b));
+    let _c = pool.get().unwrap();
+    let stats = metrics.snapshot_of(&pool);
+    assert_eq!(
+        (stats.size(), stats.idle(), stats.in_flight()),
+        (Some(2), Some(1), Some(1))
+    );
+    assert_eq!(metrics.snapshot().size(), None);
+    assert_eq!(stats.created(), 2);
+    assert_eq!(stats.recycled(), 3);
+    assert_eq!(stats.checkouts(), 1);
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            PoolEvent::Created,
+            PoolEvent::Recycled,
+            PoolEvent::Created,
+            PoolEvent::Recycled,
+            PoolEvent::Recycled
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "sync")]
+fn metrics_connection_errors() {
+    let metrics = PoolMetrics::new();
+    let mgr = ConnectionMgrTcp::new(Config::new(
+        "127.0.0.1",
+        mock_server(b"\x10\x05\x00"),
+        "user",
+        "pass",
+    ))
+    .with_metrics(metrics.clone());
+    let pool = r2d2::Pool::builder()
+        .max_size(1)
+        .min_idle(Some(0))
+        .connection_timeout(Duration::from_millis(100))
+        .build(mgr)
+        .unwrap();
+    // every connection fails validation
+    assert!(pool.get().is_err());
+    let stats = metrics.snapshot();
+    assert!(stats.discarded() >= 1);
+    assert_eq!(stats.recycled(), 0);
+    // nothing is listening on this port
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let mgr = ConnectionMgrTcp::new(Config::new("127.0.0.1", port, "user", "pass"))
+        .with_metrics(metrics.clone());
+    r2d2::ManageConnection::connect(&mgr).unwrap_err();
+    assert_eq!(metrics.snapshot().connect_errors(), 1);
+}
+
+#[test]
+#[cfg(feature = "aio")]
+fn metrics_async_checkouts() {
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let metrics = PoolMetrics::new();
+        let mgr = ConnectionMgrTcp::new(Config::new(
+            "127.0.0.1",
+            mock_server(b"\x12"),
+            "user",
+            "pass",
+        ))
+        .with_metrics(metrics.clone());
+        let pool = bb8::Pool::builder()
+            .max_size(1)
+            .connection_timeout(Duration::from_millis(100))
+            .build(mgr)
+            .await
+            .unwrap();
+        let con = metrics.checkout_async(&pool).await.unwrap();
+        // exhausted
+        assert!(matches!(
+            metrics.checkout_async(&pool).await,
+            Err(bb8::RunError::TimedOut)
+        ));
+        drop(con);
+        let _con = metrics.checkout_async(&pool).await.unwrap();
+        // checkouts that don't go through the metrics aren't recorded
+        assert!(pool.get().await.is_err());
+        let stats = metrics.snapshot_of(&pool);
+        assert_eq!(
+            (stats.size(), stats.idle(), stats.in_flight()),
+            (Some(1), Some(0), Some(1))
+        );
+        assert_eq!((stats.checkouts(), stats.timeouts()), (2, 1));
+        assert!(stats.wait_min().unwrap() <= stats.wait_mean().unwrap());
+        assert!(stats.wait_mean().unwrap() <= stats.wait_max().unwrap());
+    })
+}
+
+#[test]
+#[cfg(feature = "sync")]
+fn acquire_timeout() {
+    let pool = get(
+        1,
+        Config::new("127.0.0.1", mock_server(b"\x12"), "user", "pass"),
+    )
+    .unwrap();
+    let _held = pool.acquire(Duration::from_secs(5)).unwrap();
+    // the pool is saturated
+    let timeout = Duration::from_millis(50);
+    let e = match pool.acquire(timeout) {
+        Err(AcquireError::Timeout(e)) => e,
+        other => panic!("expected a timeout, got {:?}", other.map(|_| ())),
+    };
+    assert!(e.waited() >= timeout);
+    assert_eq!((e.max_size(), e.size(), e.in_use()), (Some(1), 1, 1));
+    assert_eq!(e.waiting(), None);
+}
+
+#[test]
+#[cfg(feature = "aio")]
+fn acquire_timeout_async() {
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let pool = get_async(
+            1,
+            Config::new("127.0.0.1", mock_server(b"\x12"), "user", "pass"),
+        )
+        .await
+        .unwrap();
+        let held = pool.acquire(Duration::from_secs(5)).await.unwrap();
+        // the pool is saturated
+        let timeout = Duration::from_millis(50);
+        let e = match pool.acquire(timeout).await {
+            Err(AcquireError::Timeout(e)) => e,
+            other => panic!("expected a timeout, got {:?}", other.map(|_| ())),
+        };
+        assert!(e.waited() >= timeout);
+        assert_eq!((e.max_size(), e.size(), e.in_use()), (None, 1, 1));
+        drop(held);
+        pool.acquire(timeout).await.unwrap();
+    })
+}
+
+#[test]
+fn acquire_error_source() {
+    use std::error::Error as _;
+    let e = AcquireError::Pool(Error::EmptyPipeline);
+    assert_eq!(
+        e.source().unwrap().to_string(),
+        Error::EmptyPipeline.to_string()
+    );
+    let e: AcquireError<Error> = AcquireError::Timeout(PoolTimeout {
+        waited: Duration::from_secs(1),
+        max_size: Some(2),
+        size: 2,
+        in_use: 2,
+        waiting: Some(3),
+    });
+    assert!(e.source().is_none());
+    assert_eq!(
+        e.to_string(),
+        "timed out after 1s waiting for a connection (2 of 2 in use, max 2, 3 other waiters)"
+    );
+}
+
+#[test]
+fn manager_eq() {
+    let config = Config::new_default("user", "pass");
+    let a = ConnectionMgrTcp::new(config.clone());
+    // the metrics aren't compared
+    assert_eq!(a, a.clone().with_metrics(PoolMetrics::new()));
+    assert_ne!(
+        a,
+        ConnectionMgrTcp::new(config.clone().with_space("myspace"))
+    );
+    let tls = ConnectionMgrTls::new(config.clone(), "pem".into());
+    assert_eq!(tls, tls.clone().with_metrics(PoolMetrics::new()));
+    assert_ne!(tls, ConnectionMgrTls::new(config, "other pem".into()));
+}
+
+#[test]
+fn wait_mean() {
+    let stats = |count, total| PoolStats {
+        size: None,
+        idle: None,
+        created: 0,
+        connect_errors: 0,
+        recycled: 0,
+        discarded: 0,
+        timeouts: 0,
+        waits: WaitStats {
+            count,
+            total,
+            min: None,
+            max: None,
+        },
+    };
+    assert_eq!(stats(0, Duration::ZERO).wait_mean(), None);
+    assert_eq!(
+        stats(4, Duration::from_millis(10)).wait_mean(),
+        Some(Duration::from_micros(2500))
+    );
+    // more checkouts than fit in a u32
+    assert_eq!(
+        stats(1 << 33, Duration::from_secs(1 << 33)).wait_mean(),
+        Some(Duration::from_secs(1))
+    );
+}
+
+#[test]
+#[cfg(feature = "aio")]
+fn interrupted_query_discards_connection() {
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let metrics = PoolMetrics::new();
+        // the server never answers queries
+        let mgr = ConnectionMgrTcp::new(Config::new("127.0.0.1", mock_server(b""), "user", "pass"))
+            .with_metrics(metrics.clone());
+        let pool = bb8::Pool::builder()
+            .max_size(1)
+            .test_on_check_out(false)
+            .build(mgr)
+            .await
+            .unwrap();
+        {
+            let mut con = pool.get().await.unwrap();
+            // give up on the query while it's waiting for the response
+            let q = query!("sysctl report status");
+            assert!(
+                tokio::time::timeout(Duration::from_millis(50), con.query(&q))
+                    .await
+                    .is_err()
+            );
+            assert!(con.is_broken());
+        }
+        // the connection isn't handed out again
+        let mut con = pool.get().await.unwrap();
+        assert!(!con.is_broken());
+        assert_eq!(metrics.snapshot().created(), 2);
+        // a closed connection is also discarded
+        con.close().await.unwrap();
+        assert!(con.is_broken());
+    })
+}
+
+#[test]
+#[cfg(feature = "aio")]
+fn unflushed_connection_is_discarded() {
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let metrics = PoolMetrics::new();
+        let (port, queries) = counting_mock_server(b"\x12");
+        let mgr = ConnectionMgrTcp::new(Config::new("127.0.0.1", port, "user", "pass"))
+            .with_metrics(metrics.clone());
+        let pool = bb8::Pool::builder()
+            .max_size(1)
+            .test_on_check_out(false)
+            .build(mgr)
+            .await
+            .unwrap();
+        {
+            let mut con = pool.get().await.unwrap();
+            con.feed_query(&query!("sysctl report status"));
+            assert!(con.is_broken());
+        }
+        // the fed query is never sent, and the connection isn't handed out again
+        let mut con = pool.get().await.unwrap();
+        assert_eq!(con.pending_write_bytes(), 0);
+        assert_eq!(metrics.snapshot().created(), 2);
+        assert_eq!(queries.load(Ordering::SeqCst), 0);
+        con.query_parse::<()>(&query!("sysctl report status"))
+            .await
+            .unwrap();
+        assert_eq!(queries.load(Ordering::SeqCst), 1);
+    })
+}
+
+#[test]
+#[cfg(feature = "sync")]
+fn space_does_not_leak_between_checkouts() {
+    let metrics = PoolMetrics::new();
+    let cfg = Config::new("127.0.0.1", mock_server(b"\x12"), "user", "pass").with_space("app");
+    let mgr = ConnectionMgrTcp::new(cfg).with_metrics(metrics.clone());
+    let pool = r2d2::Pool::builder()
+        .max_size(1)
+        .min_idle(Some(0))
+        .build(mgr)
+        .unwrap();
+    {
+        let mut con = pool.get().unwrap();
+        assert_eq!(con.current_space(), Some("app"));
+        con.use_space("sessions").unwrap();
+        assert_eq!(con.current_space(), Some("sessions"));
+    }
+    // the next user gets a (new) connection in the configured space
+    let con = pool.get().unwrap();
+    assert_eq!(con.current_space(), Some("app"));
+    assert_eq!(metrics.snapshot().created(), 2);
+}
+
+#[test]
+#[cfg(feature = "aio")]
+fn one_off_queries() {
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let cfg = |response| Config::new("127.0.0.1", mock_server(response), "user", "pass");
+        let q = query!(
+            "select followers from myspace.mymodel where username = ?",
+            "sayan"
+        );
+        // the connection is returned to the pool after a parse error, and reused
+        let pool = get_async(1, cfg(b"\x12")).await.unwrap();
+        assert_eq!(query_async(&pool, &q).await.unwrap(), Response::Empty);
+        assert!(matches!(
+            query_parse_async::<_, _, u64>(&pool, &q).await,
+            Err(bb8::RunError::User(Error::ParseError(_)))
+        ));
+        query_parse_async::<_, _, ()>(&pool, &q).await.unwrap();
+        let state = pool.state();
+        assert_eq!((state.connections, state.idle_connections), (1, 1));
+        // but discarded after a protocol error
+        // (without validation on checkout, since the validation query would fail too)
+        let pool = bb8::Pool::builder()
+            .max_size(1)
+            .test_on_check_out(false)
+            .build(ConnectionMgrTcp::new(cfg(b"\x05100\n\x12")))
+            .await
+            .unwrap();
+        assert!(matches!(
+            query_parse_async::<_, _, u64>(&pool, &q).await,
+            Err(bb8::RunError::User(Error::ProtocolError(..)))
+        ));
+        assert_eq!(pool.state().connections, 0);
+    })
+}
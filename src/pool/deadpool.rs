@@ -0,0 +1,682 @@
+/*
+ * Copyright 2024, Sayan Nandan <nandansayan@outlook.com>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+*/
+
+//! # Deadpool connection pooling
+//!
+//! This module provides connection managers for [`deadpool`](https://docs.rs/deadpool), so that Skytable can be used
+//! just like any other deadpool backed database. It requires the `pool-deadpool` feature.
+//!
+//! ## Example
+//! ```no_run
+//! use skytable::{pool::deadpool, query, Config};
+//!
+//! const POOL_SIZE: usize = 32; // we'll have atmost 32 connections in the pool
+//! async fn pool() -> Result<(), Box<dyn std::error::Error>> {
+//!     let pool = deadpool::get(POOL_SIZE, Config::new_default("username", "password"))?;
+//!     let followers: u64 = pool
+//!         .get()
+//!         .await?
+//!         .query_parse(&query!("select followers from myspace.mymodel where username = ?", "sayan"))
+//!         .await?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! For one-off queries like this one, [`query`] and [`query_parse`] check out a connection, run the query and return
+//! the connection to the pool (`deadpool::query_parse(&pool, &q).await?`).
+//!
+//! ## Warming up
+//!
+//! Connections are only established when they are first checked out. To avoid paying the connection and handshake
+//! cost on the first queries, you can pre-establish connections with [`warm_up`]:
+//!
+//! ```no_run
+//! use skytable::{pool::deadpool, Config};
+//!
+//! async fn pool() -> Result<(), Box<dyn std::error::Error>> {
+//!     let pool = deadpool::get(32, Config::new_default("username", "password"))?;
+//!     // establish 8 connections before we start serving requests
+//!     deadpool::warm_up(&pool, 8).await?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`get_with_min_idle`] and [`get_tls_with_min_idle`] build the pool and warm it up in one go. (The `r2d2` and `bb8`
+//! pools have a `min_idle` setting on their builders that does the same.)
+//!
+//! ## Waiting for a connection
+//!
+//! When all connections are in use, [`Pool::get`](managed::Pool::get) waits until one is returned, without a time
+//! limit. Use [`AcquireAsync::acquire`] to give up after a while instead. The error has a snapshot of the pool, which
+//! helps when looking into why the pool ran dry:
+//!
+//! ```no_run
+//! use {
+//!     skytable::{
+//!         pool::{deadpool, AcquireAsync},
+//!         query, Config,
+//!     },
+//!     std::time::Duration,
+//! };
+//!
+//! async fn pool() -> Result<(), Box<dyn std::error::Error>> {
+//!     let pool = deadpool::get(32, Config::new_default("username", "password"))?;
+//!     let mut con = pool.acquire(Duration::from_secs(5)).await?;
+//!     con.query_parse::<()>(&query!("sysctl report status")).await?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Waiting tasks are handed connections in the order in which they started waiting, so a steady stream of new
+//! checkouts can't starve a task that has been waiting for longer.
+//!
+//! ## Spaces
+//!
+//! Connections are switched back to the configured space (see [`Config::with_space`]) before they are reused. To check
+//! out a connection for a different space, use [`get_with_space`], which only runs `use` if the connection isn't in
+//! that space already.
+
+use {
+    super::{AcquireAsync, AcquireError, PoolSize, PoolTimeout, TlsTrust, QUERY_SYSCTL_STATUS},
+    crate::{
+        aio::TcpConnection,
+        config::TlsConfig,
+        error::Error,
+        query::Query,
+        response::{FromResponse, Response},
+        Config, ConnectionAsync, ConnectionTlsAsync,
+    },
+    ::deadpool::managed::{self, BuildError, Metrics, PoolError, RecycleError, RecycleResult},
+    core::fmt,
+    std::{
+        future::{self, Future},
+        ops::DerefMut,
+        task::Poll,
+        time::{Duration, Instant},
+    },
+    tokio::io::{AsyncRead, AsyncWrite},
+};
+
+/// A deadpool pool of Skyhash/TCP connections
+pub type Pool = managed::Pool<Manager>;
+/// A Skyhash/TCP connection checked out from a [`Pool`]
+pub type Object = managed::Object<Manager>;
+/// A deadpool pool of Skyhash/TLS connections
+pub type PoolTls = managed::Pool<ManagerTls>;
+/// A Skyhash/TLS connection checked out from a [`PoolTls`]
+pub type ObjectTls = managed::Object<ManagerTls>;
+
+/// Returns a TCP (skyhash/TCP) connection pool using deadpool's default settings and the given maximum pool size
+pub fn get(pool_size: usize, config: Config) -> Result<Pool, BuildError> {
+    Pool::builder(Manager::new(config))
+        .max_size(pool_size)
+        .build()
+}
+/// Returns a TLS (skyhash/TLS) connection pool using deadpool's default settings and the given maximum pool size
+pub fn get_tls(pool_size: usize, config: Config, pem_cert: &str) -> Result<PoolTls, BuildError> {
+    PoolTls::builder(ManagerTls::new(config, pem_cert.into()))
+        .max_size(pool_size)
+        .build()
+}
+
+/// Same as [`get`], but also establishes `min_idle` connections (see [`warm_up`]) before returning the pool. The pool is
+/// returned even if some of the connections couldn't be established, along with a [`WarmUpError`] with a summary
+pub async fn get_with_min_idle(
+    pool_size: usize,
+    min_idle: usize,
+    config: Config,
+) -> Result<(Pool, Option<WarmUpError>), BuildError> {
+    let pool = get(pool_size, config)?;
+    let e = warm_up(&pool, min_idle).await.err();
+    Ok((pool, e))
+}
+/// Same as [`get_tls`], but also establishes `min_idle` connections (see [`get_with_min_idle`])
+pub async fn get_tls_with_min_idle(
+    pool_size: usize,
+    min_idle: usize,
+    config: Config,
+    pem_cert: &str,
+) -> Result<(PoolTls, Option<WarmUpError>), BuildError> {
+    let pool = get_tls(pool_size, config, pem_cert)?;
+    let e = warm_up(&pool, min_idle).await.err();
+    Ok((pool, e))
+}
+
+/// Establish up to `n` connections (limited by the pool's maximum size) and return them to the pool as idle connections.
+/// The connections are established concurrently.
+///
+/// If some connections couldn't be established, the ones that were established are kept in the pool and a
+/// [`WarmUpError`] with a summary is returned.
+pub async fn warm_up<M>(pool: &managed::Pool<M>, n: usize) -> Result<(), WarmUpError>
+where
+    M: managed::Manager<Error = Error>,
+{
+    let requested = n.min(pool.status().max_size);
+    let mut connections = Vec::with_capacity(requested);
+    let mut errors = vec![];
+    // hold on to the connections until every checkout is done, so that the pool has to establish new ones
+    let mut checkouts: Vec<_> = (0..requested).map(|_| Some(Box::pin(pool.get()))).collect();
+    future::poll_fn(|cx| {
+        let mut pending = false;
+        for slot in &mut checkouts {
+            let Some(checkout) = slot else { continue };
+            match checkout.as_mut().poll(cx) {
+                Poll::Ready(Ok(con)) => connections.push(con),
+                Poll::Ready(Err(e)) => errors.push(e),
+                Poll::Pending => {
+                    pending = true;
+                    continue;
+                }
+            }
+            *slot = None;
+        }
+        if pending {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    })
+    .await;
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(WarmUpError {
+            requested,
+            established: connections.len(),
+            errors,
+        })
+    }
+}
+
+#[derive(Debug)]
+/// Returned by [`warm_up`] if some connections couldn't be established
+pub struct WarmUpError {
+    requested: usize,
+    established: usize,
+    errors: Vec<PoolError<Error>>,
+}
+
+impl WarmUpError {
+    /// Returns the number of connections that were requested
+    pub fn requested(&self) -> usize {
+        self.requested
+    }
+    /// Returns the number of connections that were established (and are now idle in the pool)
+    pub fn established(&self) -> usize {
+        self.established
+    }
+    /// Returns the errors that occurred for each connection that couldn't be established
+    pub fn errors(&self) -> &[PoolError<Error>] {
+        &self.errors
+    }
+}
+
+impl std::error::Error for WarmUpError {}
+impl fmt::Display for WarmUpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "established {} of {} connections",
+            self.established, self.requested
+        )?;
+        if let Some(e) = self.errors.first() {
+            write!(f, " (first error: {e})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Check out a connection from `pool` and make sure that it's using `space`. If the connection is already using `space`
+/// no query is sent, otherwise the connection is switched to it.
+///
+/// If the connection can't be switched, it is removed from the pool (since its space is unknown) and the error is
+/// returned as [`PoolError::Backend`]. The connection is switched back to the configured space when it is reused.
+pub async fn get_with_space<M, S>(
+    pool: &managed::Pool<M>,
+    space: &str,
+) -> Result<managed::Object<M>, PoolError<Error>>
+where
+    M: managed::Manager<Error = Error>,
+    M::Type: DerefMut<Target = TcpConnection<S>>,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut con = pool.get().await?;
+    if con.current_space() != Some(space) {
+        if let Err(e) = con.use_space(space).await {
+            drop(managed::Object::take(con));
+            return Err(PoolError::Backend(e));
+        }
+    }
+    Ok(con)
+}
+
+/// Check out a connection from `pool`, run `q` on it and return it to the pool. If the connection breaks while running
+/// the query (for example, because of an I/O error), it is removed from the pool right away.
+pub async fn query<M, S>(pool: &managed::Pool<M>, q: &Query) -> Result<Response, PoolError<Error>>
+where
+    M: managed::Manager<Error = Error>,
+    M::Type: DerefMut<Target = TcpConnection<S>>,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut con = pool.get().await?;
+    let r = con.query(q).await;
+    if con.is_broken() {
+        drop(managed::Object::take(con));
+    }
+    r.map_err(PoolError::Backend)
+}
+
+/// Same as [`query`], but parses the response into `T`. The connection is returned to the pool even if the response
+/// can't be parsed (since it can still be used).
+pub async fn query_parse<M, S, T>(pool: &managed::Pool<M>, q: &Query) -> Result<T, PoolError<Error>>
+where
+    M: managed::Manager<Error = Error>,
+    M::Type: DerefMut<Target = TcpConnection<S>>,
+    S: AsyncRead + AsyncWrite + Unpin,
+    T: FromResponse,
+{
+    query(pool, q)
+        .await
+        .and_then(|resp| resp.parse().map_err(PoolError::Backend))
+}
+
+#[async_trait::async_trait]
+/// Waiters are served in FIFO order, so a steady stream of new checkouts can't starve a task that has been waiting for
+/// longer.
+impl<M: managed::Manager> AcquireAsync for managed::Pool<M> {
+    type Connection = managed::Object<M>;
+    type Error = PoolError<M::Error>;
+    async fn acquire(
+        &self,
+        timeout: Duration,
+    ) -> Result<Self::Connection, AcquireError<Self::Error>> {
+        let start = Instant::now();
+        match tokio::time::timeout(timeout, self.get()).await {
+            Ok(con) => con.map_err(AcquireError::Pool),
+            Err(_) => {
+                let status = self.status();
+                Err(AcquireError::Timeout(PoolTimeout {
+                    waited: start.elapsed(),
+                    max_size: Some(status.max_size),
+                    size: status.size,
+                    in_use: status.size - status.available,
+                    waiting: Some(status.waiting),
+                }))
+            }
+        }
+    }
+}
+
+impl<M: managed::Manager> PoolSize for managed::Pool<M> {
+    fn size_and_idle(&self) -> (usize, usize) {
+        let status = self.status();
+        (status.size, status.available)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A deadpool connection manager for Skyhash/TCP connections
+pub struct Manager {
+    config: Config,
+}
+
+impl Manager {
+    /// Create a new deadpool connection manager for Skyhash/TCP connections
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+}
+
+impl managed::Manager for Manager {
+    type Type = ConnectionAsync;
+    type Error = Error;
+    async fn create(&self) -> Result<Self::Type, Self::Error> {
+        self.config.connect_async().await
+    }
+    async fn recycle(&self, conn: &mut Self::Type, _: &Metrics) -> RecycleResult<Self::Error> {
+        if conn.is_broken() {
+            return Err(RecycleError::message("the connection is broken"));
+        }
+        if conn.current_space() != self.config.space() {
+            // switch back to the configured space (it's not possible to leave a space otherwise)
+            match self.config.space() {
+                Some(space) => conn.use_space(space).await.map_err(RecycleError::Backend)?,
+                None => return Err(RecycleError::message("the connection switched spaces")),
+            }
+        }
+        conn.query_parse::<()>(&query!(QUERY_SYSCTL_STATUS))
+            .await
+            .map_err(RecycleError::Backend)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A deadpool connection manager for Skyhash/TLS connections
+pub struct ManagerTls {
+    config: Config,
+    tls: TlsTrust,
+}
+
+impl ManagerTls {
+    /// Create a new deadpool connection manager for Skyhash/TLS connections.
+    ///
+    /// The `pem_cert` argument must contain your TLS certificate in a PEM format.
+    /// **NOTE: The `pem_cert` argument does NOT accept a file path!**
+    pub fn new(config: Config, pem_cert: String) -> Self {
+        Self {
+            config,
+            tls: TlsTrust::Pem(pem_cert),
+        }
+    }
+    /// Create a new deadpool connection manager for Skyhash/TLS connections that trust the certificate authorities in
+    /// `tls`
+    pub fn with_tls_config(config: Config, tls: TlsConfig) -> Self {
+        Self {
+            config,
+            tls: TlsTrust::Config(tls),
+        }
+    }
+}
+
+impl managed::Manager for ManagerTls {
+    type Type = ConnectionTlsAsync;
+    type Error = Error;
+    async fn create(&self) -> Result<Self::Type, Self::Error> {
+        self.tls.connect_async(&self.config).await
+    }
+    async fn recycle(&self, conn: &mut Self::Type, _: &Metrics) -> RecycleResult<Self::Error> {
+        if conn.is_broken() {
+            return Err(RecycleError::message("the connection is broken"));
+        }
+        if conn.current_space() != self.config.space() {
+            // switch back to the configured space (it's not possible to leave a space otherwise)
+            match self.config.space() {
+                Some(space) => conn.use_space(space).await.map_err(RecycleError::Backend)?,
+                None => return Err(RecycleError::message("the connection switched spaces")),
+            }
+        }
+        conn.query_parse::<()>(&query!(QUERY_SYSCTL_STATUS))
+            .await
+            .map_err(RecycleError::Backend)
+    }
+}
+
+#[test]
+fn build_pool() {
+    let pool = get(8, Config::new_default("username", "password")).unwrap();
+    let status = pool.status();
+    assert_eq!(status.max_size, 8);
+    // connections are only established on checkout
+    assert_eq!(status.size, 0);
+}
+
+#[test]
+fn partial_warm_up() {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let mut held = vec![];
+        for (i, con) in listener.incoming().enumerate() {
+            let mut con = con.unwrap();
+            let mut hs = [0u8; 128];
+            let _ = con.read(&mut hs).unwrap();
+            // reject the third connection attempt
+            let resp = if i == 2 {
+                [b'H', 0, 1, 10]
+            } else {
+                [b'H', 0, 0, 0]
+            };
+            con.write_all(&resp).unwrap();
+            held.push(con);
+        }
+    });
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let pool = get(4, Config::new("127.0.0.1", port, "username", "password")).unwrap();
+        let e = warm_up(&pool, 8).await.unwrap_err();
+        assert_eq!(e.requested(), 4);
+        assert_eq!(e.established(), 3);
+        assert!(matches!(
+            e.errors(),
+            [PoolError::Backend(Error::ConnectionSetupErr(
+                crate::error::ConnectionSetupError::HandshakeError(10)
+            ))]
+        ));
+        // the established connections are kept
+        let status = pool.status();
+        assert_eq!(status.size, 3);
+        assert_eq!(status.available, 3);
+        // only the third attempt is rejected, so a new pool with an idle connection starts out warm
+        let cfg = Config::new("127.0.0.1", port, "username", "password");
+        let (pool, e) = get_with_min_idle(4, 1, cfg).await.unwrap();
+        assert!(e.is_none());
+        assert_eq!(pool.status().available, 1);
+    })
+}
+
+#[test]
+fn warm_up_is_concurrent() {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        // only complete the handshakes once all three connections were opened, which a warm up that establishes one
+        // connection at a time would never do
+        let mut held: Vec<_> = listener.incoming().take(3).map(Result::unwrap).collect();
+        for con in &mut held {
+            let _ = con.read(&mut [0u8; 128]).unwrap();
+            con.write_all(&[b'H', 0, 0, 0]).unwrap();
+        }
+        // until the pool is dropped
+        for con in &mut held {
+            while con.read(&mut [0u8; 128]).is_ok_and(|n| n != 0) {}
+        }
+    });
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let pool = get(3, Config::new("127.0.0.1", port, "username", "password")).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), warm_up(&pool, 3))
+            .await
+            .expect("the connections weren't established concurrently")
+            .unwrap();
+        let status = pool.status();
+        assert_eq!((status.size, status.available), (3, 3));
+    })
+}
+
+#[test]
+fn space_is_restored_on_recycle() {
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let cfg =
+            Config::new("127.0.0.1", super::mock_server(b"\x12"), "user", "pass").with_space("app");
+        let pool = get(1, cfg).unwrap();
+        {
+            let mut con = pool.get().await.unwrap();
+            assert_eq!(con.current_space(), Some("app"));
+            con.use_space("sessions").await.unwrap();
+        }
+        // the same connection is switched back to the configured space
+        let con = pool.get().await.unwrap();
+        assert_eq!(con.current_space(), Some("app"));
+        assert_eq!(pool.status().size, 1);
+        drop(con);
+        // without a configured space, a connection that switched spaces can't be reused
+        let pool = get(
+            1,
+            Config::new("127.0.0.1", super::mock_server(b"\x12"), "user", "pass"),
+        )
+        .unwrap();
+        pool.get()
+            .await
+            .unwrap()
+            .use_space("sessions")
+            .await
+            .unwrap();
+        assert_eq!(pool.get().await.unwrap().current_space(), None);
+    })
+}
+
+#[test]
+fn unflushed_connection_is_not_recycled() {
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let pool = get(
+            1,
+            Config::new("127.0.0.1", super::mock_server(b"\x12"), "user", "pass"),
+        )
+        .unwrap();
+        {
+            let mut con = pool.get().await.unwrap();
+            con.feed_query(&query!("sysctl report status"));
+        }
+        // the connection was replaced
+        let con = pool.get().await.unwrap();
+        assert!(!con.is_broken());
+        assert_eq!(con.pending_write_bytes(), 0);
+        assert_eq!(managed::Object::metrics(&con).recycle_count, 0);
+        drop(con);
+        // while a clean connection is recycled
+        let con = pool.get().await.unwrap();
+        assert_eq!(managed::Object::metrics(&con).recycle_count, 1);
+    })
+}
+
+#[test]
+fn acquire_timeout_and_fifo_grants() {
+    use std::sync::{Arc, Mutex};
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let pool = get(
+            1,
+            Config::new("127.0.0.1", super::mock_server(b"\x12"), "user", "pass"),
+        )
+        .unwrap();
+        let held = pool.acquire(Duration::from_secs(5)).await.unwrap();
+        // the pool is saturated
+        let timeout = Duration::from_millis(50);
+        let e = match pool.acquire(timeout).await {
+            Err(AcquireError::Timeout(e)) => e,
+            other => panic!("expected a timeout, got {:?}", other.map(|_| ())),
+        };
+        assert!(e.waited() >= timeout);
+        assert_eq!((e.max_size(), e.size(), e.in_use()), (Some(1), 1, 1));
+        assert_eq!(e.waiting(), Some(0));
+        // queue up waiters one after the other and check that they're served in the same order
+        let grants = Arc::new(Mutex::new(vec![]));
+        let mut waiters = vec![];
+        for i in 0..3 {
+            let (waiter_pool, grants) = (pool.clone(), grants.clone());
+            waiters.push(tokio::spawn(async move {
+                let _con = waiter_pool.acquire(Duration::from_secs(5)).await.unwrap();
+                grants.lock().unwrap().push(i);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }));
+            while pool.status().waiting != i + 1 {
+                tokio::task::yield_now().await;
+            }
+        }
+        drop(held);
+        for waiter in waiters {
+            waiter.await.unwrap();
+        }
+        assert_eq!(*grants.lock().unwrap(), [0, 1, 2]);
+    })
+}
+
+#[test]
+fn get_with_space_switches_only_when_needed() {
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let (port, queries) = super::counting_mock_server(b"\x12");
+        let sent = || queries.load(std::sync::atomic::Ordering::SeqCst);
+        let pool = get(
+            1,
+            Config::new("127.0.0.1", port, "user", "pass").with_space("app"),
+        )
+        .unwrap();
+        // a new connection is switched to the configured space when it's established
+        let con = get_with_space(&pool, "app").await.unwrap();
+        assert_eq!(con.current_space(), Some("app"));
+        assert_eq!(sent(), 1);
+        drop(con);
+        // cache hit: only the recycle validation query is sent
+        let con = get_with_space(&pool, "app").await.unwrap();
+        assert_eq!(sent(), 2);
+        drop(con);
+        // cache miss: the validation query and the switch
+        let con = get_with_space(&pool, "sessions").await.unwrap();
+        assert_eq!(con.current_space(), Some("sessions"));
+        assert_eq!(sent(), 4);
+        drop(con);
+        // the connection was switched back before it was reused
+        let con = pool.get().await.unwrap();
+        assert_eq!(con.current_space(), Some("app"));
+        assert_eq!(pool.status().size, 1);
+    })
+}
+
+#[test]
+fn failed_space_switch_discards_connection() {
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let pool = get(
+            1,
+            Config::new(
+                "127.0.0.1",
+                super::mock_server(b"\x10\x05\x00"),
+                "user",
+                "pass",
+            ),
+        )
+        .unwrap();
+        assert!(matches!(
+            get_with_space(&pool, "sessions").await,
+            Err(PoolError::Backend(Error::ServerError(5)))
+        ));
+        assert_eq!(pool.status().size, 0);
+    })
+}
+
+#[test]
+fn one_off_queries() {
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let cfg = |response| Config::new("127.0.0.1", super::mock_server(response), "user", "pass");
+        let q = query!(
+            "select followers from myspace.mymodel where username = ?",
+            "sayan"
+        );
+        // the connection is returned to the pool after a parse error, and reused
+        let pool = get(1, cfg(b"\x12")).unwrap();
+        assert_eq!(query(&pool, &q).await.unwrap(), Response::Empty);
+        assert!(matches!(
+            query_parse::<_, _, u64>(&pool, &q).await,
+            Err(PoolError::Backend(Error::ParseError(_)))
+        ));
+        query_parse::<_, _, ()>(&pool, &q).await.unwrap();
+        let status = pool.status();
+        assert_eq!((status.size, status.available), (1, 1));
+        // but removed after a protocol error
+        let pool = get(1, cfg(b"\x05100\n\x12")).unwrap();
+        assert!(matches!(
+            query_parse::<_, _, u64>(&pool, &q).await,
+            Err(PoolError::Backend(Error::ProtocolError(..)))
+        ));
+        assert_eq!(pool.status().size, 0);
+    })
+}
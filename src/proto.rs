@@ -0,0 +1,253 @@
+/*
+ * Copyright 2024, Sayan Nandan <nandansayan@outlook.com>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+*/
+
+//! # Skyhash encoding and decoding (without I/O)
+//!
+//! This module exposes the Skyhash encoder and decoder that the connections are built on, so that you can do the I/O
+//! yourself. It requires the `alloc-proto` feature, and only needs `core` and `alloc`: build with
+//! `default-features = false, features = ["alloc-proto"]` for `no_std` targets (for example, a WASM plugin without
+//! networking).
+//!
+//! **Note**: This module doesn't do the connection handshake, so the connection must be set up elsewhere.
+//!
+//! ## Example
+//!
+//! ```
+//! use skytable::{
+//!     proto::{self, ResponseDecoder},
+//!     query,
+//!     response::{Response, Value},
+//! };
+//!
+//! let mut packet = vec![];
+//! proto::encode_query(
+//!     &query!("select followers from myspace.mymodel where username = ?", "sayan"),
+//!     &mut packet,
+//! );
+//! // send `packet` to the server and feed whatever is received into the decoder
+//! let mut decoder = ResponseDecoder::new();
+//! assert_eq!(decoder.feed(b"\x05").unwrap(), None);
+//! assert_eq!(
+//!     decoder.feed(b"100\n").unwrap(),
+//!     Some(Response::Value(Value::UInt64(100)))
+//! );
+//! ```
+
+pub use crate::protocol::{ProtocolError, ProtocolResult};
+use {
+    crate::{
+        error::{CapturedResponse, DEFAULT_RESPONSE_CAPTURE_LEN},
+        protocol::{check_trailing, DecodeState, Decoder, MRespState, PipelineResult, RState},
+        query::{Pipeline, Query},
+        response::Response,
+    },
+    alloc::vec::Vec,
+};
+
+/// Append the Skyhash packet for `query` to `buf`
+pub fn encode_query(query: &Query, buf: &mut Vec<u8>) {
+    query.write_packet(buf)
+}
+
+/// Append the Skyhash packet for `pipeline` to `buf`
+pub fn encode_pipeline(pipeline: &Pipeline, buf: &mut Vec<u8>) {
+    buf.push(b'P');
+    buf.extend_from_slice(itoa::Buffer::new().format(pipeline.buf().len()).as_bytes());
+    buf.push(b'\n');
+    buf.extend_from_slice(pipeline.buf());
+}
+
+#[derive(Debug)]
+/// An incremental decoder for the response to a single query
+///
+/// Feed it the bytes as they are received. Once a response is returned (or an error occurs), the decoder is reset and
+/// can be used for the response to the next query.
+pub struct ResponseDecoder {
+    buf: Vec<u8>,
+    cursor: usize,
+    state: RState,
+    response_capture_len: usize,
+    last_invalid_response: Option<CapturedResponse>,
+}
+
+impl Default for ResponseDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResponseDecoder {
+    /// Create a new decoder
+    pub fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            cursor: 0,
+            state: RState::default(),
+            response_capture_len: DEFAULT_RESPONSE_CAPTURE_LEN,
+            last_invalid_response: None,
+        }
+    }
+    /// Decode the bytes received so far along with `data`. Returns the response if it's complete, and `None` if more
+    /// data is needed
+    pub fn feed(&mut self, data: &[u8]) -> ProtocolResult<Option<Response>> {
+        self.buf.extend_from_slice(data);
+        let state = core::mem::take(&mut self.state);
+        let (state, position) = Decoder::new(&self.buf, self.cursor).validate_response(state);
+        match state {
+            DecodeState::ChangeState(state) => {
+                self.state = state;
+                self.cursor = position;
+                Ok(None)
+            }
+            DecodeState::Completed(resp) => {
+                let r = check_trailing(&self.buf, position).map(|()| Some(resp));
+                self.reset();
+                r
+            }
+            DecodeState::Error(e) => {
+                self.last_invalid_response =
+                    CapturedResponse::capture(&e, &self.buf, self.response_capture_len);
+                self.reset();
+                Err(e)
+            }
+        }
+    }
+    /// Returns the start of the last response that couldn't be decoded
+    pub fn last_invalid_response(&self) -> Option<&CapturedResponse> {
+        self.last_invalid_response.as_ref()
+    }
+    /// Set the number of bytes that are kept from the start of a response that couldn't be decoded (see
+    /// [`last_invalid_response`](Self::last_invalid_response)). Defaults to [`DEFAULT_RESPONSE_CAPTURE_LEN`], and
+    /// `0` disables the capture
+    pub fn set_response_capture_len(&mut self, len: usize) {
+        self.response_capture_len = len;
+    }
+    fn reset(&mut self) {
+        self.buf.clear();
+        self.cursor = 0;
+    }
+}
+
+#[derive(Debug)]
+/// An incremental decoder for the responses to a pipeline
+///
+/// Feed it the bytes as they are received. Once the responses are returned (or an error occurs), the decoder is reset
+/// and can be used for the responses to another pipeline with the same number of queries.
+pub struct PipelineDecoder {
+    queries: usize,
+    buf: Vec<u8>,
+    cursor: usize,
+    state: MRespState,
+    response_capture_len: usize,
+    last_invalid_response: Option<CapturedResponse>,
+}
+
+impl PipelineDecoder {
+    /// Create a new decoder for a pipeline of `queries` queries (see [`Pipeline::query_count`])
+    pub fn new(queries: usize) -> Self {
+        Self {
+            queries,
+            buf: Vec::new(),
+            cursor: 0,
+            state: MRespState::default(),
+            response_capture_len: DEFAULT_RESPONSE_CAPTURE_LEN,
+            last_invalid_response: None,
+        }
+    }
+    /// Decode the bytes received so far along with `data`. Returns the responses (in the order of the queries) if
+    /// they're complete, and `None` if more data is needed
+    pub fn feed(&mut self, data: &[u8]) -> ProtocolResult<Option<Vec<Response>>> {
+        self.buf.extend_from_slice(data);
+        let state = core::mem::take(&mut self.state);
+        let (state, position) =
+            Decoder::new(&self.buf, self.cursor).validate_pipe(self.queries, state);
+        match state {
+            PipelineResult::Pending(state) => {
+                self.state = state;
+                self.cursor = position;
+                Ok(None)
+            }
+            PipelineResult::Completed(responses) => {
+                let r = check_trailing(&self.buf, position).map(|()| Some(responses));
+                self.reset();
+                r
+            }
+            PipelineResult::Error(e, _) => {
+                self.last_invalid_response =
+                    CapturedResponse::capture(&e, &self.buf, self.response_capture_len);
+                self.reset();
+                Err(e)
+            }
+        }
+    }
+    /// Returns the start of the last response that couldn't be decoded
+    pub fn last_invalid_response(&self) -> Option<&CapturedResponse> {
+        self.last_invalid_response.as_ref()
+    }
+    /// Set the number of bytes that are kept from the start of a response that couldn't be decoded (see
+    /// [`last_invalid_response`](Self::last_invalid_response)). Defaults to [`DEFAULT_RESPONSE_CAPTURE_LEN`], and
+    /// `0` disables the capture
+    pub fn set_response_capture_len(&mut self, len: usize) {
+        self.response_capture_len = len;
+    }
+    fn reset(&mut self) {
+        self.buf.clear();
+        self.cursor = 0;
+    }
+}
+
+#[test]
+fn response_decoder() {
+    use {crate::response::Value, alloc::vec};
+    let mut decoder = ResponseDecoder::new();
+    // one byte at a time
+    let resp = b"\x0E2\n\x0D5\nsa\nyn\x0220\n";
+    for b in &resp[..resp.len() - 1] {
+        assert_eq!(decoder.feed(&[*b]).unwrap(), None);
+    }
+    assert_eq!(
+        decoder.feed(&resp[resp.len() - 1..]).unwrap(),
+        Some(Response::Value(Value::List(vec![
+            Value::String("sa\nyn".into()),
+            Value::UInt8(20)
+        ])))
+    );
+    // the decoder is reset for the next response
+    assert_eq!(decoder.feed(b"\x12").unwrap(), Some(Response::Empty));
+    assert_eq!(
+        decoder.feed(b"\x12\x12").unwrap_err(),
+        ProtocolError::TrailingData { bytes: 1 }
+    );
+    assert_eq!(
+        decoder.feed(b"\x10\x05\x00").unwrap(),
+        Some(Response::Error(5))
+    );
+}
+
+#[test]
+fn pipeline_decoder() {
+    use alloc::vec;
+    let pipeline = pipe!(query!("use $current"), query!("use nonexistent"));
+    let mut packet = vec![];
+    encode_pipeline(&pipeline, &mut packet);
+    assert_eq!(packet, b"P37\n12\n0\nuse $current15\n0\nuse nonexistent");
+    let mut decoder = PipelineDecoder::new(pipeline.query_count());
+    assert_eq!(decoder.feed(b"\x12\x10").unwrap(), None);
+    assert_eq!(
+        decoder.feed(b"\x05\x00").unwrap(),
+        Some(vec![Response::Empty, Response::Error(5)])
+    );
+}
@@ -0,0 +1,476 @@
+/*
+ * Copyright 2024, Sayan Nandan <nandansayan@outlook.com>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+*/
+
+//! Decoding responses into a [`ResponseBuf`] instead of a [`Response`](crate::response::Response).
+//!
+//! This accepts (and rejects) exactly what [`Decoder`](super::Decoder) does, with the same errors. The difference is
+//! that values are appended to the buffer's vectors instead of being allocated one by one, and that the decoder is
+//! resumed by retrying the value that was cut short (every value is decoded in one go, except for lists and rows,
+//! whose elements are decoded one at a time). The decoder's state is kept in the buffer so that it is reused as well.
+
+use {
+    super::{invalid_number, scan, LfsObject, ProtocolError, ProtocolResult},
+    crate::response::buf::{Kind, ResponseBuf, Slot},
+    alloc::vec::Vec,
+    core::str::FromStr,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum Phase {
+    /// waiting for the type of the response
+    #[default]
+    Start,
+    /// waiting for the error code
+    ErrorCode,
+    /// waiting for the number of columns of a row
+    RowColumns,
+    /// waiting for the number of rows of a multi-row response
+    RowCount,
+    /// waiting for the number of columns of a multi-row response
+    RowsColumns { rows: usize },
+    /// decoding the values of the response (along with the number of rows that weren't started yet)
+    Values { rows: usize },
+}
+
+#[derive(Debug, Clone, Copy)]
+/// A row or list whose elements are being decoded
+struct Frame {
+    /// the slot of the list (if this is a list)
+    list: Option<usize>,
+    len: usize,
+    remaining: usize,
+    /// false for the frame holding the value of a single-value response (whose errors aren't in an element)
+    indexed: bool,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct FlatState {
+    phase: Phase,
+    cursor: usize,
+    frames: Vec<Frame>,
+}
+
+impl FlatState {
+    pub(crate) fn clear(&mut self) {
+        self.phase = Phase::Start;
+        self.cursor = 0;
+        self.frames.clear();
+    }
+}
+
+enum Element {
+    Slot(Slot),
+    Bytes {
+        string: bool,
+        start: usize,
+        end: usize,
+    },
+    List(usize),
+}
+
+impl ResponseBuf {
+    /// Returns a description of the structure that is being decoded (used for error reporting)
+    pub(crate) fn parsing(&self) -> &'static str {
+        match (self.state.phase, self.kind) {
+            (Phase::Start, _) => "response",
+            (Phase::ErrorCode, _) => "error code",
+            (Phase::RowColumns, _) | (Phase::Values { .. }, Kind::Row { .. }) => "row",
+            (Phase::Values { .. }, Kind::Value) => "value",
+            _ => "multi-row response",
+        }
+    }
+    /// Decode the response at the start of `b`, resuming where the previous call stopped (the buffer must be cleared
+    /// before the first call). Returns the end of the response once it is complete. If an error is returned, the
+    /// buffer is cleared.
+    pub(crate) fn decode(&mut self, b: &[u8]) -> ProtocolResult<Option<usize>> {
+        let r = self._decode(b);
+        if r.is_err() {
+            self.clear();
+        }
+        r
+    }
+    fn _decode(&mut self, b: &[u8]) -> ProtocolResult<Option<usize>> {
+        let Self {
+            kind,
+            slots,
+            data,
+            rows,
+            state,
+        } = self;
+        loop {
+            let i = state.cursor;
+            match state.phase {
+                Phase::Start => {
+                    let code = match b.get(i) {
+                        Some(&code) => code,
+                        None => return Ok(None),
+                    };
+                    state.cursor += 1;
+                    state.phase = match code {
+                        // TODO(@ohsayan): this is reserved!
+                        0x0F => return Err(ProtocolError::InvalidServerResponseUnknownDataType),
+                        0x10 => Phase::ErrorCode,
+                        0x11 => Phase::RowColumns,
+                        0x12 => {
+                            *kind = Kind::Empty;
+                            return Ok(Some(state.cursor));
+                        }
+                        0x13 => Phase::RowCount,
+                        _ => {
+                            // the type is decoded along with the value
+                            state.cursor = i;
+                            *kind = Kind::Value;
+                            state.frames.push(Frame {
+                                list: None,
+                                len: 1,
+                                remaining: 1,
+                                indexed: false,
+                            });
+                            Phase::Values { rows: 0 }
+                        }
+                    };
+                }
+                Phase::ErrorCode => {
+                    if b.len() - i < 2 {
+                        return Ok(None);
+                    }
+                    *kind = Kind::Error(u16::from_le_bytes([b[i], b[i + 1]]));
+                    return Ok(Some(i + 2));
+                }
+                Phase::RowColumns => {
+                    let (columns, next) = match unsigned::<usize>(b, i)? {
+                        Some(columns) => columns,
+                        None => return Ok(None),
+                    };
+                    state.cursor = next;
+                    *kind = Kind::Row { columns };
+                    state.frames.push(Frame::row(columns));
+                    state.phase = Phase::Values { rows: 0 };
+                }
+                Phase::RowCount => {
+                    let (rows, next) = match unsigned::<usize>(b, i)? {
+                        Some(rows) => rows,
+                        None => return Ok(None),
+                    };
+                    state.cursor = next;
+                    state.phase = Phase::RowsColumns { rows };
+                }
+                Phase::RowsColumns { rows } => {
+                    let (columns, next) = match unsigned::<usize>(b, i)? {
+                        Some(columns) => columns,
+                        None => return Ok(None),
+                    };
+                    state.cursor = next;
+                    *kind = Kind::Rows { columns };
+                    state.phase = Phase::Values { rows };
+                }
+                Phase::Values {
+                    rows: remaining_rows,
+                } => {
+                    let frame = match state.frames.last_mut() {
+                        Some(frame) => frame,
+                        None => match *kind {
+                            Kind::Rows { columns } if remaining_rows != 0 => {
+                                state.phase = Phase::Values {
+                                    rows: remaining_rows - 1,
+                                };
+                                rows.push(slots.len());
+                                state.frames.push(Frame::row(columns));
+                                continue;
+                            }
+                            _ => return Ok(Some(i)),
+                        },
+                    };
+                    if frame.remaining == 0 {
+                        if let Some(list) = frame.list {
+                            let next = slots.len();
+                            if let Slot::List { ref mut end, .. } = slots[list] {
+                                *end = next;
+                            }
+                        }
+                        state.frames.pop();
+                        continue;
+                    }
+                    if i == b.len() {
+                        return Ok(None);
+                    }
+                    let index = frame.len - frame.remaining;
+                    let (element, next) = match element(b, i) {
+                        Ok(Some(element)) => element,
+                        Ok(None) => return Ok(None),
+                        Err(e) if frame.indexed => return Err(e.in_element(index)),
+                        Err(e) => return Err(e),
+                    };
+                    frame.remaining -= 1;
+                    state.cursor = next;
+                    match element {
+                        Element::Slot(slot) => slots.push(slot),
+                        Element::Bytes { string, start, end } => {
+                            let offset = data.len();
+                            data.extend_from_slice(&b[start..end]);
+                            let (start, end) = (offset, offset + end - start);
+                            slots.push(match string {
+                                true => Slot::String(start, end),
+                                false => Slot::Binary(start, end),
+                            });
+                        }
+                        Element::List(len) => {
+                            state.frames.push(Frame {
+                                list: Some(slots.len()),
+                                len,
+                                remaining: len,
+                                indexed: true,
+                            });
+                            slots.push(Slot::List { len, end: 0 });
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Frame {
+    fn row(columns: usize) -> Self {
+        Self {
+            list: None,
+            len: columns,
+            remaining: columns,
+            indexed: true,
+        }
+    }
+}
+
+/// Decode the value that begins at `i` (with its type), or return `None` if it isn't complete. A list is returned
+/// once its size is complete (and its elements are decoded after it)
+fn element(b: &[u8], i: usize) -> ProtocolResult<Option<(Element, usize)>> {
+    macro_rules! number {
+        ($decode:ident, $variant:ident) => {
+            $decode(b, i + 1)?.map(|(v, next)| (Element::Slot(Slot::$variant(v)), next))
+        };
+    }
+    let element = match b[i] {
+        0x00 => Some((Element::Slot(Slot::Null), i + 1)),
+        0x01 => match b.get(i + 1) {
+            Some(&v) if v > 1 => return Err(ProtocolError::InvalidServerResponseForData),
+            Some(&v) => Some((Element::Slot(Slot::Bool(v == 1)), i + 2)),
+            None => None,
+        },
+        0x02 => number!(unsigned, UInt8),
+        0x03 => number!(unsigned, UInt16),
+        0x04 => number!(unsigned, UInt32),
+        0x05 => number!(unsigned, UInt64),
+        0x06 => number!(parsed, SInt8),
+        0x07 => number!(parsed, SInt16),
+        0x08 => number!(parsed, SInt32),
+        0x09 => number!(parsed, SInt64),
+        0x0A => number!(parsed, Float32),
+        0x0B => number!(parsed, Float64),
+        code @ (0x0C | 0x0D) => match unsigned::<usize>(b, i + 1)? {
+            Some((size, start)) if b.len() - start >= size => {
+                let end = start + size;
+                let string = code == 0x0D;
+                if string && core::str::from_utf8(&b[start..end]).is_err() {
+                    return Err(ProtocolError::InvalidServerResponseForData);
+                }
+                Some((Element::Bytes { string, start, end }, end))
+            }
+            _ => None,
+        },
+        0x0E => unsigned::<usize>(b, i + 1)?.map(|(len, next)| (Element::List(len), next)),
+        _ => return Err(ProtocolError::InvalidServerResponseUnknownDataType),
+    };
+    Ok(element)
+}
+
+/// Decode an unsigned number (or size) terminated by a LF that begins at `start`, along with the position after the LF
+fn unsigned<T: LfsObject<State = ()> + Default>(
+    b: &[u8],
+    start: usize,
+) -> ProtocolResult<Option<(T, usize)>> {
+    let rest = &b[start..];
+    let (digits, complete) = match scan::find_lf(rest) {
+        Some(len) => (&rest[..len], true),
+        None => (rest, false),
+    };
+    if !digits.is_empty() && scan::all_digits(digits) {
+        let mut v = T::default();
+        // the number can still overflow
+        if digits.iter().all(|&byte| v.update(&mut (), byte)) {
+            return Ok(if complete {
+                Some((v, start + digits.len() + 1))
+            } else {
+                None
+            });
+        }
+    }
+    unsigned_scalar(b, start)
+}
+
+/// Same as [`unsigned`], one byte at a time. Used to find the byte that makes the number invalid
+fn unsigned_scalar<T: LfsObject<State = ()> + Default>(
+    b: &[u8],
+    start: usize,
+) -> ProtocolResult<Option<(T, usize)>> {
+    let mut v = T::default();
+    for (i, &byte) in b.iter().enumerate().skip(start) {
+        if byte == b'\n' {
+            if i == start {
+                return Err(invalid_number(&[]));
+            }
+            return Ok(Some((v, i + 1)));
+        }
+        if !v.update(&mut (), byte) {
+            return Err(invalid_number(&b[start..=i]));
+        }
+    }
+    Ok(None)
+}
+
+/// Decode a signed number or float terminated by a LF that begins at `start`, along with the position after the LF
+fn parsed<T: FromStr>(b: &[u8], start: usize) -> ProtocolResult<Option<(T, usize)>> {
+    let end = match scan::find_lf(&b[start..]) {
+        Some(len) => start + len,
+        None => return Ok(None),
+    };
+    let block = &b[start..end];
+    if block.is_empty() {
+        return Err(invalid_number(block));
+    }
+    match core::str::from_utf8(block).map(str::parse) {
+        Ok(Ok(v)) => Ok(Some((v, end + 1))),
+        _ => Err(invalid_number(block)),
+    }
+}
+
+#[test]
+fn same_as_decoder() {
+    use super::{DecodeState, Decoder, RState};
+    // complete, incomplete and invalid responses: the buffer must stop (or fail) where the decoder does
+    for packet in [
+        &b"\x0E3\n\x021\n\x0E1\n\x0D2\nab\x00"[..],
+        b"\x113\n\x0C0\n\x0E0\n\x01\x01",
+        b"\x132\n2\n\x0E1\n\x00\x0D1\na\x0E2\n\x021\n\x0E0\n\x0C1\n\xff",
+        b"\x130\n3\n",
+        b"\x133\n0\n",
+        b"\x0E",
+        b"\x0E2\n\x0E1\n",
+        b"\x112\n\x0D3\nab",
+        b"\x133\n1\n\x051\n\x052\n",
+        b"\x10\x05",
+        b"\x134294967295\n4294967295\n\x00",
+        b"\x0F",
+        b"\x14",
+        b"\x112\n\x00\x12",
+        b"\x01\x02",
+        b"\x0D2\n\xff\xfe",
+        b"\x05\n",
+        b"\x0518446744073709551616\n",
+        b"\x051 2\n",
+        b"\x09-1x\n",
+        b"\x0A1.5.1\n",
+        b"\x112\n\x051\n\x05x\n",
+        b"\x0E2\n\x051\n\x0E1\n\x07x\n",
+        b"\x11\n",
+        b"\x131\n\n\x051\n",
+        b"\x132\n3x\n",
+        b"\x113\n\x00\x0E\n",
+    ] {
+        let mut buf = ResponseBuf::new();
+        let (expected, position) = Decoder::new(packet, 0).validate_response(RState::default());
+        match (expected, buf.decode(packet)) {
+            (DecodeState::Completed(resp), Ok(Some(end))) => {
+                assert_eq!((buf.to_response(), end), (resp, position), "{:?}", packet)
+            }
+            (DecodeState::ChangeState(state), Ok(None)) => {
+                assert_eq!(buf.parsing(), state.parsing(), "{:?}", packet)
+            }
+            (DecodeState::Error(e), Err(error)) => {
+                assert_eq!(error, e, "{:?}", packet);
+                assert_eq!(buf.to_response(), crate::response::Response::Empty);
+            }
+            (expected, r) => panic!("{:?}: expected {:?}, got {:?}", packet, expected, r),
+        }
+    }
+}
+
+#[test]
+fn reused_buffer() {
+    use crate::response::{Response, Row, Value};
+    let mut buf = ResponseBuf::new();
+    let rows = b"\x132\n2\n\x0D5\nsayan\x0E2\n\x0C2\n\x00\x01\x0E0\n\x0D5\nelana\x00";
+    assert_eq!(buf.decode(rows), Ok(Some(rows.len())));
+    let capacity = (buf.slots.capacity(), buf.data.capacity());
+    // a smaller response only holds its own values
+    buf.clear();
+    assert_eq!(buf.decode(b"\x111\n\x0D2\nhi"), Ok(Some(8)));
+    assert_eq!(
+        buf.to_response(),
+        Response::Row(Row::new(vec![Value::String("hi".into())]))
+    );
+    assert_eq!((buf.slots.capacity(), buf.data.capacity()), capacity);
+    // and a response that failed to decode doesn't hold any
+    buf.clear();
+    assert!(buf.decode(b"\x112\n\x0D2\nhi\x05x\n").is_err());
+    assert_eq!(buf.to_response(), Response::Empty);
+    assert!(buf.slots.is_empty() && buf.data.is_empty());
+}
+
+#[test]
+fn unsigned_same_as_scalar() {
+    fn check<T: LfsObject<State = ()> + Default + PartialEq + core::fmt::Debug>(b: &[u8]) {
+        for start in 0..=b.len() {
+            assert_eq!(
+                unsigned::<T>(b, start),
+                unsigned_scalar::<T>(b, start),
+                "{:?} from {}",
+                b,
+                start
+            );
+        }
+    }
+    let mut lines = vec![];
+    // sizes around the limits of every width, complete and not
+    for n in [0, 9, 10, 255, 256, 65535, 65536, u32::MAX as u64, u64::MAX] {
+        lines.push(format!("{n}\n"));
+        lines.push(format!("{n}"));
+        lines.push(format!("{n}0\n"));
+        lines.push(format!("{n}x\n"));
+    }
+    // and a pseudo-random mix of digits, LFs and other bytes
+    let mut x = 0x2545_f491_4f6c_dd1du64;
+    for _ in 0..1000 {
+        let len = (x % 24) as usize;
+        let mut line = String::new();
+        for _ in 0..len {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            line.push(match x % 16 {
+                0 => '\n',
+                1 => '/',
+                2 => ':',
+                _ => char::from(b'0' + (x % 10) as u8),
+            });
+        }
+        lines.push(line);
+    }
+    for line in &lines {
+        check::<u8>(line.as_bytes());
+        check::<u64>(line.as_bytes());
+        check::<usize>(line.as_bytes());
+    }
+}
@@ -14,6 +14,7 @@
  * limitations under the License.
 */
 
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
 use crate::{
     error::{ConnectionSetupError, Error},
     ClientResult, Config,
@@ -28,6 +29,13 @@ pub(crate) enum ProtocolVersion {
 }
 
 impl ProtocolVersion {
+    #[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+    pub(crate) const fn name(&self) -> &'static str {
+        match self {
+            Self::V2_0 => "Skyhash/2.0",
+        }
+    }
+    #[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
     pub(crate) const fn hs_block(&self) -> [u8; 6] {
         match self {
             Self::V2_0 => [b'H', 0, 0, 0, 0, 0],
@@ -35,30 +43,47 @@ impl ProtocolVersion {
     }
 }
 
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
 pub struct ClientHandshake(Box<[u8]>);
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
 impl ClientHandshake {
     pub(crate) fn new(cfg: &Config) -> Self {
         Self::_new(cfg.protocol.hs_block(), cfg)
     }
     fn _new(hs: [u8; 6], cfg: &Config) -> Self {
-        let mut v = Vec::with_capacity(6 + cfg.username().len() + cfg.password().len() + 5);
-        v.extend(hs);
-        pushlen!(v, cfg.username().len());
-        pushlen!(v, cfg.password().len());
-        v.extend(cfg.username().as_bytes());
-        v.extend(cfg.password().as_bytes());
-        Self(v.into_boxed_slice())
+        cfg.with_current_credentials(|username, password| {
+            let mut v = Vec::with_capacity(6 + username.len() + password.len() + 5);
+            v.extend(hs);
+            pushlen!(v, username.len());
+            pushlen!(v, password.len());
+            v.extend(username.as_bytes());
+            v.extend(password.as_bytes());
+            Self(v.into_boxed_slice())
+        })
     }
     pub(crate) fn inner(&self) -> &[u8] {
         &self.0
     }
 }
 
+#[cfg(all(
+    feature = "zeroize",
+    any(feature = "sync", feature = "aio", feature = "async-std")
+))]
+impl Drop for ClientHandshake {
+    fn drop(&mut self) {
+        // the handshake contains the password
+        zeroize::Zeroize::zeroize(&mut self.0)
+    }
+}
+
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
 #[derive(Debug)]
 pub enum ServerHandshake {
     Okay(u8),
     Error(u8),
 }
+#[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
 impl ServerHandshake {
     pub fn parse(v: [u8; 4]) -> ClientResult<Self> {
         Ok(match v {
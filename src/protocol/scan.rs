@@ -0,0 +1,84 @@
+/*
+ * Copyright 2024, Sayan Nandan <nandansayan@outlook.com>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+*/
+
+//! Scanning for the LF that ends a size or number, and checking that the bytes before it are digits. With the `simd`
+//! feature, the LF is found with [`memchr`](https://docs.rs/memchr) (except under Miri, which runs the scalar loops).
+//! Digits are always checked eight at a time (only the flat decoder of the blocking API checks them).
+
+/// Returns the position of the first LF in `b`
+#[cfg(all(feature = "simd", not(miri)))]
+pub(super) fn find_lf(b: &[u8]) -> Option<usize> {
+    memchr::memchr(b'\n', b)
+}
+
+/// Returns the position of the first LF in `b`
+#[cfg(not(all(feature = "simd", not(miri))))]
+pub(super) fn find_lf(b: &[u8]) -> Option<usize> {
+    b.iter().position(|&byte| byte == b'\n')
+}
+
+/// Returns true if every byte in `b` is an ASCII digit
+#[cfg(feature = "sync")]
+pub(super) fn all_digits(b: &[u8]) -> bool {
+    let mut words = b.chunks_exact(8);
+    for word in &mut words {
+        let mut w = [0; 8];
+        w.copy_from_slice(word);
+        if !eight_digits(u64::from_le_bytes(w)) {
+            return false;
+        }
+    }
+    words.remainder().iter().all(u8::is_ascii_digit)
+}
+
+/// Returns true if all the bytes of `w` are ASCII digits: the high nibble of every byte must be 3, and must still be 3
+/// after adding 6 (which carries into it for every low nibble above 9)
+#[cfg(feature = "sync")]
+fn eight_digits(w: u64) -> bool {
+    const HIGH: u64 = 0xF0F0_F0F0_F0F0_F0F0;
+    const SIX: u64 = 0x0606_0606_0606_0606;
+    const THREE: u64 = 0x3030_3030_3030_3030;
+    (w & HIGH) == THREE && (w.wrapping_add(SIX) & HIGH) == THREE
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn digits() {
+    assert!(all_digits(b""));
+    assert!(all_digits(b"0123456789"));
+    assert!(all_digits(b"18446744073709551615"));
+    // every position of a word and of the remainder
+    for len in [7, 8, 9, 16, 17] {
+        for i in 0..len {
+            for byte in 0..=u8::MAX {
+                let mut b = vec![b'5'; len];
+                b[i] = byte;
+                assert_eq!(all_digits(&b), byte.is_ascii_digit(), "{:?}", b);
+            }
+        }
+    }
+}
+
+#[test]
+fn lf() {
+    for b in [&b""[..], b"\n", b"123", b"123\n", b"12\n3\n", b"\x00\xff\n"] {
+        assert_eq!(find_lf(b), b.iter().position(|&byte| byte == b'\n'));
+    }
+    let mut b = vec![b'1'; 100];
+    assert_eq!(find_lf(&b), None);
+    b[77] = b'\n';
+    assert_eq!(find_lf(&b), Some(77));
+}
@@ -0,0 +1,471 @@
+/*
+ * Copyright 2024, Sayan Nandan <nandansayan@outlook.com>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+*/
+
+//! Byte-exact Skyhash encoding test vectors
+//!
+//! These tables pin down the wire format: every query is checked against the bytes the encoder must produce and
+//! every response is checked against the value the decoder must produce (both in one go and when the bytes trickle
+//! in one at a time). When adding a new parameter or response type, add a vector for it here.
+//!
+//! The tables are also exported (hidden from the docs) as `skytable::test_vectors`, so that mock servers and other
+//! implementations can be checked against them. They aren't part of the stable API.
+
+#[cfg(test)]
+use super::{DecodeState, Decoder, MRespState, PipelineResult, RState};
+use {
+    crate::{
+        query::{Pipeline, QList, Query},
+        response::{Response, Row, Value},
+    },
+    alloc::{vec, vec::Vec},
+};
+
+/// `(description, query, encoded packet)`
+pub fn query_vectors() -> Vec<(&'static str, Query, &'static [u8])> {
+    vec![
+        (
+            "no parameters",
+            query!("sysctl report status"),
+            b"S23\n20\nsysctl report status",
+        ),
+        (
+            "null and bool",
+            query!("insert into m(?, ?)", None::<u8>, true),
+            b"S25\n19\ninsert into m(?, ?)\x00\x01\x01",
+        ),
+        (
+            "unsigned, signed and float",
+            query!(
+                "select * from m where a = ? and b = ? and c = ?",
+                100u64,
+                -5i32,
+                3.5f64
+            ),
+            b"S64\n47\nselect * from m where a = ? and b = ? and c = ?\x02100\n\x03-5\n\x043.5\n",
+        ),
+        (
+            "binary and string containing LF",
+            query!("insert into m(?, ?)", &b"\x00\n\xff"[..], "sa\ny"),
+            b"S35\n19\ninsert into m(?, ?)\x053\n\x00\n\xff\x064\nsa\ny",
+        ),
+        (
+            "list",
+            query!("insert into m(?)", QList::new(&["a", "bc"])),
+            b"S30\n16\ninsert into m(?)\x07\x061\na\x062\nbc]",
+        ),
+    ]
+}
+
+/// `(description, encoded response, decoded response)`
+pub fn response_vectors() -> Vec<(&'static str, &'static [u8], Response)> {
+    vec![
+        ("empty", b"\x12", Response::Empty),
+        ("error", b"\x10\x05\x00", Response::Error(5)),
+        ("null", b"\x00", Response::Value(Value::Null)),
+        ("bool true", b"\x01\x01", Response::Value(Value::Bool(true))),
+        (
+            "bool false",
+            b"\x01\x00",
+            Response::Value(Value::Bool(false)),
+        ),
+        ("u8", b"\x02255\n", Response::Value(Value::UInt8(u8::MAX))),
+        ("u16", b"\x031096\n", Response::Value(Value::UInt16(1096))),
+        (
+            "u32",
+            b"\x044294967295\n",
+            Response::Value(Value::UInt32(u32::MAX)),
+        ),
+        (
+            "u64",
+            b"\x0518446744073709551615\n",
+            Response::Value(Value::UInt64(u64::MAX)),
+        ),
+        ("i8", b"\x06-128\n", Response::Value(Value::SInt8(i8::MIN))),
+        ("i16", b"\x07-1032\n", Response::Value(Value::SInt16(-1032))),
+        ("i32", b"\x0842\n", Response::Value(Value::SInt32(42))),
+        (
+            "i64",
+            b"\x09-9223372036854775808\n",
+            Response::Value(Value::SInt64(i64::MIN)),
+        ),
+        ("f32", b"\x0A1.5\n", Response::Value(Value::Float32(1.5))),
+        (
+            "f64",
+            b"\x0B-0.25\n",
+            Response::Value(Value::Float64(-0.25)),
+        ),
+        (
+            "binary containing LF",
+            b"\x0C3\n\x00\n\xff",
+            Response::Value(Value::Binary(vec![0, b'\n', 0xff])),
+        ),
+        (
+            "string containing LF",
+            b"\x0D5\nsa\nyn",
+            Response::Value(Value::String("sa\nyn".into())),
+        ),
+        (
+            "empty list",
+            b"\x0E0\n",
+            Response::Value(Value::List(vec![])),
+        ),
+        (
+            "nested list",
+            b"\x0E2\n\x0D1\na\x0E1\n\x0220\n",
+            Response::Value(Value::List(vec![
+                Value::String("a".into()),
+                Value::List(vec![Value::UInt8(20)]),
+            ])),
+        ),
+        (
+            "row",
+            b"\x112\n\x0D5\nsayan\x0220\n",
+            Response::Row(Row::new(vec![
+                Value::String("sayan".into()),
+                Value::UInt8(20),
+            ])),
+        ),
+        (
+            "rows",
+            b"\x132\n1\n\x051\n\x052\n",
+            Response::Rows(vec![
+                Row::new(vec![Value::UInt64(1)]),
+                Row::new(vec![Value::UInt64(2)]),
+            ]),
+        ),
+    ]
+}
+
+/// `(description, pipeline, encoded packet, encoded responses, decoded responses)`
+#[allow(clippy::type_complexity)]
+pub fn pipeline_vectors() -> Vec<(
+    &'static str,
+    Pipeline,
+    &'static [u8],
+    &'static [u8],
+    Vec<Response>,
+)> {
+    vec![(
+        "two queries",
+        pipe!(
+            query!("use $current"),
+            query!("select * from m where a = ?", 1u64)
+        ),
+        b"P52\n12\n0\nuse $current27\n3\nselect * from m where a = ?\x021\n",
+        b"\x12\x10\x05\x00",
+        vec![Response::Empty, Response::Error(5)],
+    )]
+}
+
+#[cfg(test)]
+fn encode_pipeline(pipeline: &Pipeline) -> Vec<u8> {
+    let mut packet = format!("P{}\n", pipeline.buf().len()).into_bytes();
+    packet.extend_from_slice(pipeline.buf());
+    packet
+}
+
+#[test]
+fn encode_query_vectors() {
+    for (description, query, expected) in query_vectors() {
+        assert_eq!(query.debug_encode_packet(), expected, "{}", description);
+    }
+}
+
+#[test]
+fn encode_pipeline_vectors() {
+    for (description, pipeline, expected, _, _) in pipeline_vectors() {
+        assert_eq!(encode_pipeline(&pipeline), expected, "{}", description);
+    }
+}
+
+#[test]
+fn decode_response_vectors() {
+    for (description, bytes, expected) in response_vectors() {
+        // all at once
+        let (state, position) = Decoder::new(bytes, 0).validate_response(RState::default());
+        assert_eq!(
+            state,
+            DecodeState::Completed(expected.clone()),
+            "{}",
+            description
+        );
+        assert_eq!(position, bytes.len(), "{}", description);
+        // one byte at a time, resuming from the previous state and cursor
+        let mut state = RState::default();
+        let mut cursor = 0;
+        for i in 1..bytes.len() {
+            match Decoder::new(&bytes[..i], cursor).validate_response(state) {
+                (DecodeState::ChangeState(_state), _position) => {
+                    state = _state;
+                    cursor = _position;
+                }
+                (other, _) => panic!("{}: unexpected state {:?} at {}", description, other, i),
+            }
+        }
+        let (state, position) = Decoder::new(bytes, cursor).validate_response(state);
+        assert_eq!(state, DecodeState::Completed(expected), "{}", description);
+        assert_eq!(position, bytes.len(), "{}", description);
+    }
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn decode_response_vectors_into_buffer() {
+    // the same buffer is used for every response, so nothing may be left over from the previous one
+    let mut buf = crate::response::buf::ResponseBuf::new();
+    for (description, bytes, expected) in response_vectors() {
+        buf.clear();
+        assert_eq!(buf.decode(bytes), Ok(Some(bytes.len())), "{}", description);
+        assert_eq!(buf.to_response(), expected, "{}", description);
+        // one byte at a time
+        buf.clear();
+        for i in 1..bytes.len() {
+            assert_eq!(
+                buf.decode(&bytes[..i]),
+                Ok(None),
+                "{}: at {}",
+                description,
+                i
+            );
+        }
+        assert_eq!(buf.decode(bytes), Ok(Some(bytes.len())), "{}", description);
+        assert_eq!(buf.to_response(), expected, "{}", description);
+    }
+}
+
+#[test]
+fn decode_pipeline_vectors() {
+    for (description, pipeline, _, bytes, expected) in pipeline_vectors() {
+        let mut state = MRespState::default();
+        let mut cursor = 0;
+        for i in 1..bytes.len() {
+            match Decoder::new(&bytes[..i], cursor).validate_pipe(pipeline.query_count(), state) {
+                (PipelineResult::Pending(_state), _position) => {
+                    state = _state;
+                    cursor = _position;
+                }
+                (other, _) => panic!("{}: unexpected state {:?} at {}", description, other, i),
+            }
+        }
+        let (state, position) =
+            Decoder::new(bytes, cursor).validate_pipe(pipeline.query_count(), state);
+        assert_eq!(
+            state,
+            PipelineResult::Completed(expected),
+            "{}",
+            description
+        );
+        assert_eq!(position, bytes.len(), "{}", description);
+    }
+}
+
+/// A xorshift generator, so that the random packets are the same on every run
+#[cfg(test)]
+struct Rng(u64);
+
+#[cfg(test)]
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+/// A random value along with its encoding (lists are only generated up to `depth` levels deep)
+#[cfg(test)]
+fn random_value(rng: &mut Rng, depth: usize, out: &mut Vec<u8>) -> Value {
+    fn sized(out: &mut Vec<u8>, code: u8, payload: &[u8]) {
+        out.push(code);
+        out.extend(payload.len().to_string().bytes());
+        out.push(b'\n');
+        out.extend_from_slice(payload);
+    }
+    fn number(out: &mut Vec<u8>, code: u8, n: impl ToString) {
+        out.push(code);
+        out.extend(n.to_string().bytes());
+        out.push(b'\n');
+    }
+    let kinds = if depth == 0 { 14 } else { 15 };
+    let bytes = |rng: &mut Rng| -> Vec<u8> {
+        let len = [0, 1, 9, 10, 100][rng.below(5) as usize];
+        (0..len).map(|_| rng.next() as u8).collect()
+    };
+    match rng.below(kinds) {
+        0 => {
+            out.push(0x00);
+            Value::Null
+        }
+        1 => {
+            let b = rng.below(2) == 1;
+            out.extend([0x01, b as u8]);
+            Value::Bool(b)
+        }
+        2 => {
+            let n = rng.next() as u8;
+            number(out, 0x02, n);
+            Value::UInt8(n)
+        }
+        3 => {
+            let n = rng.next() as u16;
+            number(out, 0x03, n);
+            Value::UInt16(n)
+        }
+        4 => {
+            let n = rng.next() as u32;
+            number(out, 0x04, n);
+            Value::UInt32(n)
+        }
+        5 => {
+            let n = rng.next() >> rng.below(64);
+            number(out, 0x05, n);
+            Value::UInt64(n)
+        }
+        6 => {
+            let n = rng.next() as i8;
+            number(out, 0x06, n);
+            Value::SInt8(n)
+        }
+        7 => {
+            let n = rng.next() as i16;
+            number(out, 0x07, n);
+            Value::SInt16(n)
+        }
+        8 => {
+            let n = rng.next() as i32;
+            number(out, 0x08, n);
+            Value::SInt32(n)
+        }
+        9 => {
+            let n = (rng.next() as i64) >> rng.below(64);
+            number(out, 0x09, n);
+            Value::SInt64(n)
+        }
+        10 => {
+            let f = (rng.next() as i32) as f32 / 1024.0;
+            number(out, 0x0A, f);
+            Value::Float32(f)
+        }
+        11 => {
+            let f = (rng.next() as i64) as f64 / 65536.0;
+            number(out, 0x0B, f);
+            Value::Float64(f)
+        }
+        12 => {
+            let b = bytes(rng);
+            sized(out, 0x0C, &b);
+            Value::Binary(b)
+        }
+        13 => {
+            let s: String = bytes(rng)
+                .into_iter()
+                .map(|b| char::from(b'a' + b % 26))
+                .collect();
+            sized(out, 0x0D, s.as_bytes());
+            Value::String(s)
+        }
+        _ => {
+            let len = rng.below(4) as usize;
+            number(out, 0x0E, len);
+            Value::List(
+                (0..len)
+                    .map(|_| random_value(rng, depth - 1, out))
+                    .collect(),
+            )
+        }
+    }
+}
+
+/// A random response along with its encoding
+#[cfg(test)]
+fn random_response(rng: &mut Rng) -> (Vec<u8>, Response) {
+    let mut out = vec![];
+    let values = |rng: &mut Rng, n: usize, out: &mut Vec<u8>| -> Vec<Value> {
+        (0..n).map(|_| random_value(rng, 2, out)).collect()
+    };
+    let response = match rng.below(5) {
+        0 => Response::Value(random_value(rng, 2, &mut out)),
+        1 => {
+            let columns = 1 + rng.below(4) as usize;
+            out.extend(format!("\x11{columns}\n").bytes());
+            Response::Row(Row::new(values(rng, columns, &mut out)))
+        }
+        2 => {
+            let (rows, columns) = (rng.below(4) as usize, 1 + rng.below(4) as usize);
+            out.extend(format!("\x13{rows}\n{columns}\n").bytes());
+            Response::Rows(
+                (0..rows)
+                    .map(|_| Row::new(values(rng, columns, &mut out)))
+                    .collect(),
+            )
+        }
+        3 => {
+            out.push(0x12);
+            Response::Empty
+        }
+        _ => {
+            let code = rng.next() as u16;
+            out.push(0x10);
+            out.extend(code.to_le_bytes());
+            Response::Error(code)
+        }
+    };
+    (out, response)
+}
+
+#[test]
+fn decode_random_responses() {
+    // the scanning (with or without the `simd` feature) must not change what is decoded, however the bytes arrive
+    let mut rng = Rng(0x5eed_cafe_f00d_d00d);
+    for _ in 0..2000 {
+        let (bytes, expected) = random_response(&mut rng);
+        let (state, position) = Decoder::new(&bytes, 0).validate_response(RState::default());
+        assert_eq!(
+            state,
+            DecodeState::Completed(expected.clone()),
+            "{:?}",
+            bytes
+        );
+        assert_eq!(position, bytes.len());
+        // in random chunks
+        let (mut state, mut cursor, mut end) = (RState::default(), 0, 0);
+        let response = loop {
+            end = (end + 1 + rng.below(16) as usize).min(bytes.len());
+            match Decoder::new(&bytes[..end], cursor).validate_response(state) {
+                (DecodeState::ChangeState(_state), _position) => {
+                    state = _state;
+                    cursor = _position;
+                }
+                (DecodeState::Completed(response), position) => {
+                    assert_eq!(position, bytes.len());
+                    break response;
+                }
+                (other, _) => panic!("unexpected state {:?} for {:?}", other, bytes),
+            }
+        };
+        assert_eq!(response, expected, "{:?}", bytes);
+        #[cfg(feature = "sync")]
+        {
+            let mut buf = crate::response::buf::ResponseBuf::new();
+            assert_eq!(buf.decode(&bytes), Ok(Some(bytes.len())));
+            assert_eq!(buf.to_response(), expected, "{:?}", bytes);
+        }
+    }
+}
@@ -30,27 +30,170 @@
 //! ```
 //!
 
-use std::{
-    io::{self, Write},
-    iter::FromIterator,
-    num::{
-        NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU16, NonZeroU32,
-        NonZeroU64, NonZeroU8, NonZeroUsize,
+use {
+    crate::error::LimitError,
+    alloc::{
+        borrow::{Cow, ToOwned},
+        boxed::Box,
+        string::String,
+        sync::Arc,
+        vec::Vec,
+    },
+    core::{
+        fmt,
+        hash::{Hash, Hasher},
+        iter::FromIterator,
+        num::{
+            NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU16, NonZeroU32,
+            NonZeroU64, NonZeroU8, NonZeroUsize,
+        },
+        ops::Range,
+        sync::atomic::{AtomicU8, Ordering},
     },
 };
 
+/*
+    log redaction
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+/// What is left out when queries (and errors containing data received from the server) are formatted for logging. Set
+/// with [`set_log_redaction`]
+pub enum LogRedaction {
+    /// Nothing is left out (the default)
+    None = 0,
+    /// The parameters are left out, but the query string is kept. Since the client can't tell keys from values, all
+    /// parameters are left out
+    ValuesOnly = 1,
+    /// Both the query string and the parameters are left out
+    All = 2,
+}
+
+static LOG_REDACTION: AtomicU8 = AtomicU8::new(LogRedaction::None as u8);
+
+/// Set what is left out when queries are formatted with [`Debug`](fmt::Debug) (and when errors containing data
+/// received from the server are formatted) for the entire process
+pub fn set_log_redaction(redaction: LogRedaction) {
+    LOG_REDACTION.store(redaction as u8, Ordering::Relaxed)
+}
+
+/// Returns the current [`LogRedaction`] policy
+pub fn log_redaction() -> LogRedaction {
+    match LOG_REDACTION.load(Ordering::Relaxed) {
+        0 => LogRedaction::None,
+        1 => LogRedaction::ValuesOnly,
+        _ => LogRedaction::All,
+    }
+}
+
+/*
+    limits
+*/
+
+/// The default maximum number of parameters that a query can have (see [`Query::set_param_limit`])
+pub const DEFAULT_PARAM_LIMIT: usize = 65_535;
+/// The default maximum number of queries that a pipeline can have (see [`Pipeline::set_query_limit`])
+pub const DEFAULT_PIPELINE_LIMIT: usize = 65_535;
+
 /*
     query impl
 */
 
-#[derive(Debug, PartialEq, Clone)]
+/// Returns the range of the payload and the total length of the encoded parameter at the start of `b`
+fn decode_param(b: &[u8]) -> Option<(Range<usize>, usize)> {
+    let line = |b: &[u8]| b[1..].iter().position(|b| *b == b'\n').map(|p| p + 1);
+    match *b.first()? {
+        // null
+        0 => Some((1..1, 1)),
+        // bool
+        1 => b.get(1).map(|_| (1..2, 2)),
+        // uint, sint, float
+        2..=4 => line(b).map(|lf| (1..lf, lf + 1)),
+        // binary, string
+        5 | 6 => {
+            let lf = line(b)?;
+            let len: usize = core::str::from_utf8(&b[1..lf]).ok()?.parse().ok()?;
+            let end = (lf + 1).checked_add(len)?;
+            if end > b.len() {
+                return None;
+            }
+            Some((lf + 1..end, end))
+        }
+        LIST_SYM_OPEN => {
+            let mut i = 1;
+            while *b.get(i)? != LIST_SYM_CLOSE {
+                i += decode_param(&b[i..])?.1;
+            }
+            Some((1..i, i + 1))
+        }
+        _ => None,
+    }
+}
+
+/// The room reserved for the header of every parameter by [`Query::with_capacity`]: the type byte and the length line
+/// of a payload that's shorter than 100 bytes
+const PARAM_HEADER_HINT: usize = 4;
+/// The room reserved for the header of every query by [`Pipeline::with_capacity`]: the query and parameter length
+/// lines of a query that's shorter than 1000 bytes
+const PIPELINE_HEADER_HINT: usize = 8;
+
+#[derive(Clone)]
 /// A [`Query`] represents a Skyhash query. This is the "standard query" that you will normally use for almost all operations.
 ///
 /// Specification: `QTDEX-A/BQL-S1`
+///
+/// The [`Debug`](fmt::Debug) output respects the [`LogRedaction`] policy.
+///
+/// ## Equality
+///
+/// Two queries are equal (and have the same hash) if their query strings and their encoded parameters are the same
+/// bytes, so queries can be used as keys (for example, to deduplicate requests). How a parameter was built doesn't
+/// matter: a `&[u8]`, a `Vec<u8>` and a `[u8; N]` with the same bytes are encoded the same way, and so are a `&str`
+/// and a `String`. Parameters that are encoded the same way are also equal even if their Rust types differ (for
+/// example, `100u8` and `100u64`, since every unsigned integer is sent as the same type). The packet framing that is
+/// added when a query is sent isn't part of the comparison, and neither is the [parameter limit](Self::set_param_limit).
 pub struct Query {
     buf: Vec<u8>,
     param_cnt: usize,
     q_window: usize,
+    param_limit: usize,
+}
+
+impl PartialEq for Query {
+    fn eq(&self, other: &Self) -> bool {
+        (&self.buf, self.param_cnt, self.q_window) == (&other.buf, other.param_cnt, other.q_window)
+    }
+}
+
+impl Eq for Query {}
+
+impl Hash for Query {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (&self.buf, self.param_cnt, self.q_window).hash(state)
+    }
+}
+
+impl fmt::Debug for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match log_redaction() {
+            LogRedaction::None => f
+                .debug_struct("Query")
+                .field("buf", &self.buf)
+                .field("param_cnt", &self.param_cnt)
+                .field("q_window", &self.q_window)
+                .finish(),
+            LogRedaction::ValuesOnly => f
+                .debug_struct("Query")
+                .field("query", &self.query_str())
+                .field("param_cnt", &self.param_cnt)
+                .finish_non_exhaustive(),
+            LogRedaction::All => f
+                .debug_struct("Query")
+                .field("param_cnt", &self.param_cnt)
+                .finish_non_exhaustive(),
+        }
+    }
 }
 
 impl From<String> for Query {
@@ -74,12 +217,39 @@ impl Query {
     pub fn new_string(query: String) -> Self {
         Self::_new(query)
     }
+    /// Create a new query with room for `params` parameters whose payloads take up about `bytes` bytes in total, so
+    /// that adding the parameters doesn't need to grow the buffer again and again (useful for queries with a large
+    /// number of parameters)
+    ///
+    /// ```
+    /// use skytable::Query;
+    ///
+    /// let keys: Vec<String> = (0..1000).map(|i| format!("key-{i}")).collect();
+    /// let bytes = keys.iter().map(String::len).sum();
+    /// let mut q = Query::with_capacity("insert into myspace.mymodel(?)", keys.len(), bytes);
+    /// for key in &keys {
+    ///     q.push_param(key);
+    /// }
+    /// assert_eq!(q.param_cnt(), 1000);
+    /// ```
+    pub fn with_capacity(query: &str, params: usize, bytes: usize) -> Self {
+        let mut buf =
+            Vec::with_capacity(query.len() + bytes + params.saturating_mul(PARAM_HEADER_HINT));
+        buf.extend_from_slice(query.as_bytes());
+        Self {
+            buf,
+            param_cnt: 0,
+            q_window: query.len(),
+            param_limit: DEFAULT_PARAM_LIMIT,
+        }
+    }
     fn _new(query: String) -> Self {
         let l = query.len();
         Self {
             buf: query.into_bytes(),
             param_cnt: 0,
             q_window: l,
+            param_limit: DEFAULT_PARAM_LIMIT,
         }
     }
     /// Returns a reference to the query string
@@ -87,44 +257,144 @@ impl Query {
         unsafe { core::str::from_utf8_unchecked(&self.buf[..self.q_window]) }
     }
     /// Add a new parameter to the query
+    ///
+    /// ## Panics
+    ///
+    /// If the query would have more than [`param_limit`](Self::param_limit) parameters. Use
+    /// [`Self::try_push_param`] to handle that instead
+    #[track_caller]
     pub fn push_param(&mut self, param: impl SQParam) -> &mut Self {
-        self.param_cnt += param.append_param(&mut self.buf);
+        if let Err(e) = self.try_push_param(param) {
+            panic!("{}", e);
+        }
         self
     }
+    /// Add a new parameter to the query, unless the query would have more than [`param_limit`](Self::param_limit)
+    /// parameters (in which case the query is left as it was)
+    ///
+    /// ```
+    /// use skytable::{error::LimitError, Query};
+    ///
+    /// let mut q = Query::new("insert into myspace.mymodel(?, ?)");
+    /// q.set_param_limit(2);
+    /// q.try_push_param("key").unwrap().try_push_param("value").unwrap();
+    /// assert_eq!(q.try_push_param("extra").unwrap_err(), LimitError::Params(2));
+    /// ```
+    pub fn try_push_param(&mut self, param: impl SQParam) -> Result<&mut Self, LimitError> {
+        let limit = self.param_limit;
+        let len = self.buf.len();
+        // most parameters count as one, but custom parameters can be any number of them
+        let cnt = self
+            .param_cnt
+            .saturating_add(param.append_param(&mut self.buf));
+        if cnt > limit {
+            self.buf.truncate(len);
+            return Err(LimitError::Params(limit));
+        }
+        self.param_cnt = cnt;
+        Ok(self)
+    }
     /// Get the number of parameters
     pub fn param_cnt(&self) -> usize {
         self.param_cnt
     }
+    /// Set the maximum number of parameters that this query can have ([`DEFAULT_PARAM_LIMIT`] by default). Parameters
+    /// that were already added are kept even if there are more of them
+    pub fn set_param_limit(&mut self, limit: usize) -> &mut Self {
+        self.param_limit = limit;
+        self
+    }
+    /// Returns the maximum number of parameters that this query can have
+    pub fn param_limit(&self) -> usize {
+        self.param_limit
+    }
+    /// Returns an iterator over the parameters of this query, without their type information. This is useful when you
+    /// need to log or label queries without keeping a copy of the parameters around.
+    ///
+    /// Each item is the raw payload of a parameter:
+    /// - `null`: an empty slice
+    /// - `bool`: a single byte (`0` or `1`)
+    /// - numbers: the number formatted as a string (for example, `b"100"`)
+    /// - binary and strings: the binary data or string
+    /// - lists: the encoded elements of the list
+    ///
+    /// **Note**: If a custom [`SQParam`] implementation writes data that isn't a valid parameter, iteration stops
+    /// there.
+    pub fn params(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        let mut params = &self.buf[self.q_window..];
+        core::iter::from_fn(move || match decode_param(params) {
+            Some((payload, len)) => {
+                let ret = &params[payload];
+                params = &params[len..];
+                Some(ret)
+            }
+            None => {
+                params = &[];
+                None
+            }
+        })
+    }
+    /// Returns the first parameter of this query, if any (in the same form as [`Self::params`]). The action of the
+    /// query (for example, `select`) is part of the query string, see [`Self::query_str`]
+    pub fn first_arg(&self) -> Option<&[u8]> {
+        self.params().next()
+    }
     #[inline(always)]
-    pub(crate) fn write_packet(&self, buf: &mut impl Write) -> io::Result<()> {
+    pub(crate) fn write_packet(&self, buf: &mut Vec<u8>) {
+        self._write_packet(buf, None)
+    }
+    /// Same as [`Self::write_packet`], but the packet is followed by a binary parameter of `len` bytes. Only the header
+    /// of the parameter is written, and the caller must write the `len` bytes of the payload right after
+    #[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+    pub(crate) fn write_packet_with_streamed_param(&self, buf: &mut Vec<u8>, len: u64) {
+        self._write_packet(buf, Some(len))
+    }
+    fn _write_packet(&self, buf: &mut Vec<u8>, streamed: Option<u64>) {
         /*
             [[total packet size][query window]][[dataframe][qframe]]
             ^meta1            ^meta2           ^payload
         */
+        // header of the streamed binary parameter
+        let mut streamed_header = Vec::new();
+        if let Some(len) = streamed {
+            streamed_header.push(5);
+            streamed_header.extend(itoa::Buffer::new().format(len).as_bytes());
+            streamed_header.push(b'\n');
+        }
         // compute the total packet size
         // q window
         let mut query_window_buffer = itoa::Buffer::new();
         let query_window_str = query_window_buffer.format(self.q_window);
         // full packet
-        let total_packet_size = query_window_str.len() + 1 + self.buf.len();
+        let total_packet_size =
+            (query_window_str.len() + 1 + self.buf.len() + streamed_header.len()) as u64
+                + streamed.unwrap_or(0);
         let mut total_packet_size_buffer = itoa::Buffer::new();
         let total_packet_size_str = total_packet_size_buffer.format(total_packet_size);
+        buf.reserve(
+            1 + total_packet_size_str.len()
+                + 1
+                + query_window_str.len()
+                + 1
+                + self.buf.len()
+                + streamed_header.len(),
+        );
         // segment 1: meta
-        buf.write_all(b"S")?;
-        buf.write_all(total_packet_size_str.as_bytes())?;
-        buf.write_all(b"\n")?;
+        buf.push(b'S');
+        buf.extend_from_slice(total_packet_size_str.as_bytes());
+        buf.push(b'\n');
         // segment 2: variable meta
-        buf.write_all(query_window_str.as_bytes())?;
-        buf.write_all(b"\n")?;
+        buf.extend_from_slice(query_window_str.as_bytes());
+        buf.push(b'\n');
         // segment 3: payload
-        buf.write_all(&self.buf)?;
-        Ok(())
+        buf.extend_from_slice(&self.buf);
+        buf.extend_from_slice(&streamed_header);
     }
     #[inline(always)]
     /// Encodes the packet using Skyhash and returns a raw packet for debugging purposes
     pub fn debug_encode_packet(&self) -> Vec<u8> {
-        let mut v = vec![];
-        self.write_packet(&mut v).unwrap();
+        let mut v = Vec::new();
+        self.write_packet(&mut v);
         v
     }
 }
@@ -136,6 +406,7 @@ impl Query {
 pub struct Pipeline {
     cnt: usize,
     buf: Vec<u8>,
+    query_limit: usize,
 }
 
 impl Pipeline {
@@ -144,8 +415,16 @@ impl Pipeline {
         Self {
             cnt: 0,
             buf: Vec::new(),
+            query_limit: DEFAULT_PIPELINE_LIMIT,
         }
     }
+    #[cfg(any(
+        test,
+        feature = "sync",
+        feature = "aio",
+        feature = "async-std",
+        feature = "alloc-proto"
+    ))]
     pub(crate) fn buf(&self) -> &[u8] {
         &self.buf
     }
@@ -153,6 +432,32 @@ impl Pipeline {
     pub fn query_count(&self) -> usize {
         self.cnt
     }
+    /// Returns true if no queries were appended to this pipeline
+    pub fn is_empty(&self) -> bool {
+        self.cnt == 0
+    }
+    /// Create a new pipeline with room for `queries` queries that take up about `bytes` bytes in total (the sum of
+    /// the sizes of the query strings and their parameters)
+    pub fn with_capacity(queries: usize, bytes: usize) -> Self {
+        Self {
+            cnt: 0,
+            buf: Vec::with_capacity(bytes + queries.saturating_mul(PIPELINE_HEADER_HINT)),
+            query_limit: DEFAULT_PIPELINE_LIMIT,
+        }
+    }
+    /// Remove all the queries from this pipeline, keeping the allocated memory for reuse
+    pub fn clear(&mut self) {
+        self.cnt = 0;
+        self.buf.clear();
+    }
+    /// Empty pipelines are rejected before anything is sent, since there would be no response to wait for
+    #[cfg(any(feature = "sync", feature = "aio", feature = "async-std"))]
+    pub(crate) fn check_not_empty(&self) -> crate::error::ClientResult<()> {
+        if self.is_empty() {
+            return Err(crate::error::Error::EmptyPipeline);
+        }
+        Ok(())
+    }
     /// Same as [`Self::push`], but passes ownership to the [`Pipeline`]
     pub fn push_owned(&mut self, q: Query) {
         self.push(&q);
@@ -161,22 +466,52 @@ impl Pipeline {
     ///
     /// Note: It's not possible to get the query back from the pipeline since it's not indexed (and doing so would be an unnecessary
     /// waste of space and time). That's why we take a reference which allows the caller to continue owning the [`Query`] item
+    ///
+    /// ## Panics
+    ///
+    /// If the pipeline already has [`query_limit`](Self::query_limit) queries. Use [`Self::try_push`] to handle that
+    /// instead
+    #[track_caller]
     pub fn push(&mut self, q: &Query) {
-        // qlen
+        if let Err(e) = self.try_push(q) {
+            panic!("{}", e);
+        }
+    }
+    fn push_unchecked(&mut self, q: &Query) {
+        let mut qlen = itoa::Buffer::new();
+        let qlen = qlen.format(q.q_window);
+        let mut plen = itoa::Buffer::new();
+        let plen = plen.format(q.buf.len() - q.q_window);
         self.buf
-            .extend(itoa::Buffer::new().format(q.q_window).as_bytes());
+            .reserve(qlen.len() + 1 + plen.len() + 1 + q.buf.len());
+        // qlen
+        self.buf.extend(qlen.as_bytes());
         self.buf.push(b'\n');
         // plen
-        self.buf.extend(
-            itoa::Buffer::new()
-                .format(q.buf.len() - q.q_window)
-                .as_bytes(),
-        );
+        self.buf.extend(plen.as_bytes());
         self.buf.push(b'\n');
         // body
         self.buf.extend(&q.buf);
         self.cnt += 1;
     }
+    /// Add a query to this pipeline, unless it already has [`query_limit`](Self::query_limit) queries (in which case
+    /// the pipeline is left as it was)
+    pub fn try_push(&mut self, q: &Query) -> Result<(), LimitError> {
+        if self.cnt >= self.query_limit {
+            return Err(LimitError::Queries(self.query_limit));
+        }
+        self.push_unchecked(q);
+        Ok(())
+    }
+    /// Set the maximum number of queries that this pipeline can have ([`DEFAULT_PIPELINE_LIMIT`] by default). Queries
+    /// that were already added are kept even if there are more of them
+    pub fn set_query_limit(&mut self, limit: usize) {
+        self.query_limit = limit;
+    }
+    /// Returns the maximum number of queries that this pipeline can have
+    pub fn query_limit(&self) -> usize {
+        self.query_limit
+    }
     /// Add a query to this pipeline (builder pattern)
     ///
     /// This is intended to be used with the
@@ -189,12 +524,19 @@ impl Pipeline {
     ///     .add(&query!("drop space myspace"));
     /// assert_eq!(pipeline.query_count(), 2);
     /// ```
+    #[allow(clippy::should_implement_trait)]
     pub fn add(mut self, q: &Query) -> Self {
         self.push(q);
         self
     }
 }
 
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Q: AsRef<Query>, I> From<I> for Pipeline
 where
     I: Iterator<Item = Q>,
@@ -311,15 +653,32 @@ macro_rules! imp_number {
         $($(impl SQParam for $ty { fn append_param(&self, b: &mut Vec<u8>) -> usize {
             let mut buf = ::itoa::Buffer::new();
             let str = buf.format(<$base>::from(*self));
+            b.reserve(str.len() + 2);
             b.push($code); b.extend(str.as_bytes()); b.push(b'\n');
             1
         } })*)*
     }
 }
 
+/// Formats straight into the parameter buffer (instead of going through a `String`)
+struct ParamWriter<'a>(&'a mut Vec<u8>);
+
+impl fmt::Write for ParamWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
+
 macro_rules! imp_terminated_str_type {
     ($($code:literal => $($ty:ty),*),* $(,)?) => {
-        $($(impl SQParam for $ty { fn append_param(&self, buf: &mut Vec<u8>) -> usize { buf.push($code); buf.extend(self.to_string().as_bytes()); buf.push(b'\n'); 1} })*)*
+        $($(impl SQParam for $ty { fn append_param(&self, buf: &mut Vec<u8>) -> usize {
+            buf.push($code);
+            // writing to a vector can't fail
+            let _ = fmt::Write::write_fmt(&mut ParamWriter(buf), format_args!("{}", self));
+            buf.push(b'\n');
+            1
+        } })*)*
     }
 }
 
@@ -333,54 +692,55 @@ imp_terminated_str_type!(
     4 => f32, f64
 );
 
+/// Writes a parameter with a length-prefixed payload (`[code][length]\n[payload]`), growing the buffer at most once
+fn append_sized(buf: &mut Vec<u8>, code: u8, payload: &[u8]) {
+    let mut len = itoa::Buffer::new();
+    let len = len.format(payload.len());
+    buf.reserve(1 + len.len() + 1 + payload.len());
+    buf.push(code);
+    buf.extend_from_slice(len.as_bytes());
+    buf.push(b'\n');
+    buf.extend_from_slice(payload);
+}
+
 // bin
-impl<'a> SQParam for &'a [u8] {
+impl SQParam for &[u8] {
     fn append_param(&self, buf: &mut Vec<u8>) -> usize {
-        buf.push(5);
-        pushlen!(buf, self.len());
-        buf.extend(*self);
+        append_sized(buf, 5, self);
         1
     }
 }
 impl<const N: usize> SQParam for [u8; N] {
     fn append_param(&self, buf: &mut Vec<u8>) -> usize {
-        buf.push(5);
-        pushlen!(buf, self.len());
-        buf.extend(self);
+        append_sized(buf, 5, &self[..]);
         1
     }
 }
-impl<'a, const N: usize> SQParam for &'a [u8; N] {
+impl<const N: usize> SQParam for &[u8; N] {
     fn append_param(&self, buf: &mut Vec<u8>) -> usize {
-        buf.push(5);
-        pushlen!(buf, self.len());
-        buf.extend(*self);
+        append_sized(buf, 5, &self[..]);
         1
     }
 }
 impl SQParam for Vec<u8> {
     fn append_param(&self, buf: &mut Vec<u8>) -> usize {
-        buf.push(5);
-        pushlen!(buf, self.len());
-        buf.extend(self);
+        append_sized(buf, 5, &self[..]);
         1
     }
 }
-impl<'a> SQParam for &'a Vec<u8> {
+impl SQParam for &Vec<u8> {
     fn append_param(&self, q: &mut Vec<u8>) -> usize {
         self.as_slice().append_param(q)
     }
 }
 // str
-impl<'a> SQParam for &'a str {
+impl SQParam for &str {
     fn append_param(&self, buf: &mut Vec<u8>) -> usize {
-        buf.push(6);
-        pushlen!(buf, self.len());
-        buf.extend(self.as_bytes());
+        append_sized(buf, 6, self.as_bytes());
         1
     }
 }
-impl<'a> SQParam for &'a String {
+impl SQParam for &String {
     fn append_param(&self, q: &mut Vec<u8>) -> usize {
         self.as_str().append_param(q)
     }
@@ -390,9 +750,20 @@ impl SQParam for String {
         self.as_str().append_param(buf)
     }
 }
+macro_rules! imp_borrowed_str_type {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl SQParam for $ty { fn append_param(&self, buf: &mut Vec<u8>) -> usize { let s: &str = self; s.append_param(buf) } }
+            impl SQParam for &$ty { fn append_param(&self, buf: &mut Vec<u8>) -> usize { let s: &str = self; s.append_param(buf) } }
+        )*
+    }
+}
+
+// borrowed directly, without going through `ToString`
+imp_borrowed_str_type!(Cow<'_, str>, Box<str>, Arc<str>);
 
 const LIST_SYM_OPEN: u8 = 0x07;
-const LIST_SYM_CLOSE: u8 = ']' as u8;
+const LIST_SYM_CLOSE: u8 = b']';
 
 /// A list type representing a Skyhash list type, used in parameter lists
 #[derive(Debug, PartialEq, Clone)]
@@ -431,3 +802,110 @@ fn list_param() {
     assert_eq!(q.param_cnt(), 3);
     dbg!(String::from_utf8(q.debug_encode_packet())).unwrap();
 }
+
+#[test]
+fn collected_pipeline_encoding() {
+    let queries: Vec<Query> = (0..10u64)
+        .map(|i| query!("delete from myspace.mymodel where id = ?", i))
+        .collect();
+    let mut pushed = Pipeline::new();
+    queries.iter().for_each(|q| pushed.push(q));
+    let collected: Pipeline = queries.iter().collect();
+    assert_eq!(collected.buf(), pushed.buf());
+    assert_eq!(collected.query_count(), pushed.query_count());
+    let owned: Pipeline = queries.clone().into_iter().collect();
+    assert_eq!(owned.buf(), pushed.buf());
+    let mut extended: Pipeline = queries[..4].iter().collect();
+    extended.extend(&queries[4..]);
+    assert_eq!(extended.buf(), pushed.buf());
+}
+
+#[test]
+fn pipeline_with_capacity() {
+    let queries: Vec<Query> = (0..100u64)
+        .map(|i| query!("select * from myspace.mymodel where id = ?", i))
+        .collect();
+    let mut pipeline = Pipeline::new();
+    let mut reserved = Pipeline::with_capacity(queries.len(), 10);
+    for q in &queries {
+        pipeline.push(q);
+        reserved.push(q);
+    }
+    assert_eq!(reserved.query_count(), 100);
+    assert_eq!(reserved.buf(), pipeline.buf());
+    assert!(reserved
+        .buf()
+        .starts_with(b"42\n3\nselect * from myspace.mymodel where id = ?\x020\n42\n3\n"));
+}
+
+#[test]
+fn number_params_match_to_string() {
+    use alloc::string::ToString;
+    fn check<T: SQParam + ToString>(code: u8, v: T) {
+        let mut buf = vec![];
+        assert_eq!(v.append_param(&mut buf), 1);
+        assert_eq!(
+            buf,
+            [&[code][..], v.to_string().as_bytes(), b"\n"].concat(),
+            "{}",
+            v.to_string()
+        );
+    }
+    for v in [0, 9, 10, 99, 100, u32::MAX as usize, usize::MAX] {
+        check(2, v);
+    }
+    for v in [0, 9, 10, -9, -10, isize::MIN, isize::MAX] {
+        check(3, v);
+    }
+    check(2, u8::MAX);
+    check(2, u64::MAX);
+    check(3, i8::MIN);
+    check(3, i64::MIN);
+    for v in [
+        0.0,
+        -0.0,
+        1.5,
+        -0.25,
+        0.1 + 0.2,
+        1e-7,
+        f64::MAX,
+        f64::MIN_POSITIVE,
+        f64::INFINITY,
+        f64::NAN,
+    ] {
+        check(4, v);
+    }
+    check(4, f32::MAX);
+    check(4, 0.1f32);
+}
+
+#[cfg(test)]
+/// Restores the log redaction policy that was set when it was created
+struct RestoreLogRedaction(LogRedaction);
+
+#[cfg(test)]
+impl Drop for RestoreLogRedaction {
+    fn drop(&mut self) {
+        set_log_redaction(self.0)
+    }
+}
+
+#[test]
+fn debug_redaction() {
+    const SENTINEL: &str = "hunter2-sentinel";
+    let _settings = crate::error::FORMAT_SETTINGS.lock().unwrap();
+    let _restore = RestoreLogRedaction(log_redaction());
+    set_log_redaction(LogRedaction::None);
+    let q = query!("insert into myspace.mymodel(?, ?)", "sayan", SENTINEL);
+    let sentinel_bytes = format!("{:?}", SENTINEL.as_bytes());
+    let sentinel_bytes = &sentinel_bytes[1..sentinel_bytes.len() - 1];
+    assert!(format!("{q:?}").contains(sentinel_bytes));
+    set_log_redaction(LogRedaction::ValuesOnly);
+    let formatted = format!("{q:?}");
+    assert!(!formatted.contains(sentinel_bytes) && !formatted.contains(SENTINEL));
+    assert!(formatted.contains("insert into myspace.mymodel(?, ?)"));
+    set_log_redaction(LogRedaction::All);
+    let formatted = format!("{q:?}");
+    assert!(!formatted.contains(sentinel_bytes) && !formatted.contains(SENTINEL));
+    assert!(!formatted.contains("insert into"));
+}
@@ -0,0 +1,384 @@
+/*
+ * Copyright 2024, Sayan Nandan <nandansayan@outlook.com>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+*/
+
+//! # Rendering responses
+//!
+//! This module renders responses as human readable text (for example, in a REPL) with
+//! [`Response::to_table_string`]:
+//!
+//! - Scalars are rendered on one line (strings are quoted and escaped)
+//! - Lists and rows are rendered as numbered lines, with nested lists indented under their number
+//! - Multiple rows are rendered as a table, one row per line, with the columns aligned
+//! - Binary values are rendered as a hex dump of their first bytes
+//! - Empty responses and error codes are rendered as `(empty)` and `(error <code>)`
+//!
+//! ## Example
+//!
+//! ```
+//! use skytable::{
+//!     render::RenderOptions,
+//!     response::{Response, Value},
+//! };
+//!
+//! let resp = Response::Value(Value::List(vec![
+//!     Value::String("sayan".into()),
+//!     Value::List(vec![Value::UInt8(20), Value::Binary(vec![0xca, 0xfe])]),
+//! ]));
+//! assert_eq!(
+//!     resp.to_table_string(RenderOptions::new()),
+//!     "1) \"sayan\"\n2) 1) 20\n   2) <ca fe>"
+//! );
+//! ```
+
+use {
+    crate::response::{Response, Row, Value},
+    alloc::{format, string::String, vec, vec::Vec},
+    core::fmt::Write,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Options for [`Response::to_table_string`]
+pub struct RenderOptions {
+    max_width: Option<usize>,
+    max_rows: Option<usize>,
+    hex_preview: usize,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderOptions {
+    /// Create the default options: no limit on the width of lines or the number of rows, and a hex preview of 16
+    /// bytes
+    pub const fn new() -> Self {
+        Self {
+            max_width: None,
+            max_rows: None,
+            hex_preview: 16,
+        }
+    }
+    /// Cut lines that are wider than `max_width` characters (ending them with `...`)
+    pub const fn with_max_width(mut self, max_width: usize) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+    /// Render at most `max_rows` rows (or elements of a list, at every level) followed by the number of rows that
+    /// were left out
+    pub const fn with_max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = Some(max_rows);
+        self
+    }
+    /// Render at most `hex_preview` bytes of binary values
+    pub const fn with_hex_preview(mut self, hex_preview: usize) -> Self {
+        self.hex_preview = hex_preview;
+        self
+    }
+    /// Returns the maximum width of a line, if any
+    pub const fn max_width(&self) -> Option<usize> {
+        self.max_width
+    }
+    /// Returns the maximum number of rows (or elements of a list), if any
+    pub const fn max_rows(&self) -> Option<usize> {
+        self.max_rows
+    }
+    /// Returns the number of bytes of binary values that are rendered
+    pub const fn hex_preview(&self) -> usize {
+        self.hex_preview
+    }
+    /// Returns the number of items (out of `len`) that are rendered
+    fn shown(&self, len: usize) -> usize {
+        self.max_rows.map_or(len, |max| len.min(max))
+    }
+}
+
+impl Response {
+    /// Render this response as human readable text (see the [`render`](crate::render) module for the format)
+    pub fn to_table_string(&self, opts: RenderOptions) -> String {
+        let lines = match self {
+            Self::Empty => vec!["(empty)".into()],
+            Self::Error(code) => vec![format!("(error {code})")],
+            Self::Value(v) => block(v, &opts),
+            Self::Row(row) => list(row, &opts),
+            Self::Rows(rows) => table(rows, &opts),
+        };
+        let mut ret = String::new();
+        for (i, line) in lines.iter().enumerate() {
+            if i != 0 {
+                ret.push('\n');
+            }
+            push_line(&mut ret, line, opts.max_width);
+        }
+        ret
+    }
+}
+
+/// Append `line` to `buf`, cutting it at `max_width` characters
+fn push_line(buf: &mut String, line: &str, max_width: Option<usize>) {
+    match max_width {
+        Some(max) if line.chars().count() > max => {
+            buf.extend(line.chars().take(max.saturating_sub(3)));
+            buf.push_str(&"..."[..max.min(3)]);
+        }
+        _ => buf.push_str(line),
+    }
+}
+
+/// Render a value on as many lines as it needs
+fn block(v: &Value, opts: &RenderOptions) -> Vec<String> {
+    match v {
+        Value::List(l) if !l.is_empty() => list(l, opts),
+        v => vec![inline(v, opts)],
+    }
+}
+
+/// Render the elements of a list as numbered lines, indenting nested lists under their number
+fn list(items: &[Value], opts: &RenderOptions) -> Vec<String> {
+    if items.is_empty() {
+        return vec!["[]".into()];
+    }
+    let shown = opts.shown(items.len());
+    let width = digits(shown);
+    let mut lines = vec![];
+    for (i, item) in items[..shown].iter().enumerate() {
+        let number = format!("{:>width$}) ", i + 1);
+        let indent = " ".repeat(number.len());
+        for (j, line) in block(item, opts).into_iter().enumerate() {
+            let prefix = if j == 0 { &number } else { &indent };
+            lines.push(format!("{prefix}{line}"));
+        }
+    }
+    more(&mut lines, items.len() - shown);
+    lines
+}
+
+/// Render rows as a table, with one (numbered) line per row and the columns aligned
+fn table(rows: &[Row], opts: &RenderOptions) -> Vec<String> {
+    if rows.is_empty() {
+        return vec!["(no rows)".into()];
+    }
+    let shown = opts.shown(rows.len());
+    let cells: Vec<Vec<String>> = rows[..shown]
+        .iter()
+        .map(|row| row.iter().map(|v| inline(v, opts)).collect())
+        .collect();
+    let mut widths: Vec<usize> = vec![];
+    for row in &cells {
+        for (i, cell) in row.iter().enumerate() {
+            let len = cell.chars().count();
+            match widths.get_mut(i) {
+                Some(width) => *width = (*width).max(len),
+                None => widths.push(len),
+            }
+        }
+    }
+    let width = digits(shown);
+    let mut lines = vec![];
+    for (i, row) in cells.iter().enumerate() {
+        let mut line = format!("{:>width$}) ", i + 1);
+        for (j, cell) in row.iter().enumerate() {
+            if j != 0 {
+                line.push_str(" | ");
+            }
+            if j == row.len() - 1 {
+                line.push_str(cell);
+            } else {
+                let _ = write!(line, "{cell:<0$}", widths[j]);
+            }
+        }
+        lines.push(line);
+    }
+    more(&mut lines, rows.len() - shown);
+    lines
+}
+
+/// Render a value on a single line
+fn inline(v: &Value, opts: &RenderOptions) -> String {
+    match v {
+        Value::Null => "null".into(),
+        Value::Bool(b) => format!("{b}"),
+        Value::UInt8(n) => format!("{n}"),
+        Value::UInt16(n) => format!("{n}"),
+        Value::UInt32(n) => format!("{n}"),
+        Value::UInt64(n) => format!("{n}"),
+        Value::SInt8(n) => format!("{n}"),
+        Value::SInt16(n) => format!("{n}"),
+        Value::SInt32(n) => format!("{n}"),
+        Value::SInt64(n) => format!("{n}"),
+        Value::Float32(f) => format!("{f:?}"),
+        Value::Float64(f) => format!("{f:?}"),
+        Value::String(s) => format!("{s:?}"),
+        Value::Binary(b) => hex(b, opts.hex_preview),
+        Value::List(l) => {
+            let shown = opts.shown(l.len());
+            let mut ret = String::from("[");
+            for (i, v) in l[..shown].iter().enumerate() {
+                if i != 0 {
+                    ret.push_str(", ");
+                }
+                ret.push_str(&inline(v, opts));
+            }
+            if shown < l.len() {
+                if shown != 0 {
+                    ret.push_str(", ");
+                }
+                let _ = write!(ret, "... ({} more)", l.len() - shown);
+            }
+            ret.push(']');
+            ret
+        }
+    }
+}
+
+/// Render the first `preview` bytes of `b` in hex
+fn hex(b: &[u8], preview: usize) -> String {
+    let mut ret = String::from("<");
+    for (i, byte) in b.iter().take(preview).enumerate() {
+        if i != 0 {
+            ret.push(' ');
+        }
+        let _ = write!(ret, "{byte:02x}");
+    }
+    if b.len() > preview {
+        if preview != 0 {
+            ret.push(' ');
+        }
+        let _ = write!(ret, "... ({} bytes)", b.len());
+    }
+    ret.push('>');
+    ret
+}
+
+fn more(lines: &mut Vec<String>, hidden: usize) {
+    if hidden != 0 {
+        lines.push(format!("... ({hidden} more)"));
+    }
+}
+
+fn digits(n: usize) -> usize {
+    let mut digits = 1;
+    let mut n = n / 10;
+    while n != 0 {
+        digits += 1;
+        n /= 10;
+    }
+    digits
+}
+
+#[test]
+fn render_scalars() {
+    let opts = RenderOptions::new();
+    for (v, expected) in [
+        (Value::Null, "null"),
+        (Value::Bool(true), "true"),
+        (Value::UInt8(u8::MAX), "255"),
+        (Value::UInt16(1096), "1096"),
+        (Value::UInt32(u32::MAX), "4294967295"),
+        (Value::UInt64(u64::MAX), "18446744073709551615"),
+        (Value::SInt8(i8::MIN), "-128"),
+        (Value::SInt16(-1032), "-1032"),
+        (Value::SInt32(42), "42"),
+        (Value::SInt64(i64::MIN), "-9223372036854775808"),
+        (Value::Float32(1.5), "1.5"),
+        (Value::Float64(-1.0), "-1.0"),
+        (Value::String("sa\"y\nan".into()), "\"sa\\\"y\\nan\""),
+        (Value::Binary(vec![0, b'\n', 0xff]), "<00 0a ff>"),
+        (Value::Binary(vec![]), "<>"),
+        (Value::List(vec![]), "[]"),
+    ] {
+        assert_eq!(
+            Response::Value(v.clone()).to_table_string(opts),
+            expected,
+            "{:?}",
+            v
+        );
+    }
+    assert_eq!(Response::Empty.to_table_string(opts), "(empty)");
+    assert_eq!(Response::Error(5).to_table_string(opts), "(error 5)");
+    assert_eq!(Response::Row(Row::new(vec![])).to_table_string(opts), "[]");
+    assert_eq!(Response::Rows(vec![]).to_table_string(opts), "(no rows)");
+}
+
+#[test]
+fn render_nested_lists() {
+    let v = Value::List(vec![
+        Value::String("a".into()),
+        Value::List(vec![
+            Value::UInt8(20),
+            Value::List(vec![Value::Null, Value::List(vec![Value::Bool(false)])]),
+            Value::List(vec![]),
+        ]),
+        Value::Float64(0.5),
+    ]);
+    assert_eq!(
+        Response::Value(v.clone()).to_table_string(RenderOptions::new()),
+        "1) \"a\"\n\
+         2) 1) 20\n   \
+            2) 1) null\n      \
+               2) 1) false\n   \
+            3) []\n\
+         3) 0.5"
+    );
+    // every level is limited
+    assert_eq!(
+        Response::Value(v).to_table_string(RenderOptions::new().with_max_rows(2)),
+        "1) \"a\"\n\
+         2) 1) 20\n   \
+            2) 1) null\n      \
+               2) 1) false\n   \
+            ... (1 more)\n\
+         ... (1 more)"
+    );
+    // numbers are aligned
+    let row = Row::new((0..10).map(Value::UInt8).collect());
+    let rendered = Response::Row(row).to_table_string(RenderOptions::new());
+    assert!(rendered.starts_with(" 1) 0\n 2) 1\n"));
+    assert!(rendered.ends_with("\n10) 9"));
+}
+
+#[test]
+fn render_table() {
+    let rows = vec![
+        Row::new(vec![
+            Value::String("sayan".into()),
+            Value::List(vec![Value::UInt8(20), Value::Null]),
+            Value::Float64(-0.25),
+        ]),
+        Row::new(vec![
+            Value::String("el".into()),
+            Value::List(vec![]),
+            Value::Float64(1.5),
+        ]),
+        Row::new(vec![
+            Value::String("x".into()),
+            Value::List(vec![Value::List(vec![Value::Binary(vec![1; 20])])]),
+            Value::Null,
+        ]),
+    ];
+    assert_eq!(
+        Response::Rows(rows.clone()).to_table_string(RenderOptions::new().with_hex_preview(2)),
+        "1) \"sayan\" | [20, null]                 | -0.25\n\
+         2) \"el\"    | []                         | 1.5\n\
+         3) \"x\"     | [[<01 01 ... (20 bytes)>]] | null"
+    );
+    assert_eq!(
+        Response::Rows(rows)
+            .to_table_string(RenderOptions::new().with_max_rows(1).with_max_width(20)),
+        "1) \"sayan\" | [20,...\n... (2 more)"
+    );
+}
@@ -31,12 +31,72 @@
 //! let (username, password, email): (String, String, Option<String>) = db.query_parse(&q).unwrap();
 //! ```
 //!
+//! To decode many responses without allocating every one of them, see [`buf`].
+
+#[cfg(feature = "sync")]
+pub mod buf;
 
 use {
-    crate::error::{ClientResult, Error, ParseError},
-    std::ops::Deref,
+    crate::error::{ClientResult, ElementCount, Error, ParseError, ValueMismatch},
+    alloc::{
+        borrow::{Cow, ToOwned},
+        boxed::Box,
+        format,
+        string::String,
+        vec,
+        vec::Vec,
+    },
+    core::{
+        convert::TryFrom,
+        fmt::{self, Write},
+        num::{
+            NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroU16, NonZeroU32, NonZeroU64,
+            NonZeroU8,
+        },
+        ops::Deref,
+        sync::atomic::{AtomicUsize, Ordering},
+    },
 };
 
+/*
+    value previews
+*/
+
+static VALUE_PREVIEW_LEN: AtomicUsize = AtomicUsize::new(256);
+
+/// Set the maximum number of bytes of a value that are included in [`ParseError::ValueMismatch`] errors (256 by
+/// default) for the entire process
+pub fn set_value_preview_len(len: usize) {
+    VALUE_PREVIEW_LEN.store(len, Ordering::Relaxed)
+}
+
+/// Returns the maximum number of bytes of a value that are included in [`ParseError::ValueMismatch`] errors
+pub fn value_preview_len() -> usize {
+    VALUE_PREVIEW_LEN.load(Ordering::Relaxed)
+}
+
+/// A writer that keeps at most `limit` bytes and fails once it's full, so that large values are never fully rendered
+struct BoundedWriter {
+    out: String,
+    limit: usize,
+}
+
+impl Write for BoundedWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let room = self.limit - self.out.len();
+        if s.len() <= room {
+            self.out.push_str(s);
+            return Ok(());
+        }
+        let mut end = room;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.out.push_str(&s[..end]);
+        Err(fmt::Error)
+    }
+}
+
 /// The value directly returned by the server without any additional type parsing and/or casting
 #[derive(Debug, PartialEq, Clone)]
 pub enum Value {
@@ -87,8 +147,218 @@ impl Value {
     pub fn parse_cloned<T: FromValue>(&self) -> ClientResult<T> {
         T::from_value(self.clone())
     }
+    /// Parse a list of alternating keys and values (`[k1, v1, k2, v2, ...]`) into `(key, value)` pairs. The keys must
+    /// be strings and the values are parsed into `V`. To parse a list of values, use [`RList`] instead.
+    ///
+    /// An error is returned if this isn't a list, if it has an odd number of elements or if a key or value can't be
+    /// parsed (along with the index of the element in the list).
+    ///
+    /// ```
+    /// use {skytable::response::Value, std::collections::HashMap};
+    ///
+    /// let v = Value::List(vec![Value::String("sayan".into()), Value::UInt64(100)]);
+    /// let followers: HashMap<String, u64> = v.into_pairs().unwrap().into_iter().collect();
+    /// assert_eq!(followers["sayan"], 100);
+    /// ```
+    pub fn into_pairs<V: FromValue>(self) -> ClientResult<Vec<(String, V)>> {
+        self.pairs(String::from_value)
+    }
+    /// Same as [`into_pairs`](Self::into_pairs), but the keys are returned as raw bytes, so that they can be binary
+    /// (such as UUIDs stored as 16 bytes) as well as strings
+    ///
+    /// ```
+    /// use {skytable::response::Value, std::collections::HashMap};
+    ///
+    /// let key = [0xca, 0xfe, 0xba, 0xbe];
+    /// let v = Value::List(vec![Value::Binary(key.to_vec()), Value::UInt64(100)]);
+    /// let followers: HashMap<Vec<u8>, u64> = v.into_binary_pairs().unwrap().into_iter().collect();
+    /// assert_eq!(followers[&key[..]], 100);
+    /// ```
+    pub fn into_binary_pairs<V: FromValue>(self) -> ClientResult<Vec<(Vec<u8>, V)>> {
+        self.pairs(|k| match k {
+            Self::Binary(k) => Ok(k),
+            Self::String(k) => Ok(k.into_bytes()),
+            k => Err(mismatch(&k, "binary or string key")),
+        })
+    }
+    fn pairs<K, V: FromValue>(
+        self,
+        key: impl Fn(Value) -> ClientResult<K>,
+    ) -> ClientResult<Vec<(K, V)>> {
+        let list = match self {
+            Self::List(l) => l,
+            v => return Err(mismatch(&v, "key/value pairs")),
+        };
+        if list.len() % 2 != 0 {
+            return Err(Error::ParseError(ParseError::Other(format!(
+                "expected key/value pairs but received a list with {} element(s)",
+                list.len()
+            ))));
+        }
+        let mut pairs = Vec::with_capacity(list.len() / 2);
+        let mut elements = list.into_iter().enumerate();
+        while let (Some((i, k)), Some((j, v))) = (elements.next(), elements.next()) {
+            let k = key(k).map_err(|e| in_element(e, i))?;
+            let v = V::from_value(v).map_err(|e| in_element(e, j))?;
+            pairs.push((k, v));
+        }
+        Ok(pairs)
+    }
+    /// Returns the element at `index` if this is a list
+    pub fn get(&self, index: usize) -> Option<&Value> {
+        match self {
+            Self::List(l) => l.get(index),
+            _ => None,
+        }
+    }
+    /// Returns the value at `path` in nested lists (for example, `[0, 2]` is the third element of the first element of
+    /// this list). Returns [`None`] if an index is out of range or if a value on the way isn't a list. An empty path
+    /// returns this value. See also [`value_path!`](crate::value_path)
+    ///
+    /// ```
+    /// use skytable::response::Value;
+    ///
+    /// let v = Value::List(vec![
+    ///     Value::List(vec![Value::UInt8(1), Value::UInt8(2)]),
+    ///     Value::String("sayan".into()),
+    /// ]);
+    /// assert_eq!(v.get_path(&[0, 1]), Some(&Value::UInt8(2)));
+    /// assert_eq!(v.get_path(&[0, 2]), None);
+    /// assert_eq!(v.get_path(&[1, 0]), None);
+    /// ```
+    pub fn get_path(&self, path: &[usize]) -> Option<&Value> {
+        path.iter().try_fold(self, |v, &index| v.get(index))
+    }
+    /// Same as [`Self::get_path`], but fails with a [`ParseError::Other`] that names the path segment that couldn't be
+    /// followed (and why)
+    pub fn try_path(&self, path: &[usize]) -> ClientResult<&Value> {
+        self.follow(path, 0)
+    }
+    /// Follow `path`, whose first segment is segment `first` of the whole path
+    fn follow(&self, path: &[usize], first: usize) -> ClientResult<&Value> {
+        let mut v = self;
+        for (segment, &index) in path.iter().enumerate() {
+            let segment = first + segment;
+            v = match v {
+                Self::List(l) => l.get(index).ok_or_else(|| {
+                    path_error(
+                        segment,
+                        index,
+                        format_args!("is out of range for a list of {} element(s)", l.len()),
+                    )
+                })?,
+                v => {
+                    return Err(path_error(
+                        segment,
+                        index,
+                        format_args!("can't be used on a {} value", v.variant_name()),
+                    ))
+                }
+            };
+        }
+        Ok(v)
+    }
+    /// Returns the name of this variant
+    fn variant_name(&self) -> &'static str {
+        match self {
+            Self::Null => "Null",
+            Self::Bool(_) => "Bool",
+            Self::UInt8(_) => "UInt8",
+            Self::UInt16(_) => "UInt16",
+            Self::UInt32(_) => "UInt32",
+            Self::UInt64(_) => "UInt64",
+            Self::SInt8(_) => "SInt8",
+            Self::SInt16(_) => "SInt16",
+            Self::SInt32(_) => "SInt32",
+            Self::SInt64(_) => "SInt64",
+            Self::Float32(_) => "Float32",
+            Self::Float64(_) => "Float64",
+            Self::Binary(_) => "Binary",
+            Self::String(_) => "String",
+            Self::List(_) => "List",
+        }
+    }
+    /// Returns the [`Debug`](fmt::Debug) rendering of this value (with binary values in hex, since they usually
+    /// aren't text), truncated to [`value_preview_len`] bytes
+    fn preview(&self) -> String {
+        let mut w = BoundedWriter {
+            out: String::new(),
+            limit: value_preview_len(),
+        };
+        if write!(w, "{:?}", Preview(self)).is_err() {
+            w.out.push_str("...");
+        }
+        w.out
+    }
+    /// Returns the value of any integer type (signed or unsigned, of any width)
+    fn integer(&self) -> Option<i128> {
+        Some(match *self {
+            Self::UInt8(v) => v.into(),
+            Self::UInt16(v) => v.into(),
+            Self::UInt32(v) => v.into(),
+            Self::UInt64(v) => v.into(),
+            Self::SInt8(v) => v.into(),
+            Self::SInt16(v) => v.into(),
+            Self::SInt32(v) => v.into(),
+            Self::SInt64(v) => v.into(),
+            _ => return None,
+        })
+    }
+}
+
+/// Same as the [`Debug`](fmt::Debug) rendering of a [`Value`], except that binary values are written in hex
+/// (`Binary(<ca fe>)`)
+struct Preview<'a>(&'a Value);
+
+impl fmt::Debug for Preview<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Value::Binary(b) => {
+                f.write_str("Binary(<")?;
+                for (i, byte) in b.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{byte:02x}")?;
+                }
+                f.write_str(">)")
+            }
+            Value::List(l) => {
+                f.write_str("List(")?;
+                f.debug_list().entries(l.iter().map(Preview)).finish()?;
+                f.write_str(")")
+            }
+            v => fmt::Debug::fmt(v, f),
+        }
+    }
+}
+
+macro_rules! saturating_conversions {
+    ($($fn:ident -> $ty:ty),* $(,)?) => {
+        impl Value {
+            $(
+                #[doc = concat!("If this is an integer (of any width), return it as a [`", stringify!($ty), "`], clamping it to the range of")]
+                #[doc = concat!("[`", stringify!($ty), "`]. Returns [`None`] for other types")]
+                pub fn $fn(&self) -> Option<$ty> {
+                    self.integer()
+                        .map(|v| v.clamp(<$ty>::MIN as i128, <$ty>::MAX as i128) as $ty)
+                }
+            )*
+        }
+    };
 }
 
+saturating_conversions!(
+    to_u8_saturating -> u8,
+    to_u16_saturating -> u16,
+    to_u32_saturating -> u32,
+    to_u64_saturating -> u64,
+    to_i8_saturating -> i8,
+    to_i16_saturating -> i16,
+    to_i32_saturating -> i32,
+    to_i64_saturating -> i64,
+);
+
 #[derive(Debug, PartialEq, Clone)]
 /// A row returned by the server
 pub struct Row {
@@ -103,6 +373,13 @@ impl Deref for Row {
 }
 
 impl Row {
+    #[cfg(any(
+        test,
+        feature = "sync",
+        feature = "aio",
+        feature = "async-std",
+        feature = "alloc-proto"
+    ))]
     pub(crate) fn new(values: Vec<Value>) -> Self {
         Self { values }
     }
@@ -154,6 +431,154 @@ impl Response {
     pub fn parse<T: FromResponse>(self) -> ClientResult<T> {
         T::from_response(self)
     }
+    /// Check that the server returned an empty response (which is what the server returns when a write, such as an
+    /// `insert`, `update` or `delete`, succeeds).
+    ///
+    /// Error codes are returned as [`Error::ServerError`], and any other response is returned as a
+    /// [`ParseError::Other`] describing what was received instead (without including any of the received data).
+    pub fn ensure_empty(self) -> ClientResult<()> {
+        self.check_empty()
+    }
+    /// Same as [`Self::ensure_empty`], but borrows the response (so that it can still be used afterwards)
+    pub fn check_empty(&self) -> ClientResult<()> {
+        let received = match self {
+            Self::Empty => return Ok(()),
+            Self::Error(e) => return Err(Error::ServerError(*e)),
+            Self::Value(_) => "a value".to_owned(),
+            Self::Row(row) => format!("a row with {} column(s)", row.values().len()),
+            Self::Rows(rows) => format!("{} row(s)", rows.len()),
+        };
+        Err(Error::ParseError(ParseError::Other(format!(
+            "expected an empty response but received {received}"
+        ))))
+    }
+    /// Returns the value at `index` of the row, or of the list, that the server returned. Returns [`None`] for any
+    /// other response (use [`Self::get_path`] to get to the values in [`Response::Rows`])
+    pub fn get(&self, index: usize) -> Option<&Value> {
+        match self {
+            Self::Value(v) => v.get(index),
+            Self::Row(row) => row.get(index),
+            _ => None,
+        }
+    }
+    /// Returns the value at `path` in this response. The path starts with the index of the row for
+    /// [`Response::Rows`], and then the column for a row. The rest of the path indexes into nested lists, like
+    /// [`Value::get_path`]. Returns [`None`] if the path doesn't lead to a value. See also
+    /// [`value_path!`](crate::value_path)
+    ///
+    /// ```
+    /// use skytable::response::{Response, Row, Value};
+    ///
+    /// let resp = Response::Rows(vec![Row::from(vec![
+    ///     Value::String("sayan".into()),
+    ///     Value::List(vec![Value::String("rust".into())]),
+    /// ])]);
+    /// assert_eq!(resp.get_path(&[0, 1, 0]), Some(&Value::String("rust".into())));
+    /// assert_eq!(resp.get_path(&[1, 0]), None);
+    /// ```
+    pub fn get_path(&self, path: &[usize]) -> Option<&Value> {
+        match (self, path) {
+            (Self::Value(v), path) => v.get_path(path),
+            (Self::Row(row), [column, path @ ..]) => row.get(*column)?.get_path(path),
+            (Self::Rows(rows), [row, column, path @ ..]) => {
+                rows.get(*row)?.get(*column)?.get_path(path)
+            }
+            _ => None,
+        }
+    }
+    /// Same as [`Self::get_path`], but fails with a [`ParseError::Other`] that names the path segment that couldn't be
+    /// followed (and why). Error codes are returned as [`Error::ServerError`]
+    ///
+    /// ```
+    /// use skytable::response::{Response, Row, Value};
+    ///
+    /// let resp = Response::Row(Row::from(vec![Value::List(vec![Value::UInt8(1)])]));
+    /// assert_eq!(
+    ///     resp.try_path(&[0, 3]).unwrap_err().to_string(),
+    ///     "application parse error: path segment 1 (index 3) is out of range for a list of 1 element(s)"
+    /// );
+    /// ```
+    pub fn try_path(&self, path: &[usize]) -> ClientResult<&Value> {
+        /// The column at `path[0]`, which is segment `segment` of the whole path
+        fn row<'a>(row: &'a Row, segment: usize, path: &[usize]) -> ClientResult<&'a Value> {
+            match path.first() {
+                Some(&column) => row.get(column).ok_or_else(|| {
+                    path_error(
+                        segment,
+                        column,
+                        format_args!("is out of range for a row of {} column(s)", row.len()),
+                    )
+                }),
+                None => Err(path_ends(segment, "a row")),
+            }
+        }
+        match self {
+            Self::Value(v) => v.try_path(path),
+            Self::Row(r) => row(r, 0, path)?.follow(&path[1..], 1),
+            Self::Rows(rows) => {
+                let (&index, rest) = path
+                    .split_first()
+                    .ok_or_else(|| path_ends(0, "a list of rows"))?;
+                let r = rows.get(index).ok_or_else(|| {
+                    path_error(
+                        0,
+                        index,
+                        format_args!("is out of range for {} row(s)", rows.len()),
+                    )
+                })?;
+                row(r, 1, rest)?.follow(&rest[1..], 2)
+            }
+            Self::Empty => match path.first() {
+                Some(&index) => Err(path_error(
+                    0,
+                    index,
+                    format_args!("can't be used on an empty response"),
+                )),
+                None => Err(path_ends(0, "an empty response")),
+            },
+            Self::Error(e) => Err(Error::ServerError(*e)),
+        }
+    }
+    /// Returns the error code if the server returned an error
+    pub fn as_error(&self) -> Option<u16> {
+        match *self {
+            Self::Error(e) => Some(e),
+            _ => None,
+        }
+    }
+    /// Returns the value if the server returned a value. Fails like parsing the response into a [`FromValue`] type
+    /// does: error codes are returned as [`Error::ServerError`] and other responses as a
+    /// [`ParseError::ResponseMismatch`]
+    pub fn as_value(&self) -> ClientResult<&Value> {
+        match self {
+            Self::Value(v) => Ok(v),
+            Self::Row(_) | Self::Empty | Self::Rows(_) => {
+                Err(Error::ParseError(ParseError::ResponseMismatch))
+            }
+            Self::Error(e) => Err(Error::ServerError(*e)),
+        }
+    }
+    /// Returns the string if the server returned a string value, failing with the same errors as parsing the response
+    /// into a [`String`]
+    ///
+    /// ```
+    /// use skytable::response::{Response, Value};
+    ///
+    /// let resp = Response::Value(Value::String("sayan".into()));
+    /// // peek at the response first
+    /// assert_eq!(resp.as_str().unwrap(), "sayan");
+    /// // and then take it
+    /// let username: String = resp.parse().unwrap();
+    /// assert_eq!(username, "sayan");
+    /// ```
+    pub fn as_str(&self) -> ClientResult<&str> {
+        self.as_value().and_then(<&str>::try_from)
+    }
+    /// Returns the integer if the server returned an integer value (of any width) that fits in a [`u64`], failing with
+    /// the same errors as parsing the response into a [`u64`]
+    pub fn as_u64(&self) -> ClientResult<u64> {
+        self.as_value().and_then(u64::try_from)
+    }
 }
 
 /*
@@ -229,7 +654,7 @@ impl<V: FromValue> FromValue for Option<V> {
     fn from_value(v: Value) -> ClientResult<Self> {
         match v {
             Value::Null => Ok(None),
-            v => FromValue::from_value(v),
+            v => V::from_value(v).map(Some),
         }
     }
 }
@@ -240,25 +665,35 @@ macro_rules! from_response_direct {
             fn from_value(v: Value) -> ClientResult<Self> {
                 match v {
                     Value::$var(capture) => Ok(From::from(capture)),
-                    _ => Err(Error::ParseError(ParseError::TypeMismatch)),
+                    v => Err(mismatch(&v, stringify!($ty))),
                 }
             }
         })*
     }
 }
 
+/// Borrowing conversions, which fail with the same errors as the [`FromValue`] implementations of the owned types
+macro_rules! try_from_value_ref {
+    ($($ty:ty as $var:ident => $expected:expr),* $(,)?) => {
+        $(impl<'a> TryFrom<&'a Value> for $ty {
+            type Error = Error;
+            fn try_from(v: &'a Value) -> ClientResult<Self> {
+                match v {
+                    Value::$var(capture) => Ok(capture),
+                    v => Err(mismatch(v, $expected)),
+                }
+            }
+        })*
+    };
+}
+
+try_from_value_ref!(
+    &'a str as String => "String",
+    &'a [u8] as Binary => "Vec<u8>",
+    &'a [Value] as List => "Vec<Value>",
+);
+
 from_response_direct!(
-    bool as Bool,
-    u8 as UInt8,
-    u16 as UInt16,
-    u32 as UInt32,
-    u64 as UInt64,
-    i8 as SInt8,
-    i16 as SInt16,
-    i32 as SInt32,
-    i64 as SInt64,
-    f32 as Float32,
-    f64 as Float64,
     Vec<u8> as Binary,
     Box<[u8]> as Binary,
     String as String,
@@ -266,6 +701,141 @@ from_response_direct!(
     Vec<Value> as List,
 );
 
+fn mismatch(v: &Value, expected: impl Into<Cow<'static, str>>) -> Error {
+    Error::ParseError(ParseError::ValueMismatch(Box::new(ValueMismatch {
+        expected: expected.into(),
+        received: v.variant_name(),
+        element: None,
+        value: v.preview(),
+    })))
+}
+
+/// Fails with a [`ParseError::ElementCount`] unless `received == expected` (where `T` is the type that's being parsed)
+fn expect_element_count<T>(received: usize, expected: usize) -> ClientResult<()> {
+    if received == expected {
+        return Ok(());
+    }
+    Err(Error::ParseError(ParseError::ElementCount(Box::new(
+        ElementCount {
+            target: core::any::type_name::<T>(),
+            expected,
+            received,
+        },
+    ))))
+}
+
+/// A path segment (the index at position `segment` of the path) that couldn't be followed
+fn path_error(segment: usize, index: usize, why: fmt::Arguments) -> Error {
+    Error::ParseError(ParseError::Other(format!(
+        "path segment {segment} (index {index}) {why}"
+    )))
+}
+
+/// A path (of `len` segments) that ends at something that isn't a value
+fn path_ends(len: usize, what: &str) -> Error {
+    Error::ParseError(ParseError::Other(format!(
+        "a path of {len} segment(s) leads to {what} instead of a value"
+    )))
+}
+
+fn in_element(e: Error, index: usize) -> Error {
+    match e {
+        Error::ParseError(e) => Error::ParseError(e.in_element(index)),
+        e => e,
+    }
+}
+
+fn out_of_range(value: i128, ty: &str) -> Error {
+    let e = match crate::query::log_redaction() {
+        crate::query::LogRedaction::None => format!("value {value} is out of range for {ty}"),
+        _ => format!("value is out of range for {ty}"),
+    };
+    Error::ParseError(ParseError::Other(e))
+}
+
+/// Integers can be parsed from any integer type (regardless of the width or signedness that the server used), as long
+/// as the value fits in the target type
+macro_rules! from_response_integer {
+    ($($ty:ty),* $(,)?) => {
+        $(impl TryFrom<&Value> for $ty {
+            type Error = Error;
+            fn try_from(v: &Value) -> ClientResult<Self> {
+                let v = v
+                    .integer()
+                    .ok_or_else(|| mismatch(v, stringify!($ty)))?;
+                <$ty>::try_from(v).map_err(|_| out_of_range(v, stringify!($ty)))
+            }
+        }
+        impl FromValue for $ty {
+            fn from_value(v: Value) -> ClientResult<Self> {
+                Self::try_from(&v)
+            }
+        })*
+    };
+}
+
+from_response_integer!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+/// Copy types that are parsed from a single variant, both from a borrowed and an owned [`Value`]
+macro_rules! from_response_copy {
+    ($($ty:ty as $var:ident),* $(,)?) => {
+        $(impl TryFrom<&Value> for $ty {
+            type Error = Error;
+            fn try_from(v: &Value) -> ClientResult<Self> {
+                match *v {
+                    Value::$var(capture) => Ok(capture),
+                    ref v => Err(mismatch(v, stringify!($ty))),
+                }
+            }
+        }
+        impl FromValue for $ty {
+            fn from_value(v: Value) -> ClientResult<Self> {
+                Self::try_from(&v)
+            }
+        })*
+    };
+}
+
+from_response_copy!(bool as Bool, f32 as Float32, f64 as Float64);
+
+/// Binary values of exactly `N` bytes (such as UUIDs stored as 16 bytes)
+impl<const N: usize> FromValue for [u8; N] {
+    fn from_value(v: Value) -> ClientResult<Self> {
+        match v {
+            Value::Binary(b) => <[u8; N]>::try_from(b)
+                .map_err(|b| mismatch(&Value::Binary(b), format!("[u8; {N}]"))),
+            v => Err(mismatch(&v, format!("[u8; {N}]"))),
+        }
+    }
+}
+
+macro_rules! from_response_nonzero {
+    ($($ty:ty as $prim:ty),* $(,)?) => {
+        $(impl FromValue for $ty {
+            fn from_value(v: Value) -> ClientResult<Self> {
+                let v = v
+                    .integer()
+                    .ok_or_else(|| mismatch(&v, stringify!($ty)))?;
+                <$prim>::try_from(v)
+                    .ok()
+                    .and_then(<$ty>::new)
+                    .ok_or_else(|| out_of_range(v, stringify!($ty)))
+            }
+        })*
+    };
+}
+
+from_response_nonzero!(
+    NonZeroU8 as u8,
+    NonZeroU16 as u16,
+    NonZeroU32 as u32,
+    NonZeroU64 as u64,
+    NonZeroI8 as i8,
+    NonZeroI16 as i16,
+    NonZeroI32 as i32,
+    NonZeroI64 as i64,
+);
+
 macro_rules! from_response_row {
     ($(($($elem:ident),*) as $size:literal),* $(,)?) => {
         $(
@@ -276,18 +846,14 @@ macro_rules! from_response_row {
                         Response::Empty | Response::Value(_) | Response::Rows(_) => return Err(Error::ParseError(ParseError::ResponseMismatch)),
                         Response::Error(e) => return Err(Error::ServerError(e)),
                     };
-                    if row.len() != $size {
-                        return Err(Error::ParseError(ParseError::TypeMismatch));
-                    }
+                    expect_element_count::<Self>(row.len(), $size)?;
                     let mut values = row.into_iter();
                     Ok(($($elem::from_value(values.next().unwrap())?),*,))
                 }
             }
             impl<$($elem: FromValue),*> FromRow for ($($elem),*,) {
                 fn from_row(row: Row) -> ClientResult<Self> {
-                    if row.values().len() != $size {
-                        return Err(Error::ParseError(ParseError::TypeMismatch));
-                    }
+                    expect_element_count::<Self>(row.values().len(), $size)?;
                     let mut values = row.into_values().into_iter();
                     Ok(($($elem::from_value(values.next().unwrap())?),*,))
                 }
@@ -441,12 +1007,12 @@ impl<T: FromValue> FromValue for RList<T> {
         match v {
             Value::List(l) => {
                 let mut ret = Vec::new();
-                for value in l {
-                    ret.push(T::from_value(value)?);
+                for (i, value) in l.into_iter().enumerate() {
+                    ret.push(T::from_value(value).map_err(|e| in_element(e, i))?);
                 }
                 Ok(Self(ret))
             }
-            _ => Err(Error::ParseError(ParseError::TypeMismatch)),
+            v => Err(mismatch(&v, "RList")),
         }
     }
 }
@@ -465,3 +1031,476 @@ fn resp_list_parse() {
     assert_eq!(name, "sayan");
     assert_eq!(languages.as_ref(), vec!["c", "assembly", "rust"]);
 }
+
+#[test]
+fn option_values() {
+    assert_eq!(Value::Null.parse::<Option<u64>>().unwrap(), None);
+    assert_eq!(
+        Value::UInt64(100).parse::<Option<u64>>().unwrap(),
+        Some(100)
+    );
+    let (username, email) = Response::Row(Row::new(vec![
+        Value::String("sayan".into()),
+        Value::String("sayan@example.com".into()),
+    ]))
+    .parse::<(String, Option<String>)>()
+    .unwrap();
+    assert_eq!(username, "sayan");
+    assert_eq!(email.as_deref(), Some("sayan@example.com"));
+}
+
+#[test]
+fn row_element_count() {
+    fn count<T: fmt::Debug>(r: ClientResult<T>) -> ElementCount {
+        match r {
+            Err(Error::ParseError(ParseError::ElementCount(e))) => *e,
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+    let row = |n: usize| Row::new((0..n as u64).map(Value::UInt64).collect());
+    // short and long rows, parsed as a response, as a row and as one of several rows
+    for (n, expected) in [
+        (0, 1),
+        (2, 1),
+        (1, 2),
+        (3, 2),
+        (2, 3),
+        (4, 3),
+        (12, 11),
+        (10, 11),
+    ] {
+        let e = match expected {
+            1 => [
+                count(Response::Row(row(n)).parse::<(u64,)>()),
+                count(<(u64,)>::from_row(row(n))),
+                count(Response::Rows(vec![row(1), row(n)]).parse::<Rows<(u64,)>>()),
+            ],
+            2 => [
+                count(Response::Row(row(n)).parse::<(u64, u64)>()),
+                count(<(u64, u64)>::from_row(row(n))),
+                count(Response::Rows(vec![row(2), row(n)]).parse::<Rows<(u64, u64)>>()),
+            ],
+            3 => [
+                count(Response::Row(row(n)).parse::<(u64, u64, String)>()),
+                count(<(u64, u64, String)>::from_row(row(n))),
+                count(Response::Rows(vec![row(n)]).parse::<Rows<(u64, u64, String)>>()),
+            ],
+            _ => [
+                count(
+                    Response::Row(row(n)).parse::<(u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8)>(),
+                ),
+                count(<(u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8)>::from_row(
+                    row(n),
+                )),
+                count(Response::Rows(vec![row(n)]).parse::<Rows<(
+                    u8,
+                    u8,
+                    u8,
+                    u8,
+                    u8,
+                    u8,
+                    u8,
+                    u8,
+                    u8,
+                    u8,
+                    u8,
+                )>>()),
+            ],
+        };
+        for e in e {
+            assert_eq!((e.expected, e.received), (expected, n));
+        }
+    }
+    // the target is only meant for humans (`type_name` output isn't stable), so just check that it's there
+    let e = count(Response::Row(row(1)).parse::<(u64, String)>());
+    assert_eq!((e.expected, e.received), (2, 1));
+    assert!(!e.target.is_empty());
+}
+
+#[test]
+fn ensure_empty() {
+    assert!(Response::Empty.ensure_empty().is_ok());
+    assert!(matches!(
+        Response::Error(5).ensure_empty(),
+        Err(Error::ServerError(5))
+    ));
+    let received = |resp: Response| match resp.ensure_empty() {
+        Err(Error::ParseError(ParseError::Other(e))) => e,
+        r => panic!("unexpected result: {:?}", r),
+    };
+    assert_eq!(
+        received(Response::Value(Value::String("sayan".into()))),
+        "expected an empty response but received a value"
+    );
+    assert_eq!(
+        received(Response::Row(Row::new(vec![Value::UInt8(1), Value::Null]))),
+        "expected an empty response but received a row with 2 column(s)"
+    );
+    assert_eq!(
+        received(Response::Rows(vec![Row::new(vec![]); 3])),
+        "expected an empty response but received 3 row(s)"
+    );
+}
+
+#[test]
+fn integer_conversions() {
+    use std::{
+        fmt,
+        num::{NonZeroU32, NonZeroU64},
+    };
+    fn out_of_range<T: fmt::Debug>(r: ClientResult<T>) -> String {
+        match r {
+            Err(Error::ParseError(ParseError::Other(e))) => e,
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+    // any width and signedness, as long as the value fits
+    assert_eq!(
+        Value::UInt64(u32::MAX as u64).parse::<u32>().unwrap(),
+        u32::MAX
+    );
+    assert_eq!(Value::UInt8(200).parse::<u64>().unwrap(), 200);
+    assert_eq!(Value::SInt64(-128).parse::<i8>().unwrap(), i8::MIN);
+    assert_eq!(Value::UInt16(10).parse::<i64>().unwrap(), 10);
+    assert!(
+        out_of_range(Value::UInt64(u32::MAX as u64 + 1).parse::<u32>())
+            .contains("out of range for u32")
+    );
+    assert!(out_of_range(Value::SInt64(-129).parse::<i8>()).contains("out of range for i8"));
+    assert!(out_of_range(Value::SInt8(-1).parse::<u64>()).contains("out of range for u64"));
+    assert!(out_of_range(Value::UInt64(u64::MAX).parse::<i64>()).contains("out of range for i64"));
+    // non-zero integers
+    assert_eq!(
+        Value::UInt8(5).parse::<NonZeroU64>().unwrap(),
+        NonZeroU64::new(5).unwrap()
+    );
+    assert!(out_of_range(Value::UInt32(0).parse::<NonZeroU32>())
+        .contains("out of range for NonZeroU32"));
+    // other types are still rejected
+    assert!(matches!(
+        Value::Float64(1.0).parse::<u64>(),
+        Err(Error::ParseError(ParseError::ValueMismatch(e)))
+            if e.expected == "u64" && e.received == "Float64"
+    ));
+    // saturating conversions
+    assert_eq!(
+        Value::UInt64(u32::MAX as u64 + 1).to_u32_saturating(),
+        Some(u32::MAX)
+    );
+    assert_eq!(Value::UInt32(u32::MAX).to_u32_saturating(), Some(u32::MAX));
+    assert_eq!(Value::SInt8(-3).to_u64_saturating(), Some(0));
+    assert_eq!(Value::UInt64(u64::MAX).to_i64_saturating(), Some(i64::MAX));
+    assert_eq!(Value::SInt64(i64::MIN).to_i8_saturating(), Some(i8::MIN));
+    assert_eq!(Value::String("1".into()).to_u64_saturating(), None);
+}
+
+#[test]
+fn value_mismatch() {
+    fn mismatch<T: fmt::Debug>(r: ClientResult<T>) -> ValueMismatch {
+        match r {
+            Err(Error::ParseError(ParseError::ValueMismatch(e))) => *e,
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+    // the value is formatted according to the redaction policy and the preview length
+    let _settings = crate::error::FORMAT_SETTINGS.lock().unwrap();
+    // short values are included in full
+    assert_eq!(
+        mismatch(Value::String("sayan".into()).parse::<u64>()),
+        ValueMismatch {
+            expected: "u64".into(),
+            received: "String",
+            element: None,
+            value: "String(\"sayan\")".into(),
+        }
+    );
+    // long values are truncated
+    let e = mismatch(Value::Binary(vec![0; 10 * 1024 * 1024]).parse::<String>());
+    assert_eq!(e.value.len(), value_preview_len() + "...".len());
+    assert!(e.value.starts_with("Binary(<00 00 "));
+    assert!(e.value.ends_with("..."));
+    // the index of the offending element in a list
+    let e = mismatch(
+        Value::List(vec![
+            Value::UInt64(1),
+            Value::List(vec![]),
+            Value::String("3".into()),
+        ])
+        .parse::<RList<u64>>(),
+    );
+    assert_eq!(
+        e,
+        ValueMismatch {
+            expected: "u64".into(),
+            received: "List",
+            element: Some(1),
+            value: "List([])".into(),
+        }
+    );
+    assert_eq!(
+        e.to_string(),
+        "expected u64 but received List in element 1: List([])"
+    );
+}
+
+#[test]
+fn into_pairs() {
+    fn mismatch<T: fmt::Debug>(r: ClientResult<T>) -> ValueMismatch {
+        match r {
+            Err(Error::ParseError(ParseError::ValueMismatch(e))) => *e,
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+    let pairs = Value::List(vec![
+        Value::String("sayan".into()),
+        Value::UInt64(100),
+        Value::String("elana".into()),
+        Value::UInt8(20),
+    ]);
+    assert_eq!(
+        pairs.clone().into_pairs::<u64>().unwrap(),
+        vec![("sayan".to_owned(), 100), ("elana".to_owned(), 20)]
+    );
+    assert_eq!(Value::List(vec![]).into_pairs::<u64>().unwrap(), vec![]);
+    // bad values and keys are reported with their index
+    let e = mismatch(pairs.into_pairs::<bool>());
+    assert_eq!(
+        (e.expected, e.received, e.element),
+        ("bool".into(), "UInt64", Some(1))
+    );
+    let e = mismatch(
+        Value::List(vec![
+            Value::String("sayan".into()),
+            Value::UInt64(100),
+            Value::Null,
+            Value::UInt64(1),
+        ])
+        .into_pairs::<u64>(),
+    );
+    assert_eq!(
+        (e.expected, e.received, e.element),
+        ("String".into(), "Null", Some(2))
+    );
+    // odd lengths and other types
+    assert!(matches!(
+        Value::List(vec![Value::String("sayan".into())]).into_pairs::<u64>(),
+        Err(Error::ParseError(ParseError::Other(e))) if e.contains("1 element(s)")
+    ));
+    assert_eq!(
+        mismatch(Value::UInt64(1).into_pairs::<u64>()).expected,
+        "key/value pairs"
+    );
+}
+
+#[test]
+fn binary_keys() {
+    fn mismatch<T: fmt::Debug>(r: ClientResult<T>) -> ValueMismatch {
+        match r {
+            Err(Error::ParseError(ParseError::ValueMismatch(e))) => *e,
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+    let uuid = [0xa5, 0, b'\n', 0xff, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+    // binary and string keys
+    let pairs = Value::List(vec![
+        Value::Binary(uuid.to_vec()),
+        Value::UInt64(100),
+        Value::String("sayan".into()),
+        Value::UInt64(200),
+    ]);
+    assert_eq!(
+        pairs.into_binary_pairs::<u64>().unwrap(),
+        vec![(uuid.to_vec(), 100), (b"sayan".to_vec(), 200)]
+    );
+    let e = mismatch(
+        Value::List(vec![Value::UInt64(1), Value::UInt64(100)]).into_binary_pairs::<u64>(),
+    );
+    assert_eq!(
+        (e.expected, e.received, e.element),
+        ("binary or string key".into(), "UInt64", Some(0))
+    );
+    // binary keys are strings for `into_pairs`, and the error has them in hex
+    let e = mismatch(
+        Value::List(vec![Value::Binary(uuid.to_vec()), Value::UInt64(100)]).into_pairs::<u64>(),
+    );
+    assert_eq!(
+        e.value,
+        "Binary(<a5 00 0a ff 01 02 03 04 05 06 07 08 09 0a 0b 0c>)"
+    );
+    assert_eq!(
+        mismatch(Value::List(vec![Value::Binary(vec![0xca, 0xfe]), Value::Null]).parse::<u64>())
+            .value,
+        "List([Binary(<ca fe>), Null])"
+    );
+    // fixed-size keys
+    assert_eq!(
+        Value::Binary(uuid.to_vec()).parse::<[u8; 16]>().unwrap(),
+        uuid
+    );
+    let e = mismatch(Value::Binary(vec![1, 2, 3]).parse::<[u8; 16]>());
+    assert_eq!(
+        (e.expected, e.received, e.value),
+        ("[u8; 16]".into(), "Binary", "Binary(<01 02 03>)".into())
+    );
+    assert_eq!(
+        mismatch(Value::String("sayan".into()).parse::<[u8; 16]>()).expected,
+        "[u8; 16]"
+    );
+}
+
+#[test]
+fn borrowing_conversions() {
+    /// Borrowing and owned conversions fail the same way
+    fn same_error<T: FromResponse + fmt::Debug>(
+        resp: Response,
+        borrowed: impl FnOnce(&Response) -> Option<Error>,
+    ) {
+        let borrowed = borrowed(&resp).unwrap();
+        let owned = resp.parse::<T>().unwrap_err();
+        assert_eq!(format!("{borrowed:?}"), format!("{owned:?}"));
+    }
+    // borrow, then consume
+    let resp = Response::Value(Value::String("sayan".into()));
+    assert_eq!(resp.as_str().unwrap(), "sayan");
+    assert_eq!(resp.as_error(), None);
+    assert!(resp.check_empty().is_err());
+    assert_eq!(resp.parse::<String>().unwrap(), "sayan");
+    let resp = Response::Value(Value::UInt8(100));
+    assert_eq!(resp.as_u64().unwrap(), 100);
+    assert_eq!(resp.parse::<u64>().unwrap(), 100);
+    let resp = Response::Empty;
+    resp.check_empty().unwrap();
+    resp.ensure_empty().unwrap();
+    let resp = Response::Error(111);
+    assert_eq!(resp.as_error(), Some(111));
+    assert!(matches!(resp.check_empty(), Err(Error::ServerError(111))));
+    assert!(matches!(resp.as_str(), Err(Error::ServerError(111))));
+    assert!(matches!(
+        resp.parse::<String>(),
+        Err(Error::ServerError(111))
+    ));
+    // values
+    let v = Value::List(vec![Value::Bool(true), Value::Binary(vec![0xca, 0xfe])]);
+    let l = <&[Value]>::try_from(&v).unwrap();
+    assert!(bool::try_from(&l[0]).unwrap());
+    assert_eq!(<&[u8]>::try_from(&l[1]).unwrap(), [0xca, 0xfe]);
+    assert_eq!(i8::try_from(&Value::SInt64(-1)).unwrap(), -1);
+    assert_eq!(f64::try_from(&Value::Float64(1.5)).unwrap(), 1.5);
+    assert_eq!(v.parse::<Vec<Value>>().unwrap().len(), 2);
+    // errors
+    same_error::<String>(Response::Value(Value::UInt8(1)), |r| r.as_str().err());
+    same_error::<String>(Response::Empty, |r| r.as_str().err());
+    same_error::<u64>(Response::Value(Value::SInt8(-1)), |r| r.as_u64().err());
+    same_error::<u64>(Response::Value(Value::String("100".into())), |r| {
+        r.as_u64().err()
+    });
+    same_error::<u64>(Response::Rows(vec![]), |r| r.as_u64().err());
+    let resp = Response::Value(Value::Null);
+    assert_eq!(
+        format!("{:?}", resp.check_empty()),
+        format!("{:?}", resp.ensure_empty())
+    );
+}
+
+#[test]
+fn paths() {
+    // three levels: rows, columns and nested lists
+    let list = Value::List(vec![
+        Value::UInt8(1),
+        Value::List(vec![Value::String("a".into()), Value::Null]),
+    ]);
+    let row = Row::new(vec![Value::String("sayan".into()), list.clone()]);
+    let rows = Response::Rows(vec![row.clone(), Row::new(vec![Value::Bool(true)])]);
+    let a = Value::String("a".into());
+    // found
+    assert_eq!(rows.get_path(&[0, 1, 1, 0]), Some(&a));
+    assert_eq!(crate::value_path!(rows, 0, 1, 1, 0), Some(&a));
+    assert_eq!(rows.try_path(&[0, 1, 1, 0]).unwrap(), &a);
+    assert_eq!(rows.get_path(&[1, 0]), Some(&Value::Bool(true)));
+    let resp = Response::Row(row);
+    assert_eq!(resp.get_path(&[1, 1, 0]), Some(&a));
+    assert_eq!(resp.try_path(&[1, 1, 0]).unwrap(), &a);
+    assert_eq!(resp.get(1), Some(&list));
+    assert_eq!(list.get_path(&[1, 0]), Some(&a));
+    assert_eq!(list.get_path(&[]), Some(&list));
+    let resp = Response::Value(list.clone());
+    assert_eq!(resp.get(0), Some(&Value::UInt8(1)));
+    assert_eq!(resp.try_path(&[1, 1]).unwrap(), &Value::Null);
+    assert_eq!(resp.try_path(&[]).unwrap(), &list);
+    assert_eq!(rows.get(0), None);
+    // not found
+    let error = |r: ClientResult<&Value>| match r {
+        Err(Error::ParseError(ParseError::Other(e))) => e,
+        r => panic!("unexpected result: {:?}", r),
+    };
+    for (resp, path, e) in [
+        (
+            &rows,
+            &[2, 0][..],
+            "path segment 0 (index 2) is out of range for 2 row(s)",
+        ),
+        (
+            &rows,
+            &[0, 2],
+            "path segment 1 (index 2) is out of range for a row of 2 column(s)",
+        ),
+        (
+            &rows,
+            &[0, 1, 2],
+            "path segment 2 (index 2) is out of range for a list of 2 element(s)",
+        ),
+        (
+            &rows,
+            &[0, 1, 1, 2],
+            "path segment 3 (index 2) is out of range for a list of 2 element(s)",
+        ),
+        (
+            &rows,
+            &[0, 1, 1, 0, 0],
+            "path segment 4 (index 0) can't be used on a String value",
+        ),
+        (
+            &rows,
+            &[0, 0, 0],
+            "path segment 2 (index 0) can't be used on a String value",
+        ),
+        (
+            &rows,
+            &[],
+            "a path of 0 segment(s) leads to a list of rows instead of a value",
+        ),
+        (
+            &rows,
+            &[1],
+            "a path of 1 segment(s) leads to a row instead of a value",
+        ),
+        (
+            &resp,
+            &[0, 0],
+            "path segment 1 (index 0) can't be used on a UInt8 value",
+        ),
+        (
+            &resp,
+            &[5],
+            "path segment 0 (index 5) is out of range for a list of 2 element(s)",
+        ),
+        (
+            &Response::Empty,
+            &[0],
+            "path segment 0 (index 0) can't be used on an empty response",
+        ),
+        (
+            &Response::Empty,
+            &[],
+            "a path of 0 segment(s) leads to an empty response instead of a value",
+        ),
+    ] {
+        assert_eq!(resp.get_path(path), None, "{:?}", path);
+        assert_eq!(error(resp.try_path(path)), e, "{:?}", path);
+    }
+    assert!(matches!(
+        Response::Error(5).try_path(&[0]),
+        Err(Error::ServerError(5))
+    ));
+    assert_eq!(Response::Error(5).get_path(&[0]), None);
+}
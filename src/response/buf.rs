@@ -0,0 +1,397 @@
+/*
+ * Copyright 2024, Sayan Nandan <nandansayan@outlook.com>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+*/
+
+//! # Reusable response buffers
+//!
+//! Every [`Response`] owns its strings, binaries, lists and rows, so decoding a response allocates. Applications that
+//! run a lot of queries can instead decode every response into the same [`ResponseBuf`] with
+//! [`query_into`](crate::syncio::TcpConnection::query_into): the buffer is cleared and refilled for every response,
+//! and keeps the memory that it allocated for earlier responses. Once the buffer has grown to fit the largest
+//! response, decoding a response doesn't allocate at all.
+//!
+//! The response is read through borrowed views ([`ResponseRef`], [`ValueRef`], [`ValuesRef`] and [`RowsRef`]) that
+//! mirror [`Response`] and [`Value`], and can be copied into an owned [`Response`] with
+//! [`to_response`](ResponseBuf::to_response).
+//!
+//! ## Example
+//!
+//! ```no_run
+//! use skytable::{
+//!     query,
+//!     response::buf::{ResponseBuf, ResponseRef, ValueRef},
+//!     Config,
+//! };
+//!
+//! let mut db = Config::new_default("username", "password").connect().unwrap();
+//! let mut buf = ResponseBuf::new();
+//! for user in ["sayan", "elana"] {
+//!     let q = query!("select followers from myspace.mymodel where username = ?", user);
+//!     db.query_into(&q, &mut buf).unwrap();
+//!     if let ResponseRef::Row(row) = buf.response() {
+//!         if let Some(ValueRef::UInt64(followers)) = row.iter().next() {
+//!             println!("{user} has {followers} followers");
+//!         }
+//!     }
+//! }
+//! ```
+
+use {
+    super::{Response, Row, Value},
+    crate::protocol::FlatState,
+    alloc::{borrow::ToOwned, vec::Vec},
+    core::fmt,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum Kind {
+    #[default]
+    Empty,
+    Value,
+    Row {
+        columns: usize,
+    },
+    Rows {
+        columns: usize,
+    },
+    Error(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A decoded value. Values are stored in the order they were received, and a list is followed by its elements
+pub(crate) enum Slot {
+    Null,
+    Bool(bool),
+    UInt8(u8),
+    UInt16(u16),
+    UInt32(u32),
+    UInt64(u64),
+    SInt8(i8),
+    SInt16(i16),
+    SInt32(i32),
+    SInt64(i64),
+    Float32(f32),
+    Float64(f64),
+    /// the start and end of the bytes in [`ResponseBuf::data`]
+    Binary(usize, usize),
+    /// the start and end of the (validated) string in [`ResponseBuf::data`]
+    String(usize, usize),
+    /// the number of elements and the slot that follows the last (nested) element
+    List {
+        len: usize,
+        end: usize,
+    },
+}
+
+#[derive(Default)]
+/// A response buffer that is reused for many responses (see the [module documentation](self))
+pub struct ResponseBuf {
+    pub(crate) kind: Kind,
+    pub(crate) slots: Vec<Slot>,
+    /// the bytes of every string and binary value
+    pub(crate) data: Vec<u8>,
+    /// the first slot of every row of a multi-row response
+    pub(crate) rows: Vec<usize>,
+    pub(crate) state: FlatState,
+}
+
+impl ResponseBuf {
+    /// Create an empty buffer (which holds an empty response)
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Clear the buffer (so that it holds an empty response), keeping the allocated memory
+    pub fn clear(&mut self) {
+        self.kind = Kind::Empty;
+        self.slots.clear();
+        self.data.clear();
+        self.rows.clear();
+        self.state.clear();
+    }
+    /// Returns the response in this buffer
+    pub fn response(&self) -> ResponseRef<'_> {
+        match self.kind {
+            Kind::Empty => ResponseRef::Empty,
+            Kind::Value => ResponseRef::Value(self.value(0)),
+            Kind::Row { columns } => ResponseRef::Row(ValuesRef {
+                buf: self,
+                start: 0,
+                len: columns,
+            }),
+            Kind::Rows { columns } => ResponseRef::Rows(RowsRef { buf: self, columns }),
+            Kind::Error(code) => ResponseRef::Error(code),
+        }
+    }
+    /// Copy the response in this buffer into a [`Response`]
+    pub fn to_response(&self) -> Response {
+        self.response().to_response()
+    }
+    fn value(&self, slot: usize) -> ValueRef<'_> {
+        match self.slots[slot] {
+            Slot::Null => ValueRef::Null,
+            Slot::Bool(v) => ValueRef::Bool(v),
+            Slot::UInt8(v) => ValueRef::UInt8(v),
+            Slot::UInt16(v) => ValueRef::UInt16(v),
+            Slot::UInt32(v) => ValueRef::UInt32(v),
+            Slot::UInt64(v) => ValueRef::UInt64(v),
+            Slot::SInt8(v) => ValueRef::SInt8(v),
+            Slot::SInt16(v) => ValueRef::SInt16(v),
+            Slot::SInt32(v) => ValueRef::SInt32(v),
+            Slot::SInt64(v) => ValueRef::SInt64(v),
+            Slot::Float32(v) => ValueRef::Float32(v),
+            Slot::Float64(v) => ValueRef::Float64(v),
+            Slot::Binary(start, end) => ValueRef::Binary(&self.data[start..end]),
+            Slot::String(start, end) => {
+                // strings are validated when they are decoded
+                ValueRef::String(unsafe { core::str::from_utf8_unchecked(&self.data[start..end]) })
+            }
+            Slot::List { len, .. } => ValueRef::List(ValuesRef {
+                buf: self,
+                start: slot + 1,
+                len,
+            }),
+        }
+    }
+    /// Returns the slot that follows `slot` and its nested elements
+    fn skip(&self, slot: usize) -> usize {
+        match self.slots[slot] {
+            Slot::List { end, .. } => end,
+            _ => slot + 1,
+        }
+    }
+}
+
+impl fmt::Debug for ResponseBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ResponseBuf")
+            .field(&self.response())
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A response in a [`ResponseBuf`] (see [`Response`])
+pub enum ResponseRef<'a> {
+    /// The server returned an empty response
+    Empty,
+    /// The server returned a value
+    Value(ValueRef<'a>),
+    /// The server returned a row
+    Row(ValuesRef<'a>),
+    /// A list of rows
+    Rows(RowsRef<'a>),
+    /// The server returned an error code
+    Error(u16),
+}
+
+impl<'a> ResponseRef<'a> {
+    /// Copy this response into a [`Response`]
+    pub fn to_response(&self) -> Response {
+        match self {
+            Self::Empty => Response::Empty,
+            Self::Value(v) => Response::Value(v.to_value()),
+            Self::Row(row) => Response::Row(Row::new(row.to_values())),
+            Self::Rows(rows) => {
+                Response::Rows(rows.iter().map(|row| Row::new(row.to_values())).collect())
+            }
+            Self::Error(code) => Response::Error(*code),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A value in a [`ResponseBuf`] (see [`Value`])
+pub enum ValueRef<'a> {
+    /// A null value
+    Null,
+    /// A [`bool`]
+    Bool(bool),
+    /// An [`u8`]
+    UInt8(u8),
+    /// An [`u16`]
+    UInt16(u16),
+    /// An [`u32`]
+    UInt32(u32),
+    /// An [`u64`]
+    UInt64(u64),
+    /// An [`i8`]
+    SInt8(i8),
+    /// An [`i16`]
+    SInt16(i16),
+    /// An [`i32`]
+    SInt32(i32),
+    /// An [`i64`]
+    SInt64(i64),
+    /// A [`f32`]
+    Float32(f32),
+    /// A [`f64`]
+    Float64(f64),
+    /// A binary value
+    Binary(&'a [u8]),
+    /// A string
+    String(&'a str),
+    /// A list
+    List(ValuesRef<'a>),
+}
+
+impl<'a> ValueRef<'a> {
+    /// Copy this value into a [`Value`]
+    pub fn to_value(&self) -> Value {
+        match *self {
+            Self::Null => Value::Null,
+            Self::Bool(v) => Value::Bool(v),
+            Self::UInt8(v) => Value::UInt8(v),
+            Self::UInt16(v) => Value::UInt16(v),
+            Self::UInt32(v) => Value::UInt32(v),
+            Self::UInt64(v) => Value::UInt64(v),
+            Self::SInt8(v) => Value::SInt8(v),
+            Self::SInt16(v) => Value::SInt16(v),
+            Self::SInt32(v) => Value::SInt32(v),
+            Self::SInt64(v) => Value::SInt64(v),
+            Self::Float32(v) => Value::Float32(v),
+            Self::Float64(v) => Value::Float64(v),
+            Self::Binary(v) => Value::Binary(v.to_owned()),
+            Self::String(v) => Value::String(v.to_owned()),
+            Self::List(l) => Value::List(l.to_values()),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+/// The values of a row or a list in a [`ResponseBuf`]
+pub struct ValuesRef<'a> {
+    buf: &'a ResponseBuf,
+    start: usize,
+    len: usize,
+}
+
+impl<'a> ValuesRef<'a> {
+    /// Returns the number of values
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Returns true if there are no values
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Returns an iterator over the values
+    pub fn iter(&self) -> ValuesIter<'a> {
+        ValuesIter {
+            buf: self.buf,
+            slot: self.start,
+            remaining: self.len,
+        }
+    }
+    /// Copy the values into a vector of [`Value`]s
+    pub fn to_values(&self) -> Vec<Value> {
+        self.iter().map(|v| v.to_value()).collect()
+    }
+}
+
+impl<'a> IntoIterator for ValuesRef<'a> {
+    type Item = ValueRef<'a>;
+    type IntoIter = ValuesIter<'a>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> fmt::Debug for ValuesRef<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, 'b> PartialEq<ValuesRef<'b>> for ValuesRef<'a> {
+    fn eq(&self, other: &ValuesRef<'b>) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+#[derive(Debug, Clone)]
+/// An iterator over the values of a row or a list in a [`ResponseBuf`]
+pub struct ValuesIter<'a> {
+    buf: &'a ResponseBuf,
+    slot: usize,
+    remaining: usize,
+}
+
+impl<'a> Iterator for ValuesIter<'a> {
+    type Item = ValueRef<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let value = self.buf.value(self.slot);
+        self.slot = self.buf.skip(self.slot);
+        Some(value)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for ValuesIter<'a> {}
+
+#[derive(Clone, Copy)]
+/// The rows of a multi-row response in a [`ResponseBuf`]
+pub struct RowsRef<'a> {
+    buf: &'a ResponseBuf,
+    columns: usize,
+}
+
+impl<'a> RowsRef<'a> {
+    /// Returns the number of rows
+    pub fn len(&self) -> usize {
+        self.buf.rows.len()
+    }
+    /// Returns true if there are no rows
+    pub fn is_empty(&self) -> bool {
+        self.buf.rows.is_empty()
+    }
+    /// Returns the row at `index`, if there is one
+    pub fn get(&self, index: usize) -> Option<ValuesRef<'a>> {
+        let buf = self.buf;
+        let columns = self.columns;
+        buf.rows.get(index).map(|&start| ValuesRef {
+            buf,
+            start,
+            len: columns,
+        })
+    }
+    /// Returns an iterator over the rows
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = ValuesRef<'a>> + 'a {
+        let buf = self.buf;
+        let columns = self.columns;
+        buf.rows.iter().map(move |&start| ValuesRef {
+            buf,
+            start,
+            len: columns,
+        })
+    }
+}
+
+impl<'a> fmt::Debug for RowsRef<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, 'b> PartialEq<RowsRef<'b>> for RowsRef<'a> {
+    fn eq(&self, other: &RowsRef<'b>) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
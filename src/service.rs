@@ -0,0 +1,347 @@
+/*
+ * Copyright 2024, Sayan Nandan <nandansayan@outlook.com>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+*/
+
+//! # `tower` services
+//!
+//! This module provides [`tower::Service`](tower_service::Service) implementations that run a [`Query`] and return
+//! its [`Response`], so that queries can be wrapped with `tower` middleware (such as timeouts, retries and concurrency
+//! limits). It requires the `tower` feature.
+//!
+//! - [`ConnectionService`] runs queries on a single connection. Clones share the connection, and run their queries
+//!   one after the other
+//! - [`PoolService`] checks out a connection from a [`bb8`] pool for every query
+//!
+//! Both services reserve what they need in `poll_ready`: the service isn't ready until the connection is free (or a
+//! pooled connection has been checked out), and a broken connection is reported as an error. The query runs on the
+//! reserved connection when the service is called.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! use {
+//!     skytable::{query, service::ConnectionService, Config},
+//!     std::time::Duration,
+//!     tower::{Service, ServiceBuilder, ServiceExt},
+//! };
+//!
+//! async fn run() -> Result<(), tower::BoxError> {
+//!     let con = Config::new_default("username", "password").connect_async().await?;
+//!     let mut db = ServiceBuilder::new()
+//!         .timeout(Duration::from_secs(1))
+//!         .service(ConnectionService::new(con));
+//!     let response = db
+//!         .ready()
+//!         .await?
+//!         .call(query!("sysctl report status"))
+//!         .await?;
+//!     Ok(())
+//! }
+//! ```
+
+use {
+    crate::{
+        aio::TcpConnection,
+        error::{ClientResult, Error},
+        query::Query,
+        response::Response,
+    },
+    core::{
+        fmt,
+        future::Future,
+        ops::DerefMut,
+        pin::Pin,
+        task::{Context, Poll},
+    },
+    std::{io, sync::Arc},
+    tokio::{
+        io::{AsyncRead, AsyncWrite},
+        sync::{Mutex, OwnedMutexGuard},
+    },
+    tower_service::Service,
+};
+
+/// The future returned by the services in this module
+pub type ResponseFuture = Pin<Box<dyn Future<Output = ClientResult<Response>> + Send>>;
+
+type Pending<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+type Checkout<M> = Pending<
+    Result<bb8::PooledConnection<'static, M>, bb8::RunError<<M as bb8::ManageConnection>::Error>>,
+>;
+
+fn broken() -> Error {
+    Error::IoError(io::Error::new(
+        io::ErrorKind::NotConnected,
+        "the connection is broken",
+    ))
+}
+
+fn not_ready() -> ResponseFuture {
+    Box::pin(async {
+        Err(Error::IoError(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the service was called before it was ready",
+        )))
+    })
+}
+
+/// A [`Service`] that runs queries on a single connection
+///
+/// `C` can be any of the async connection types (or any other type that dereferences to an
+/// [`aio::TcpConnection`](TcpConnection), like a connection checked out from a pool).
+///
+/// Cloning the service shares the connection: queries from different clones wait for each other (a clone is ready
+/// when the connection is free). Once the connection is broken (for example, because a timeout dropped a query before
+/// its response was received), `poll_ready` fails with a [`NotConnected`](io::ErrorKind::NotConnected) I/O error, and
+/// a new service with a new connection is needed.
+pub struct ConnectionService<C> {
+    con: Arc<Mutex<C>>,
+    lock: Option<Pending<OwnedMutexGuard<C>>>,
+    guard: Option<OwnedMutexGuard<C>>,
+}
+
+impl<C> ConnectionService<C> {
+    /// Create a service that runs queries on `con`
+    pub fn new(con: C) -> Self {
+        Self {
+            con: Arc::new(Mutex::new(con)),
+            lock: None,
+            guard: None,
+        }
+    }
+}
+
+impl<C> Clone for ConnectionService<C> {
+    fn clone(&self) -> Self {
+        Self {
+            con: self.con.clone(),
+            lock: None,
+            guard: None,
+        }
+    }
+}
+
+impl<C> fmt::Debug for ConnectionService<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionService")
+            .field("ready", &self.guard.is_some())
+            .finish()
+    }
+}
+
+impl<C, S> Service<Query> for ConnectionService<C>
+where
+    C: DerefMut<Target = TcpConnection<S>> + Send + 'static,
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    type Response = Response;
+    type Error = Error;
+    type Future = ResponseFuture;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<ClientResult<()>> {
+        if self.guard.is_none() {
+            let con = self.con.clone();
+            let lock = self.lock.get_or_insert_with(|| Box::pin(con.lock_owned()));
+            let guard = match lock.as_mut().poll(cx) {
+                Poll::Ready(guard) => guard,
+                Poll::Pending => return Poll::Pending,
+            };
+            self.lock = None;
+            if guard.is_broken() {
+                return Poll::Ready(Err(broken()));
+            }
+            self.guard = Some(guard);
+        }
+        Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, q: Query) -> ResponseFuture {
+        match self.guard.take() {
+            Some(mut con) => Box::pin(async move { con.query(&q).await }),
+            None => not_ready(),
+        }
+    }
+}
+
+/// A [`Service`] that runs every query on a connection checked out from a [`bb8`] pool (see
+/// [`get_async`](crate::pool::get_async))
+///
+/// The service is ready once it has checked out a connection, so it stays pending while every connection is in use
+/// and `poll_ready` fails if a connection can't be established (or if the pool's connection timeout expires, with a
+/// [`TimedOut`](io::ErrorKind::TimedOut) I/O error). The connection is returned to the pool when the query completes.
+///
+/// Cloning the service shares the pool.
+pub struct PoolService<M: bb8::ManageConnection> {
+    pool: bb8::Pool<M>,
+    checkout: Option<Checkout<M>>,
+    con: Option<bb8::PooledConnection<'static, M>>,
+}
+
+impl<M: bb8::ManageConnection> PoolService<M> {
+    /// Create a service that runs queries on connections from `pool`
+    pub fn new(pool: bb8::Pool<M>) -> Self {
+        Self {
+            pool,
+            checkout: None,
+            con: None,
+        }
+    }
+    /// Returns the pool that this service checks out connections from
+    pub fn pool(&self) -> &bb8::Pool<M> {
+        &self.pool
+    }
+}
+
+impl<M: bb8::ManageConnection> Clone for PoolService<M> {
+    fn clone(&self) -> Self {
+        Self::new(self.pool.clone())
+    }
+}
+
+impl<M: bb8::ManageConnection> fmt::Debug for PoolService<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolService")
+            .field("pool", &self.pool)
+            .field("ready", &self.con.is_some())
+            .finish()
+    }
+}
+
+impl<M, S> Service<Query> for PoolService<M>
+where
+    M: bb8::ManageConnection<Error = Error>,
+    M::Connection: DerefMut<Target = TcpConnection<S>>,
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    type Response = Response;
+    type Error = Error;
+    type Future = ResponseFuture;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<ClientResult<()>> {
+        if self.con.is_none() {
+            let pool = self.pool.clone();
+            let checkout = self
+                .checkout
+                .get_or_insert_with(|| Box::pin(async move { pool.get_owned().await }));
+            let r = match checkout.as_mut().poll(cx) {
+                Poll::Ready(r) => r,
+                Poll::Pending => return Poll::Pending,
+            };
+            self.checkout = None;
+            match r {
+                Ok(con) => self.con = Some(con),
+                Err(bb8::RunError::User(e)) => return Poll::Ready(Err(e)),
+                Err(bb8::RunError::TimedOut) => {
+                    return Poll::Ready(Err(Error::IoError(io::ErrorKind::TimedOut.into())))
+                }
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, q: Query) -> ResponseFuture {
+        match self.con.take() {
+            Some(mut con) => Box::pin(async move { con.query(&q).await }),
+            None => not_ready(),
+        }
+    }
+}
+
+#[test]
+fn connection_service_timeout() {
+    use {
+        crate::{pool::mock_server, response::Value, Config},
+        std::time::Duration,
+        tower::{timeout::error::Elapsed, ServiceBuilder, ServiceExt},
+    };
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let q = query!(
+            "select followers from myspace.mymodel where username = ?",
+            "sayan"
+        );
+        let con = Config::new(
+            "127.0.0.1",
+            mock_server(b"\x05100\n"),
+            "username",
+            "password",
+        )
+        .connect_async()
+        .await
+        .unwrap();
+        let mut db = ServiceBuilder::new()
+            .timeout(Duration::from_secs(5))
+            .service(ConnectionService::new(con));
+        assert_eq!(
+            db.ready().await.unwrap().call(q.clone()).await.unwrap(),
+            Response::Value(Value::UInt64(100))
+        );
+        // this server never answers, so the query times out and the connection can't be used again
+        let con = Config::new("127.0.0.1", mock_server(b""), "username", "password")
+            .connect_async()
+            .await
+            .unwrap();
+        let mut db = ServiceBuilder::new()
+            .timeout(Duration::from_millis(100))
+            .service(ConnectionService::new(con));
+        let e = db.ready().await.unwrap().call(q).await.unwrap_err();
+        assert!(e.is::<Elapsed>());
+        let e = db.ready().await.unwrap_err();
+        assert!(matches!(
+            e.downcast_ref::<Error>(),
+            Some(Error::IoError(e)) if e.kind() == io::ErrorKind::NotConnected
+        ));
+    })
+}
+
+#[test]
+fn pool_service_readiness() {
+    use {
+        crate::{pool::mock_server, Config},
+        std::time::Duration,
+        tower::ServiceExt,
+    };
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let cfg = Config::new("127.0.0.1", mock_server(b"\x12"), "username", "password");
+        let pool = crate::pool::get_async(1, cfg).await.unwrap();
+        let mut first = PoolService::new(pool);
+        let mut second = first.clone();
+        first.ready().await.unwrap();
+        // the pool checks connections with a query when they're checked out, so the server responds with an empty
+        // response; the only connection is reserved by the first service
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), second.ready())
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            first
+                .call(query!(
+                    "select followers from myspace.mymodel where username = ?",
+                    "sayan"
+                ))
+                .await
+                .unwrap(),
+            Response::Empty
+        );
+        // and it's back in the pool once the query completes
+        assert_eq!(
+            second
+                .ready()
+                .await
+                .unwrap()
+                .call(query!("sysctl report status"))
+                .await
+                .unwrap(),
+            Response::Empty
+        );
+        assert_eq!(first.pool().state().connections, 1);
+    })
+}
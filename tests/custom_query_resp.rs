@@ -35,7 +35,7 @@ impl BookmarkUser {
     }
 }
 
-impl<'a> SQParam for &'a BookmarkUser {
+impl SQParam for &BookmarkUser {
     fn append_param(&self, q: &mut Vec<u8>) -> usize {
         self.username.append_param(q)
             + self.password.append_param(q)
@@ -25,3 +25,35 @@ fn test_params() {
     );
     assert_eq!(q.param_cnt(), 3);
 }
+
+#[test]
+fn test_response_element_count() {
+    use skytable::{
+        error::{Error, ParseError},
+        response::{self, FromRow, Row, Rows, Value},
+    };
+    let row = |n: usize| {
+        Row::from(
+            (0..n)
+                .map(|i| Value::String(i.to_string()))
+                .collect::<Vec<_>>(),
+        )
+    };
+    for n in [2, 4] {
+        let errors = [
+            response::Response::Row(row(n)).parse::<User>().map(drop),
+            User::from_row(row(n)).map(drop),
+            response::Response::Rows(vec![row(3), row(n)])
+                .parse::<Rows<User>>()
+                .map(drop),
+        ];
+        for e in errors {
+            match e {
+                Err(Error::ParseError(ParseError::ElementCount(e))) => {
+                    assert_eq!((e.expected, e.received), (3, n))
+                }
+                r => panic!("unexpected result: {:?}", r),
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Checks that string and number parameters are written into the query buffer without intermediate allocations
+
+use {
+    skytable::{query, query::SQParam, Query},
+    std::{
+        alloc::{GlobalAlloc, Layout, System},
+        borrow::Cow,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    },
+};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const KEY: &str = "a fairly long key that doesn't fit in a small buffer";
+
+/// Returns the query built by `f` along with the number of allocations made while building it
+fn count_allocations(f: &dyn Fn() -> Query) -> (Query, usize) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let q = f();
+    (q, ALLOCATIONS.load(Ordering::Relaxed) - before)
+}
+
+// this is the only test in this binary, so that other tests can't allocate while we're counting
+#[test]
+fn params_do_not_allocate() {
+    let owned = KEY.to_owned();
+    let cow_borrowed: Cow<'static, str> = Cow::Borrowed(KEY);
+    let cow_owned: Cow<'static, str> = Cow::Owned(KEY.to_owned());
+    let boxed: Box<str> = KEY.into();
+    let arc: Arc<str> = KEY.into();
+    // the only allocations are made by the query buffer itself
+    let (expected, baseline) = count_allocations(&|| query!("select * from m where k = ?", KEY));
+    let check = |description: &str, f: &dyn Fn() -> Query| {
+        let (q, allocations) = count_allocations(f);
+        assert_eq!(
+            q.debug_encode_packet(),
+            expected.debug_encode_packet(),
+            "{}",
+            description
+        );
+        assert_eq!(allocations, baseline, "{}", description);
+    };
+    check("&String", &|| query!("select * from m where k = ?", &owned));
+    check("Cow::Borrowed", &|| {
+        query!("select * from m where k = ?", cow_borrowed.clone())
+    });
+    check("&Cow::Owned", &|| {
+        query!("select * from m where k = ?", &cow_owned)
+    });
+    check("&Box<str>", &|| {
+        query!("select * from m where k = ?", &boxed)
+    });
+    check("&Arc<str>", &|| query!("select * from m where k = ?", &arc));
+    // numbers are formatted straight into the buffer
+    let mut buf = Vec::with_capacity(1024);
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    u64::MAX.append_param(&mut buf);
+    i64::MIN.append_param(&mut buf);
+    usize::MAX.append_param(&mut buf);
+    (-0.25f32).append_param(&mut buf);
+    f64::MIN_POSITIVE.append_param(&mut buf);
+    assert_eq!(ALLOCATIONS.load(Ordering::Relaxed), before);
+}
@@ -10,3 +10,16 @@ fn compile_add_queries() {
     pipeline.extend(vec![&query]);
     assert_eq!(pipeline.query_count(), 124);
 }
+
+#[test]
+fn is_empty_and_clear() {
+    let mut pipeline: Pipeline = (0..3u64)
+        .map(|num| query!("select * from mymodel where id = ?", num))
+        .collect();
+    assert!(!pipeline.is_empty());
+    pipeline.clear();
+    assert!(pipeline.is_empty());
+    assert_eq!(pipeline.query_count(), 0);
+    pipeline.push(&query!("use $current"));
+    assert_eq!(pipeline.query_count(), 1);
+}
@@ -13,3 +13,242 @@ fn param_cnt() {
     assert_eq!(q.param_cnt(), 2);
     assert_eq!(q.query_str(), "insert into myspace.mymodel(?, ?)");
 }
+
+#[test]
+fn encode_packet() {
+    // [S][total packet size]\n[query window]\n[query][params]; params are length-prefixed so nothing is escaped
+    let q = query!(
+        "insert into myspace.mymodel(?, ?, ?, ?)",
+        "sa\nan",
+        100u64,
+        None::<u8>,
+        true
+    );
+    assert_eq!(
+        q.debug_encode_packet(),
+        b"S58\n39\ninsert into myspace.mymodel(?, ?, ?, ?)\x065\nsa\nan\x02100\n\x00\x01\x01"
+    );
+}
+
+#[test]
+fn with_capacity_encodes_the_same() {
+    use skytable::Query;
+    let mut q = Query::with_capacity("insert into myspace.mymodel(?, ?, ?, ?)", 4, 11);
+    q.push_param("sa\nan")
+        .push_param(100u64)
+        .push_param(None::<u8>)
+        .push_param(true);
+    assert_eq!(
+        q.debug_encode_packet(),
+        b"S58\n39\ninsert into myspace.mymodel(?, ?, ?, ?)\x065\nsa\nan\x02100\n\x00\x01\x01"
+    );
+    // the hints don't have to be right
+    let keys: Vec<String> = (0..1000).map(|i| "k".repeat(i % 150)).collect();
+    let mut expected = Query::new("insert into myspace.mymodel(?)");
+    for key in &keys {
+        expected.push_param(key).push_param(key.as_bytes());
+    }
+    for (params, bytes) in [(0, 0), (10, 10), (2000, 1000), (5000, 500_000)] {
+        let mut q = Query::with_capacity("insert into myspace.mymodel(?)", params, bytes);
+        for key in &keys {
+            q.push_param(key).push_param(key.as_bytes());
+        }
+        assert_eq!(q, expected);
+        assert_eq!(q.debug_encode_packet(), expected.debug_encode_packet());
+    }
+}
+
+#[test]
+fn params() {
+    use skytable::query::QList;
+    let q = query!(
+        "insert into myspace.mymodel(?, ?, ?, ?, ?, ?, ?, ?)",
+        "sayan",
+        b"\n\x00bin".as_slice(),
+        100u64,
+        -100i64,
+        1.5f64,
+        None::<u8>,
+        true,
+        QList::new(&["a", "b"])
+    );
+    let params: Vec<&[u8]> = q.params().collect();
+    assert_eq!(
+        params,
+        [
+            b"sayan".as_slice(),
+            b"\n\x00bin",
+            b"100",
+            b"-100",
+            b"1.5",
+            b"",
+            b"\x01",
+            b"\x061\na\x061\nb",
+        ]
+    );
+    assert_eq!(params.len(), q.param_cnt());
+    assert_eq!(q.first_arg(), Some(b"sayan".as_slice()));
+    let q = query!("sysctl report status");
+    assert_eq!(q.params().count(), 0);
+    assert_eq!(q.first_arg(), None);
+}
+
+#[test]
+fn equal_queries_dedup() {
+    use {
+        skytable::{query::QList, Query},
+        std::collections::HashSet,
+    };
+    const Q: &str = "insert into myspace.mymodel(?, ?, ?)";
+    let key = *b"\xca\xfe\xba\xbe";
+    let mut queries = HashSet::new();
+    // the same parameters, built in different ways
+    assert!(queries.insert(query!(Q, &key[..], "sayan", 100u64)));
+    assert!(!queries.insert(query!(Q, key.to_vec(), String::from("sayan"), 100u8)));
+    assert!(!queries.insert(query!(Q, key, &String::from("sayan"), 100u32)));
+    let mut q = Query::with_capacity(Q, 3, 64);
+    q.push_param(key).push_param("sayan").push_param(100u16);
+    assert!(!queries.insert(q));
+    assert!(!queries.insert(query!(String::from(Q), &key, "sayan", 100u64)));
+    assert_eq!(queries.len(), 1);
+    // while different parameters (or types, or query strings) are different queries
+    assert!(queries.insert(query!(Q, &key[..], "sayan", 101u64)));
+    assert!(queries.insert(query!(Q, &key[..], "sayan", 100i64)));
+    assert!(queries.insert(query!(Q, &key[..], &b"sayan"[..], 100u64)));
+    assert!(queries.insert(query!(
+        "insert into myspace.mymodel(?, ?,?)",
+        &key[..],
+        "sayan",
+        100u64
+    )));
+    // the query string and the parameters are separate: moving bytes from one to the other changes the query
+    assert!(queries.insert(query!("select ?", "a")));
+    assert!(queries.insert(query!("select ?a")));
+    assert!(queries.insert(query!("x", QList::new(&["a", "b"]))));
+    assert!(!queries.insert(query!(
+        "x",
+        QList::new(&[String::from("a"), String::from("b")])
+    )));
+    assert_eq!(queries.len(), 8);
+}
+
+#[test]
+fn param_limit() {
+    use skytable::{
+        error::LimitError,
+        query::{self, SQParam},
+        Query,
+    };
+    /// A parameter that counts as two
+    struct Pair;
+    impl SQParam for Pair {
+        fn append_param(&self, buf: &mut Vec<u8>) -> usize {
+            1u64.append_param(buf) + 2u64.append_param(buf)
+        }
+    }
+    let mut q = Query::new("insert into myspace.mymodel(?, ?, ?)");
+    assert_eq!(q.param_limit(), query::DEFAULT_PARAM_LIMIT);
+    q.set_param_limit(3);
+    q.try_push_param(1u64)
+        .unwrap()
+        .try_push_param(1u64)
+        .unwrap();
+    // a parameter that would go over the limit is left out
+    let packet = q.debug_encode_packet();
+    assert_eq!(q.try_push_param(Pair).unwrap_err(), LimitError::Params(3));
+    assert_eq!(q.debug_encode_packet(), packet);
+    // up to the limit
+    q.try_push_param(1u64).unwrap();
+    assert_eq!(q.param_cnt(), 3);
+    // but not over it
+    let e = q.try_push_param(1u64).unwrap_err();
+    assert_eq!(e, LimitError::Params(3));
+    assert_eq!(e.to_string(), "a query can't have more than 3 parameters");
+    assert_eq!(q.param_cnt(), 3);
+    assert_eq!(q.params().count(), 3);
+    // the limit is per query
+    let mut other = Query::new("insert into myspace.mymodel(?, ?, ?)");
+    other
+        .push_param(1u64)
+        .push_param(1u64)
+        .push_param(1u64)
+        .push_param(1u64);
+    assert_eq!(other.param_cnt(), 4);
+    // and isn't part of the comparison
+    q.set_param_limit(4);
+    assert_eq!(q.push_param(1u64).param_cnt(), 4);
+    other.set_param_limit(5);
+    assert_eq!(q, other);
+}
+
+#[test]
+fn param_limit_default() {
+    use skytable::{error::LimitError, query, Query};
+    let mut q = Query::new("select * from myspace.mymodel where username in ?");
+    for _ in 0..query::DEFAULT_PARAM_LIMIT {
+        q.push_param(1u64);
+    }
+    assert_eq!(
+        q.try_push_param(1u64).unwrap_err(),
+        LimitError::Params(query::DEFAULT_PARAM_LIMIT)
+    );
+}
+
+#[test]
+#[should_panic(expected = "a query can't have more than 2 parameters")]
+fn param_limit_push_panics() {
+    let mut q = skytable::Query::new("insert into myspace.mymodel(?, ?)");
+    q.set_param_limit(2);
+    q.push_param(1u64).push_param(1u64).push_param(1u64);
+}
+
+#[test]
+fn pipeline_limit() {
+    use skytable::{error::LimitError, query as q, Pipeline};
+    let query = query!("sysctl report status");
+    let mut pipeline = Pipeline::new();
+    assert_eq!(pipeline.query_limit(), q::DEFAULT_PIPELINE_LIMIT);
+    pipeline.set_query_limit(3);
+    pipeline.try_push(&query).unwrap();
+    pipeline.try_push(&query).unwrap();
+    pipeline.try_push(&query).unwrap();
+    assert_eq!(pipeline.query_count(), 3);
+    let e = pipeline.try_push(&query).unwrap_err();
+    assert_eq!(e, LimitError::Queries(3));
+    assert_eq!(e.to_string(), "a pipeline can't have more than 3 queries");
+    assert_eq!(pipeline.query_count(), 3);
+    // the limit is per pipeline
+    let pipeline: Pipeline = (0..5).map(|_| &query).collect();
+    assert_eq!(pipeline.query_count(), 5);
+    let mut pipeline: Pipeline = (0..q::DEFAULT_PIPELINE_LIMIT).map(|_| &query).collect();
+    assert_eq!(
+        pipeline.try_push(&query).unwrap_err(),
+        LimitError::Queries(q::DEFAULT_PIPELINE_LIMIT)
+    );
+}
+
+#[test]
+#[should_panic(expected = "a pipeline can't have more than 1 queries")]
+fn pipeline_limit_push_panics() {
+    let mut pipeline = skytable::Pipeline::new();
+    pipeline.set_query_limit(1);
+    pipeline.push(&query!("sysctl report status"));
+    pipeline.push(&query!("sysctl report status"));
+}
+
+#[test]
+#[cfg(any(
+    feature = "sync",
+    feature = "aio",
+    feature = "async-std",
+    feature = "alloc-proto"
+))]
+fn public_test_vectors() {
+    // the same vectors that the encoder and decoder are tested against, as seen by a mock server
+    for (description, q, expected) in skytable::test_vectors::query_vectors() {
+        assert_eq!(q.debug_encode_packet(), expected, "{}", description);
+    }
+    for (description, pipeline, _, _, responses) in skytable::test_vectors::pipeline_vectors() {
+        assert_eq!(pipeline.query_count(), responses.len(), "{}", description);
+    }
+}
@@ -0,0 +1,334 @@
+//! End-to-end tests against a running Skytable server.
+//!
+//! These only run if `SKYTABLE_TEST_HOST` is set to the address of the server (`host` or `host:port`), along with
+//! `SKYTABLE_TEST_PASSWORD` (and `SKYTABLE_TEST_USER`, which defaults to `root`). Otherwise every test is skipped
+//! with a message. For example:
+//!
+//! ```text
+//! SKYTABLE_TEST_HOST=127.0.0.1:2003 SKYTABLE_TEST_PASSWORD=password12345678 cargo test --test server
+//! ```
+//!
+//! Every test gives up after [`TEST_TIMEOUT`] instead of waiting forever for a server that doesn't respond. The tests
+//! create their own space (named after the test and the process) and drop it when they finish.
+
+use {
+    skytable::{
+        error::{ConnectionSetupError, Error},
+        pipe, query,
+        query::QList,
+        response::{Response, Row, Value},
+        Config,
+    },
+    std::{
+        env,
+        io::{self, Read, Write},
+        net::{Shutdown, TcpListener, TcpStream},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc, Arc, Mutex,
+        },
+        thread,
+        time::Duration,
+    },
+};
+
+const HOST_ENV: &str = "SKYTABLE_TEST_HOST";
+const USER_ENV: &str = "SKYTABLE_TEST_USER";
+const PASSWORD_ENV: &str = "SKYTABLE_TEST_PASSWORD";
+/// The maximum time that a test can take
+const TEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// The maximum time that a single query can take
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Returns the configuration for the test server, or `None` (after saying so) if it isn't set
+fn server(test: &str) -> Option<Config> {
+    let host = match env::var(HOST_ENV) {
+        Ok(host) => host,
+        Err(_) => {
+            eprintln!("skipping {test}: {HOST_ENV} is not set");
+            return None;
+        }
+    };
+    let (host, port) = match host.rsplit_once(':') {
+        Some((host, port)) => (host.to_owned(), port.parse().expect("invalid port")),
+        None => (host, skytable::config::DEFAULT_TCP_PORT),
+    };
+    let user = env::var(USER_ENV).unwrap_or_else(|_| "root".into());
+    let password = env::var(PASSWORD_ENV).unwrap_or_else(|_| {
+        panic!(
+            "{} is set, so {} must be set as well",
+            HOST_ENV, PASSWORD_ENV
+        )
+    });
+    Some(Config::new(&host, port, &user, &password))
+}
+
+/// Run `test` with the server's configuration, failing if it takes longer than [`TEST_TIMEOUT`]
+fn run(test: &'static str, f: impl FnOnce(Config) + Send + 'static) {
+    let cfg = match server(test) {
+        Some(cfg) => cfg,
+        None => return,
+    };
+    let (tx, rx) = mpsc::channel();
+    let handle = thread::spawn(move || {
+        f(cfg);
+        let _ = tx.send(());
+    });
+    match rx.recv_timeout(TEST_TIMEOUT) {
+        Ok(()) => handle.join().unwrap(),
+        // the test panicked
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            if let Err(e) = handle.join() {
+                std::panic::resume_unwind(e)
+            }
+        }
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            panic!("{} timed out after {:?}", test, TEST_TIMEOUT)
+        }
+    }
+}
+
+/// A space (with a model) that is dropped when the test finishes
+struct Space {
+    cfg: Config,
+    name: String,
+}
+
+impl Space {
+    fn create(cfg: &Config, test: &str) -> Self {
+        let name = format!("clienttest_{}_{}", test, std::process::id());
+        let mut db = cfg.connect().unwrap();
+        db.query_parse::<()>(&query!(format!("create space {name}")))
+            .unwrap();
+        db.query_parse::<()>(&query!(format!(
+            "create model {name}.users(username: string, followers: uint64, tags: list {{ type: string }})"
+        )))
+        .unwrap();
+        Self {
+            cfg: cfg.clone(),
+            name,
+        }
+    }
+    fn model(&self) -> String {
+        format!("{}.users", self.name)
+    }
+}
+
+impl Drop for Space {
+    fn drop(&mut self) {
+        if let Ok(mut db) = self.cfg.connect() {
+            let _ = db.query(&query!(format!("drop space allow not empty {}", self.name)));
+        }
+    }
+}
+
+#[test]
+fn connect_and_query() {
+    run("connect_and_query", |cfg| {
+        let mut db = cfg.connect().unwrap();
+        db.set_timeout(Some(QUERY_TIMEOUT));
+        db.query_parse::<()>(&query!("sysctl report status"))
+            .unwrap();
+        assert!(db.info().is_some());
+    })
+}
+
+#[test]
+fn multi_element_responses() {
+    run("multi_element_responses", |cfg| {
+        let space = Space::create(&cfg, "multi");
+        let model = space.model();
+        let mut db = cfg.connect().unwrap();
+        db.set_timeout(Some(QUERY_TIMEOUT));
+        for (username, followers) in [("sayan", 100u64), ("elana", 200)] {
+            db.query_parse::<()>(&query!(
+                format!("insert into {model}(?, ?, ?)"),
+                username,
+                followers,
+                QList::new(&["a", "b"])
+            ))
+            .unwrap();
+        }
+        // a row
+        assert_eq!(
+            db.query(&query!(
+                format!("select * from {model} where username = ?"),
+                "sayan"
+            ))
+            .unwrap(),
+            Response::Row(Row::from(vec![
+                Value::String("sayan".into()),
+                Value::UInt64(100),
+                Value::List(vec![Value::String("a".into()), Value::String("b".into())]),
+            ]))
+        );
+        // and rows
+        let mut rows: Vec<(String, u64)> = db
+            .query_parse::<skytable::response::Rows<(String, u64)>>(&query!(
+                format!("select all username, followers from {model} limit ?"),
+                10u64
+            ))
+            .unwrap()
+            .into_rows();
+        rows.sort();
+        assert_eq!(rows, [("elana".into(), 200), ("sayan".into(), 100)]);
+    })
+}
+
+#[test]
+fn pipeline() {
+    run("pipeline", |cfg| {
+        let space = Space::create(&cfg, "pipeline");
+        let model = space.model();
+        let mut db = cfg.connect().unwrap();
+        db.set_timeout(Some(QUERY_TIMEOUT));
+        let responses = db
+            .execute_pipeline(&pipe!(
+                query!(
+                    format!("insert into {model}(?, ?, ?)"),
+                    "sayan",
+                    100u64,
+                    QList::new(&["a"])
+                ),
+                query!(
+                    format!("select followers from {model} where username = ?"),
+                    "sayan"
+                ),
+                query!(
+                    format!("select followers from {model} where username = ?"),
+                    "nobody"
+                )
+            ))
+            .unwrap();
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0], Response::Empty);
+        assert_eq!(
+            responses[1],
+            Response::Row(Row::from(vec![Value::UInt64(100)]))
+        );
+        // a query that fails doesn't fail the pipeline
+        assert!(matches!(responses[2], Response::Error(_)));
+    })
+}
+
+#[test]
+fn reconnect_after_restart() {
+    run("reconnect_after_restart", |cfg| {
+        let mut proxy = Proxy::start(&cfg);
+        let mut db = Config::new("127.0.0.1", proxy.port, cfg.username(), cfg.password())
+            .connect()
+            .unwrap();
+        db.set_timeout(Some(QUERY_TIMEOUT));
+        let q = query!("sysctl report status");
+        db.query(&q).unwrap();
+        // the server goes away: the query fails, and so does the reconnect
+        proxy.kill();
+        assert!(db.query(&q).is_err());
+        assert!(db.query(&q).is_err());
+        // until it's back
+        proxy.reopen();
+        let (resp, meta) = db.query_meta(&q).unwrap();
+        assert_eq!(resp, Response::Empty);
+        assert!(meta.reconnected);
+        proxy.kill();
+    })
+}
+
+#[test]
+fn auth_failure() {
+    run("auth_failure", |cfg| {
+        let (user, password) = (
+            cfg.username().to_owned(),
+            format!("{}-wrong", cfg.password()),
+        );
+        let r = cfg.with_credentials(&user, &password).connect();
+        assert!(
+            matches!(
+                r,
+                Err(Error::ConnectionSetupErr(
+                    ConnectionSetupError::HandshakeError(_)
+                ))
+            ),
+            "{:?}",
+            r.map(drop)
+        );
+    })
+}
+
+/// A TCP proxy to the server that can be killed (closing the listener and every connection) and reopened on the same
+/// port, as if the server was restarted
+struct Proxy {
+    port: u16,
+    upstream: (String, u16),
+    killed: Arc<AtomicBool>,
+    connections: Arc<Mutex<Vec<TcpStream>>>,
+    acceptor: Option<thread::JoinHandle<()>>,
+}
+
+impl Proxy {
+    fn start(cfg: &Config) -> Self {
+        let mut proxy = Self {
+            port: 0,
+            upstream: (cfg.host().to_owned(), cfg.port()),
+            killed: Arc::new(AtomicBool::new(false)),
+            connections: Arc::new(Mutex::new(vec![])),
+            acceptor: None,
+        };
+        proxy.reopen();
+        proxy
+    }
+    fn reopen(&mut self) {
+        let listener = TcpListener::bind(("127.0.0.1", self.port)).unwrap();
+        self.port = listener.local_addr().unwrap().port();
+        self.killed.store(false, Ordering::SeqCst);
+        let (upstream, killed, connections) = (
+            self.upstream.clone(),
+            self.killed.clone(),
+            self.connections.clone(),
+        );
+        self.acceptor = Some(thread::spawn(move || {
+            for client in listener.incoming() {
+                if killed.load(Ordering::SeqCst) {
+                    break;
+                }
+                let client = client.unwrap();
+                let server = TcpStream::connect((upstream.0.as_str(), upstream.1)).unwrap();
+                let mut tracked = connections.lock().unwrap();
+                tracked.push(client.try_clone().unwrap());
+                tracked.push(server.try_clone().unwrap());
+                forward(client.try_clone().unwrap(), server.try_clone().unwrap());
+                forward(server, client);
+            }
+        }));
+    }
+    fn kill(&mut self) {
+        self.killed.store(true, Ordering::SeqCst);
+        // wake the acceptor up so that it closes the listener
+        let _ = TcpStream::connect(("127.0.0.1", self.port));
+        if let Some(acceptor) = self.acceptor.take() {
+            acceptor.join().unwrap();
+        }
+        for con in self.connections.lock().unwrap().drain(..) {
+            let _ = con.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Copy everything from `from` to `to` (until either is closed)
+fn forward(mut from: TcpStream, mut to: TcpStream) {
+    thread::spawn(move || {
+        let mut buf = [0; 4096];
+        loop {
+            match from.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if to.write_all(&buf[..n]).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        let _ = to.shutdown(Shutdown::Both);
+        let _: io::Result<()> = from.shutdown(Shutdown::Both);
+    });
+}